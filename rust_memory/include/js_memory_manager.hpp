@@ -1,211 +1,295 @@
-#pragma once
-
-#include <functional>
-#include <memory>
-#include <stdexcept>
-#include <string>
-#include <vector>
-
-// Forward declarations for Rust types
-extern "C" {
-typedef void *RustGCHandle;
-typedef void *RustObjectHandle;
-
-// These enums match their Rust counterparts
-enum JSObjectType { Object = 0, Array = 1, Function = 2, String = 3, Number = 4, Boolean = 5, Null = 6, Undefined = 7 };
-
-// Configuration struct for the GC
-struct GCConfiguration {
-    size_t young_gen_threshold_kb;
-    size_t old_gen_threshold_kb;
-    uint64_t max_pause_ms;
-    bool incremental;
-    bool verbose;
-};
-
-// Statistics returned from the GC
-struct GCStatistics {
-    size_t allocation_count;
-    size_t collection_count;
-    size_t objects_freed;
-    size_t young_generation_size;
-    size_t old_generation_size;
-};
-
-// FFI functions
-RustGCHandle js_memory_init();
-void js_memory_shutdown(RustGCHandle gc);
-void js_gc_configure(RustGCHandle gc, const GCConfiguration *config);
-void js_gc_collect(RustGCHandle gc);
-void js_gc_add_root(RustGCHandle gc, RustObjectHandle obj);
-void js_gc_remove_root(RustGCHandle gc, RustObjectHandle obj);
-GCStatistics js_gc_get_stats(RustGCHandle gc);
-
-RustObjectHandle js_create_object(RustGCHandle gc, int obj_type);
-void js_release_object(RustObjectHandle obj);
-
-int js_set_property_string(RustObjectHandle obj, const char *key, const char *value);
-int js_set_property_number(RustObjectHandle obj, const char *key, double value);
-int js_set_property_boolean(RustObjectHandle obj, const char *key, int value);
-int js_set_property_object(RustObjectHandle obj, const char *key, RustObjectHandle value);
-
-int js_get_property_string(RustObjectHandle obj, const char *key, char *buffer, size_t buffer_size);
-int js_get_property_number(RustObjectHandle obj, const char *key, double *out_value);
-int js_get_property_boolean(RustObjectHandle obj, const char *key, int *out_value);
-int js_get_property_object(RustObjectHandle obj, const char *key, RustObjectHandle *out_value);
-
-int js_set_finalizer(RustObjectHandle obj, void (*finalizer)(RustObjectHandle));
-int js_get_object_type(RustObjectHandle obj);
-}
-
-namespace js_memory {
-
-    // Forward declarations
-    class MemoryManager;
-    class JSObjectWrapper;
-
-    /**
-     * RAII wrapper for JavaScript values to ensure proper memory management
-     */
-    class JSValue {
-    public:
-        enum class Type { Undefined, Null, Boolean, Number, String, Object };
-
-        JSValue() : type_(Type::Undefined) {}
-        JSValue(std::nullptr_t) : type_(Type::Null) {}
-        JSValue(bool value) : type_(Type::Boolean), bool_val_(value) {}
-        JSValue(double value) : type_(Type::Number), num_val_(value) {}
-        JSValue(int value) : type_(Type::Number), num_val_(static_cast<double>(value)) {}
-        JSValue(const std::string &value) : type_(Type::String), str_val_(value) {}
-        JSValue(std::shared_ptr<JSObjectWrapper> obj) : type_(Type::Object), obj_val_(obj) {}
-
-        Type type() const { return type_; }
-        bool is_undefined() const { return type_ == Type::Undefined; }
-        bool is_null() const { return type_ == Type::Null; }
-        bool is_boolean() const { return type_ == Type::Boolean; }
-        bool is_number() const { return type_ == Type::Number; }
-        bool is_string() const { return type_ == Type::String; }
-        bool is_object() const { return type_ == Type::Object; }
-
-        bool as_boolean() const;
-        double as_number() const;
-        std::string as_string() const;
-        std::shared_ptr<JSObjectWrapper> as_object() const;
-
-    private:
-        Type type_;
-        bool bool_val_ = false;
-        double num_val_ = 0.0;
-        std::string str_val_;
-        std::shared_ptr<JSObjectWrapper> obj_val_;
-    };
-
-    /**
-     * Safe C++ wrapper for Rust JSObject
-     */
-    class JSObjectWrapper {
-    public:
-        JSObjectWrapper(RustObjectHandle handle);
-        ~JSObjectWrapper();
-
-        // Prevent accidental copies
-        JSObjectWrapper(const JSObjectWrapper &) = delete;
-        JSObjectWrapper &operator=(const JSObjectWrapper &) = delete;
-
-        // Allow move semantics
-        JSObjectWrapper(JSObjectWrapper &&other) noexcept;
-        JSObjectWrapper &operator=(JSObjectWrapper &&other) noexcept;
-
-        // Property access
-        void set_property(const std::string &key, const JSValue &value);
-        JSValue get_property(const std::string &key) const;
-
-        // Type information
-        JSObjectType type() const;
-
-        // Set finalizer callback
-        void set_finalizer(std::function<void(RustObjectHandle)> finalizer);
-
-        // Access the underlying handle (for advanced usage)
-        RustObjectHandle handle() const { return handle_; }
-
-    private:
-        RustObjectHandle handle_;
-        static void finalize_wrapper(RustObjectHandle handle);
-        static std::unordered_map<RustObjectHandle, std::function<void(RustObjectHandle)>> finalizers_;
-    };
-
-    /**
-     * Garbage collector for JavaScript objects
-     */
-    class GarbageCollector {
-    public:
-        GarbageCollector(RustGCHandle handle);
-        ~GarbageCollector();
-
-        // Prevent copies
-        GarbageCollector(const GarbageCollector &) = delete;
-        GarbageCollector &operator=(const GarbageCollector &) = delete;
-
-        // Configure the GC
-        void configure(size_t young_gen_threshold_kb, size_t old_gen_threshold_kb, uint64_t max_pause_ms,
-                       bool incremental, bool verbose);
-
-        // Force collection
-        void collect();
-
-        // Root management
-        void add_root(std::shared_ptr<JSObjectWrapper> obj);
-        void remove_root(std::shared_ptr<JSObjectWrapper> obj);
-
-        // Get statistics
-        GCStatistics statistics() const;
-
-        // Access the underlying handle (for advanced usage)
-        RustGCHandle handle() const { return handle_; }
-
-    private:
-        RustGCHandle handle_;
-    };
-
-    /**
-     * Main memory manager for JavaScript compiler
-     */
-    class MemoryManager {
-    public:
-        // Get the singleton instance
-        static MemoryManager &instance();
-
-        // Initialize and shutdown
-        void initialize();
-        void shutdown();
-
-        // Object creation
-        std::shared_ptr<JSObjectWrapper> create_object(JSObjectType type);
-
-        // Configure the garbage collector
-        void configure_gc(size_t young_gen_threshold_kb, size_t old_gen_threshold_kb, uint64_t max_pause_ms,
-                          bool incremental, bool verbose);
-
-        // Manually trigger garbage collection
-        void collect();
-
-        // Get statistics
-        GCStatistics gc_statistics() const;
-
-        // Get the GC
-        std::shared_ptr<GarbageCollector> gc() const { return gc_; }
-
-    private:
-        MemoryManager() = default;
-        ~MemoryManager() = default;
-
-        // Prevent copies
-        MemoryManager(const MemoryManager &) = delete;
-        MemoryManager &operator=(const MemoryManager &) = delete;
-
-        bool initialized_ = false;
-        std::shared_ptr<GarbageCollector> gc_;
-    };
-
-} // namespace js_memory
+#pragma once
+
+#include <functional>
+#include <memory>
+#include <stdexcept>
+#include <string>
+#include <vector>
+
+// Forward declarations for Rust types
+extern "C" {
+typedef void *RustGCHandle;
+typedef void *RustObjectHandle;
+typedef void *RustWeakRefHandle;
+
+// These enums match their Rust counterparts
+enum JSObjectType { Object = 0, Array = 1, Function = 2, String = 3, Number = 4, Boolean = 5, Null = 6, Undefined = 7 };
+enum class JSValueTag : int { Undefined = 0, Null = 1, Boolean = 2, Number = 3, String = 4, Object = 5, BigInt = 6, Symbol = 7 };
+// Returned by js_set_property_value; Ok is 0, unlike the typed setters which return 1 on success
+enum class JSSetStatus : int { Ok = 0, InvalidHandle = 1, InvalidArgument = 2, Frozen = 3, NotExtensible = 4 };
+
+// Value passed to batch setters; tag is a JSValueTag and selects the payload member
+union JSValuePayload {
+    double number;
+    int boolean;
+    const char *string;
+    RustObjectHandle object;
+};
+
+struct JSValueFFI {
+    int tag;
+    JSValuePayload payload;
+};
+
+// Configuration struct for the GC
+struct GCConfiguration {
+    size_t young_gen_threshold_kb;
+    size_t old_gen_threshold_kb;
+    uint64_t max_pause_ms;
+    bool incremental;  // collect() runs one max_pause_ms slice; off by default
+    bool verbose;
+    uint8_t log_level;
+    uint64_t thrash_window_ms;
+    size_t thrash_min_freed_percent;
+    size_t max_young_gen_threshold_kb;
+    bool deferred_finalization;
+    uint8_t promotion_age;
+    double heap_growth_factor;
+    size_t min_young_gen_threshold_kb;
+    size_t max_heap_kb;
+    uint32_t deferred_free_generations;
+    double compact_threshold_ratio;
+};
+
+// Statistics returned from the GC
+struct GCStatistics {
+    size_t allocation_count;
+    size_t collection_count;
+    size_t objects_freed;
+    size_t young_generation_size;
+    size_t old_generation_size;
+    size_t threshold_raises;
+    size_t write_barrier_hits;
+    size_t remembered_set_size;
+    size_t weak_entries_pruned;
+    uint64_t total_pause_ns;
+    uint64_t max_pause_ns;
+    uint64_t last_collection_ns;
+};
+
+// Process-wide shape counts
+struct ShapeStats {
+    size_t live_shapes;
+    uint64_t transition_hits;
+    uint64_t transition_misses;
+};
+
+// FFI functions
+RustGCHandle js_memory_init();
+void js_memory_shutdown(RustGCHandle gc);
+void js_gc_configure(RustGCHandle gc, const GCConfiguration *config);
+void js_gc_collect(RustGCHandle gc);
+void js_gc_begin_shutdown(RustGCHandle gc);
+void js_gc_add_root(RustGCHandle gc, RustObjectHandle obj);
+void js_gc_remove_root(RustGCHandle gc, RustObjectHandle obj);
+GCStatistics js_gc_get_stats(RustGCHandle gc);
+int js_gc_pause_percentiles_json(RustGCHandle gc, char *buffer, size_t buffer_size);
+size_t js_gc_pending_finalizers(RustGCHandle gc);
+size_t js_gc_run_finalizers(RustGCHandle gc, size_t max);
+int js_gc_set_oom_handler(RustGCHandle gc, int (*handler)(void));
+int js_gc_set_promotion_callback(RustGCHandle gc, void (*callback)(RustObjectHandle obj, uint32_t age));
+int js_gc_register_weak(RustGCHandle gc, RustObjectHandle obj, void (*callback)(void *user), void *user);
+
+RustObjectHandle js_create_object(RustGCHandle gc, int obj_type);
+void js_release_object(RustObjectHandle obj);
+RustWeakRefHandle js_create_weak_ref(RustObjectHandle obj);
+RustObjectHandle js_weak_ref_deref(RustWeakRefHandle weak);
+void js_release_weak_ref(RustWeakRefHandle weak);
+
+int js_set_property_string(RustObjectHandle obj, const char *key, const char *value);
+int js_set_property_number(RustObjectHandle obj, const char *key, double value);
+int js_set_property_boolean(RustObjectHandle obj, const char *key, int value);
+int js_set_property_object(RustObjectHandle obj, const char *key, RustObjectHandle value);
+JSSetStatus js_set_property_value(RustObjectHandle obj, const char *key, const JSValueFFI *value);
+JSSetStatus js_set_properties_batch(RustObjectHandle obj, const char *const *keys, const JSValueFFI *values, size_t count, size_t *out_written);
+
+int js_get_property_string(RustObjectHandle obj, const char *key, char *buffer, size_t buffer_size);
+int js_get_property_number(RustObjectHandle obj, const char *key, double *out_value);
+int js_get_property_boolean(RustObjectHandle obj, const char *key, int *out_value);
+int js_get_property_object(RustObjectHandle obj, const char *key, RustObjectHandle *out_value);
+int js_get_property_string_with_proto(RustObjectHandle obj, const char *key, char *buffer, size_t buffer_size);
+int js_get_property_number_with_proto(RustObjectHandle obj, const char *key, double *out_value);
+int js_get_property_boolean_with_proto(RustObjectHandle obj, const char *key, int *out_value);
+int js_get_property_object_with_proto(RustObjectHandle obj, const char *key, RustObjectHandle *out_value);
+uint64_t js_object_id(RustObjectHandle obj);
+size_t js_get_property_count(RustObjectHandle obj);
+int js_get_property_name_at(RustObjectHandle obj, size_t index, char *buffer, size_t buffer_size);
+int js_has_own_property(RustObjectHandle obj, const char *key);
+int js_has_property(RustObjectHandle obj, const char *key);
+int js_delete_property(RustObjectHandle obj, const char *key);
+int js_freeze_shape(RustObjectHandle obj);
+// After js_freeze_object, the typed setters, js_delete_property and js_array_push
+// return 0, js_set_property_value returns JSSetStatus::Frozen and js_set_prototype -1
+int js_freeze_object(RustObjectHandle obj);
+int js_is_frozen(RustObjectHandle obj);
+int js_set_prototype(RustObjectHandle obj, RustObjectHandle proto);
+int js_set_shape_change_callback(RustObjectHandle obj, void (*callback)(RustObjectHandle obj, size_t old_shape_id, size_t new_shape_id));
+int js_get_prototype(RustObjectHandle obj, RustObjectHandle *out_proto);
+int js_get_value_tag(RustObjectHandle obj, const char *key);
+const char *js_value_type_of(RustObjectHandle obj, const char *key);
+
+typedef void (*JSEntryCallback)(const char *key, int tag, void *user_data);
+size_t js_object_entries(RustObjectHandle obj, JSEntryCallback callback, void *user_data);
+
+size_t js_array_push(RustObjectHandle obj, double value);
+
+int js_set_finalizer(RustObjectHandle obj, void (*finalizer)(RustObjectHandle));
+int js_get_object_type(RustObjectHandle obj);
+int js_set_object_type(RustObjectHandle obj, int type);
+int js_value_equals(RustObjectHandle a, RustObjectHandle b);
+
+size_t js_intern_sweep(void);
+ShapeStats js_get_shape_stats(void);
+}
+
+namespace js_memory {
+
+    // Forward declarations
+    class MemoryManager;
+    class JSObjectWrapper;
+
+    /**
+     * RAII wrapper for JavaScript values to ensure proper memory management
+     */
+    class JSValue {
+    public:
+        enum class Type { Undefined, Null, Boolean, Number, String, Object };
+
+        JSValue() : type_(Type::Undefined) {}
+        JSValue(std::nullptr_t) : type_(Type::Null) {}
+        JSValue(bool value) : type_(Type::Boolean), bool_val_(value) {}
+        JSValue(double value) : type_(Type::Number), num_val_(value) {}
+        JSValue(int value) : type_(Type::Number), num_val_(static_cast<double>(value)) {}
+        JSValue(const std::string &value) : type_(Type::String), str_val_(value) {}
+        JSValue(std::shared_ptr<JSObjectWrapper> obj) : type_(Type::Object), obj_val_(obj) {}
+
+        Type type() const { return type_; }
+        bool is_undefined() const { return type_ == Type::Undefined; }
+        bool is_null() const { return type_ == Type::Null; }
+        bool is_boolean() const { return type_ == Type::Boolean; }
+        bool is_number() const { return type_ == Type::Number; }
+        bool is_string() const { return type_ == Type::String; }
+        bool is_object() const { return type_ == Type::Object; }
+
+        bool as_boolean() const;
+        double as_number() const;
+        std::string as_string() const;
+        std::shared_ptr<JSObjectWrapper> as_object() const;
+
+    private:
+        Type type_;
+        bool bool_val_ = false;
+        double num_val_ = 0.0;
+        std::string str_val_;
+        std::shared_ptr<JSObjectWrapper> obj_val_;
+    };
+
+    /**
+     * Safe C++ wrapper for Rust JSObject
+     */
+    class JSObjectWrapper {
+    public:
+        JSObjectWrapper(RustObjectHandle handle);
+        ~JSObjectWrapper();
+
+        // Prevent accidental copies
+        JSObjectWrapper(const JSObjectWrapper &) = delete;
+        JSObjectWrapper &operator=(const JSObjectWrapper &) = delete;
+
+        // Allow move semantics
+        JSObjectWrapper(JSObjectWrapper &&other) noexcept;
+        JSObjectWrapper &operator=(JSObjectWrapper &&other) noexcept;
+
+        // Property access
+        void set_property(const std::string &key, const JSValue &value);
+        JSValue get_property(const std::string &key) const;
+
+        // Type information
+        JSObjectType type() const;
+
+        // Set finalizer callback
+        void set_finalizer(std::function<void(RustObjectHandle)> finalizer);
+
+        // Access the underlying handle (for advanced usage)
+        RustObjectHandle handle() const { return handle_; }
+
+    private:
+        RustObjectHandle handle_;
+        static void finalize_wrapper(RustObjectHandle handle);
+        static std::unordered_map<RustObjectHandle, std::function<void(RustObjectHandle)>> finalizers_;
+    };
+
+    /**
+     * Garbage collector for JavaScript objects
+     */
+    class GarbageCollector {
+    public:
+        GarbageCollector(RustGCHandle handle);
+        ~GarbageCollector();
+
+        // Prevent copies
+        GarbageCollector(const GarbageCollector &) = delete;
+        GarbageCollector &operator=(const GarbageCollector &) = delete;
+
+        // Configure the GC
+        void configure(size_t young_gen_threshold_kb, size_t old_gen_threshold_kb, uint64_t max_pause_ms,
+                       bool incremental, bool verbose);
+
+        // Force collection
+        void collect();
+
+        // Root management
+        void add_root(std::shared_ptr<JSObjectWrapper> obj);
+        void remove_root(std::shared_ptr<JSObjectWrapper> obj);
+
+        // Get statistics
+        GCStatistics statistics() const;
+
+        // Access the underlying handle (for advanced usage)
+        RustGCHandle handle() const { return handle_; }
+
+    private:
+        RustGCHandle handle_;
+    };
+
+    /**
+     * Main memory manager for JavaScript compiler
+     */
+    class MemoryManager {
+    public:
+        // Get the singleton instance
+        static MemoryManager &instance();
+
+        // Initialize and shutdown
+        void initialize();
+        void shutdown();
+
+        // Object creation
+        std::shared_ptr<JSObjectWrapper> create_object(JSObjectType type);
+
+        // Configure the garbage collector
+        void configure_gc(size_t young_gen_threshold_kb, size_t old_gen_threshold_kb, uint64_t max_pause_ms,
+                          bool incremental, bool verbose);
+
+        // Manually trigger garbage collection
+        void collect();
+
+        // Get statistics
+        GCStatistics gc_statistics() const;
+
+        // Get the GC
+        std::shared_ptr<GarbageCollector> gc() const { return gc_; }
+
+    private:
+        MemoryManager() = default;
+        ~MemoryManager() = default;
+
+        // Prevent copies
+        MemoryManager(const MemoryManager &) = delete;
+        MemoryManager &operator=(const MemoryManager &) = delete;
+
+        bool initialized_ = false;
+        std::shared_ptr<GarbageCollector> gc_;
+    };
+
+} // namespace js_memory
@@ -14,7 +14,7 @@ fn main() {
 
     cbindgen::Builder::new()
         .with_crate(crate_dir)
-        .with_language(cbindgen::Language::Cpp)
+        .with_language(cbindgen::Language::Cxx)
         .with_namespace("rust_memory")
         .with_parse_deps(true)
         .with_parse_include(&["js_memory_manager"])
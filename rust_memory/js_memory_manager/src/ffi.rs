@@ -1,409 +1,1243 @@
-use crate::gc::{GarbageCollector, GCConfiguration, GCStatistics};
-use crate::object::{JSObject, JSObjectHandle, JSObjectType, JSValue};
-use crate::string_interner::{InternedString, get_interner_stats};
-use libc::{c_char, c_double, c_int, c_void, size_t};
-use std::ffi::{CStr, CString};
-use std::ptr;
-use std::sync::Arc;
-
-// Export the GC and object types to C++
-pub type RustGCHandle = *mut GarbageCollector;
-pub type RustObjectHandle = *mut JSObject;
-
-/// Initialize the memory manager and return a handle to the GC
-#[no_mangle]
-pub extern "C" fn js_memory_init() -> RustGCHandle {
-    let gc = GarbageCollector::new();
-    // Convert Arc<GarbageCollector> to raw pointer
-    Arc::into_raw(gc) as *mut GarbageCollector
-}
-
-/// Clean up and destroy the memory manager
-#[no_mangle]
-pub extern "C" fn js_memory_shutdown(gc_handle: RustGCHandle) {
-    if !gc_handle.is_null() {
-        // Safety: Convert back to Arc and drop it
-        unsafe {
-            let _ = Arc::from_raw(gc_handle);
-        }
-    }
-}
-
-/// Configure the garbage collector
-#[no_mangle]
-pub extern "C" fn js_gc_configure(gc_handle: RustGCHandle, config: *const GCConfiguration) {
-    if gc_handle.is_null() || config.is_null() {
-        return;
-    }
-
-    // Safety: We trust the C++ side to provide a valid configuration
-    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
-    let config = unsafe { &*config };
-    
-    gc.configure(config.clone());
-}
-
-/// Force a garbage collection cycle
-#[no_mangle]
-pub extern "C" fn js_gc_collect(gc_handle: RustGCHandle) {
-    if gc_handle.is_null() {
-        return;
-    }
-
-    // Safety: We trust the gc_handle to be valid
-    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
-    gc.collect();
-}
-
-/// Add a root object that shouldn't be collected
-#[no_mangle]
-pub extern "C" fn js_gc_add_root(gc_handle: RustGCHandle, obj_handle: RustObjectHandle) {
-    if gc_handle.is_null() || obj_handle.is_null() {
-        return;
-    }
-
-    // Safety: We trust both handles to be valid
-    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
-    gc.add_root(obj_handle);
-}
-
-/// Remove a root object
-#[no_mangle]
-pub extern "C" fn js_gc_remove_root(gc_handle: RustGCHandle, obj_handle: RustObjectHandle) {
-    if gc_handle.is_null() || obj_handle.is_null() {
-        return;
-    }
-
-    // Safety: We trust both handles to be valid
-    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
-    gc.remove_root(obj_handle);
-}
-
-/// Get garbage collector statistics
-#[no_mangle]
-pub extern "C" fn js_gc_get_stats(gc_handle: RustGCHandle) -> GCStatistics {
-    if gc_handle.is_null() {
-        return GCStatistics {
-            allocation_count: 0,
-            collection_count: 0,
-            objects_freed: 0,
-            young_generation_size: 0,
-            old_generation_size: 0,
-        };
-    }
-
-    // Safety: We trust the handle to be valid
-    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
-    gc.statistics()
-}
-
-/// Create a new JavaScript object
-#[no_mangle]
-pub extern "C" fn js_create_object(gc_handle: RustGCHandle, obj_type: c_int) -> RustObjectHandle {
-    if gc_handle.is_null() {
-        return ptr::null_mut();
-    }
-    
-    unsafe {
-        let gc = &*(gc_handle);
-        let obj_type = match obj_type {
-            0 => JSObjectType::Object,
-            1 => JSObjectType::Array,
-            2 => JSObjectType::Function,
-            3 => JSObjectType::String,
-            4 => JSObjectType::Number,
-            5 => JSObjectType::Boolean,
-            6 => JSObjectType::Null,
-            _ => JSObjectType::Undefined,
-        };
-        
-        let obj = gc.create_object(obj_type);
-        Box::into_raw(Box::new(obj.ptr)) as *mut JSObject
-    }
-}
-
-/// Release an object handle
-#[no_mangle]
-pub extern "C" fn js_release_object(obj_handle: RustObjectHandle) {
-    if !obj_handle.is_null() {
-        // Safety: Convert raw pointer back to Arc and let it drop
-        unsafe {
-            let _ = Arc::from_raw(obj_handle);
-        }
-    }
-}
-
-/// Set a property on an object with a string value
-#[no_mangle]
-pub extern "C" fn js_set_property_string(
-    obj_handle: RustObjectHandle,
-    key: *const c_char,
-    value: *const c_char,
-) -> c_int {
-    if obj_handle.is_null() || key.is_null() || value.is_null() {
-        return 0;
-    }
-
-    // Safety: Convert raw pointers to Rust types
-    unsafe {
-        let obj = &*(obj_handle as *const JSObject);
-        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
-        let val_str = CStr::from_ptr(value).to_str().unwrap_or("");
-        
-        // Use interned strings for both keys and values
-        obj.set_property(key_str, JSValue::String(InternedString::new(val_str)));
-        1
-    }
-}
-
-/// Set a property on an object with a number value
-#[no_mangle]
-pub extern "C" fn js_set_property_number(
-    obj_handle: RustObjectHandle,
-    key: *const c_char,
-    value: c_double,
-) -> c_int {
-    if obj_handle.is_null() || key.is_null() {
-        return 0;
-    }
-
-    // Safety: Convert raw pointers to Rust types
-    unsafe {
-        let obj = &*(obj_handle as *const JSObject);
-        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
-        
-        obj.set_property(key_str, JSValue::Number(value));
-        1
-    }
-}
-
-/// Set a property on an object with a boolean value
-#[no_mangle]
-pub extern "C" fn js_set_property_boolean(
-    obj_handle: RustObjectHandle,
-    key: *const c_char,
-    value: c_int,
-) -> c_int {
-    if obj_handle.is_null() || key.is_null() {
-        return 0;
-    }
-
-    // Safety: Convert raw pointers to Rust types
-    unsafe {
-        let obj = &*(obj_handle as *const JSObject);
-        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
-        
-        obj.set_property(key_str, JSValue::Boolean(value != 0));
-        1
-    }
-}
-
-/// Set a property on an object with an object value
-#[no_mangle]
-pub extern "C" fn js_set_property_object(
-    obj_handle: RustObjectHandle,
-    key: *const c_char,
-    value: RustObjectHandle,
-) -> c_int {
-    if obj_handle.is_null() || key.is_null() || value.is_null() {
-        return 0;
-    }
-
-    // Safety: Convert raw pointers to Rust types
-    unsafe {
-        let obj = &*(obj_handle as *const JSObject);
-        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
-        
-        // Create a handle from the raw pointer
-        if let Some(value_handle) = JSObjectHandle::from_raw(value) {
-            obj.set_property(key_str, JSValue::Object(value_handle));
-            1
-        } else {
-            0
-        }
-    }
-}
-
-/// Get a string property from an object
-#[no_mangle]
-pub extern "C" fn js_get_property_string(
-    obj_handle: RustObjectHandle,
-    key: *const c_char,
-    buffer: *mut c_char,
-    buffer_size: size_t,
-) -> c_int {
-    if obj_handle.is_null() || key.is_null() || buffer.is_null() || buffer_size == 0 {
-        return 0;
-    }
-
-    // Safety: Convert raw pointers to Rust types
-    unsafe {
-        let obj = &*(obj_handle as *const JSObject);
-        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
-        
-        // Get the property
-        let value = obj.get_property(key_str);
-        
-        // Extract string value
-        if let JSValue::String(s) = value {
-            // InternedString implements Deref<Target=str>, so we can use as_bytes() directly
-            let bytes = s.as_bytes();
-            let copy_size = bytes.len().min(buffer_size - 1);
-            
-            ptr::copy_nonoverlapping(bytes.as_ptr(), buffer as *mut u8, copy_size);
-            *buffer.add(copy_size) = 0; // Null terminate
-            
-            1
-        } else {
-            0
-        }
-    }
-}
-
-/// Get a number property from an object
-#[no_mangle]
-pub extern "C" fn js_get_property_number(
-    obj_handle: RustObjectHandle,
-    key: *const c_char,
-    out_value: *mut c_double,
-) -> c_int {
-    if obj_handle.is_null() || key.is_null() || out_value.is_null() {
-        return 0;
-    }
-
-    // Safety: Convert raw pointers to Rust types
-    unsafe {
-        let obj = &*(obj_handle as *const JSObject);
-        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
-        
-        // Get the property
-        let value = obj.get_property(key_str);
-        
-        // Extract number value
-        if let JSValue::Number(n) = value {
-            *out_value = n;
-            1
-        } else {
-            0
-        }
-    }
-}
-
-/// Get a boolean property from an object
-#[no_mangle]
-pub extern "C" fn js_get_property_boolean(
-    obj_handle: RustObjectHandle,
-    key: *const c_char,
-    out_value: *mut c_int,
-) -> c_int {
-    if obj_handle.is_null() || key.is_null() || out_value.is_null() {
-        return 0;
-    }
-
-    // Safety: Convert raw pointers to Rust types
-    unsafe {
-        let obj = &*(obj_handle as *const JSObject);
-        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
-        
-        // Get the property
-        let value = obj.get_property(key_str);
-        
-        // Extract boolean value
-        if let JSValue::Boolean(b) = value {
-            *out_value = if b { 1 } else { 0 };
-            1
-        } else {
-            0
-        }
-    }
-}
-
-/// Get an object property from an object
-#[no_mangle]
-pub extern "C" fn js_get_property_object(
-    obj_handle: RustObjectHandle,
-    key: *const c_char,
-    out_value: *mut RustObjectHandle,
-) -> c_int {
-    if obj_handle.is_null() || key.is_null() || out_value.is_null() {
-        return 0;
-    }
-
-    // Safety: Convert raw pointers to Rust types
-    unsafe {
-        let obj = &*(obj_handle as *const JSObject);
-        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
-        
-        // Get the property
-        let value = obj.get_property(key_str);
-        
-        // Extract object value
-        if let JSValue::Object(handle) = value {
-            // Increment ref count to avoid dropping when this function returns
-            let ptr = Arc::into_raw(handle.ptr.clone()) as *mut JSObject;
-            *out_value = ptr;
-            1
-        } else {
-            *out_value = ptr::null_mut();
-            0
-        }
-    }
-}
-
-/// Set a finalizer function for an object
-#[no_mangle]
-pub extern "C" fn js_set_finalizer(
-    obj_handle: RustObjectHandle,
-    finalizer: extern "C" fn(*mut JSObject)
-) -> c_int {
-    if obj_handle.is_null() {
-        return 0;
-    }
-
-    // Safety: We trust the handle to be valid
-    unsafe {
-        let obj = &*(obj_handle as *const JSObject);
-        obj.set_finalizer(finalizer);
-        1
-    }
-}
-
-/// Get the type of an object
-#[no_mangle]
-pub extern "C" fn js_get_object_type(obj_handle: RustObjectHandle) -> c_int {
-    if obj_handle.is_null() {
-        return -1;
-    }
-
-    // Safety: We trust the handle to be valid
-    unsafe {
-        let obj = &*(obj_handle as *const JSObject);
-        let obj_type = obj.inner.read().obj_type;
-        
-        // Convert JSObjectType to C int
-        match obj_type {
-            JSObjectType::Object => 0,
-            JSObjectType::Array => 1,
-            JSObjectType::Function => 2,
-            JSObjectType::String => 3,
-            JSObjectType::Number => 4,
-            JSObjectType::Boolean => 5,
-            JSObjectType::Null => 6,
-            JSObjectType::Undefined => 7,
-        }
-    }
-}
-
-/// Get the number of unique strings in the string interner
-#[no_mangle]
-pub extern "C" fn js_get_interned_string_count() -> size_t {
-    let (count, _) = get_interner_stats();
-    count
-}
-
-/// Get the approximate memory usage of the string interner
-#[no_mangle]
-pub extern "C" fn js_get_interned_string_memory() -> size_t {
-    let (_, memory) = get_interner_stats();
-    memory
+// FFI entry points trust the C++ side to pass valid handles
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::gc::{GarbageCollector, GCConfiguration, GCStatistics, OomHandler, PromotionCallback, WeakCallback};
+use crate::object::{JSObject, JSObjectHandle, JSObjectType, JSValue, JSValueTag, JSWeakRef, PropertyKey, ShapeChangeCallback, is_live_handle};
+use crate::shape::{ShapeStats, get_shape_stats};
+use crate::string_interner::{InternedString, get_interner_stats, sweep_unused_strings};
+use libc::{c_char, c_double, c_int, c_void, size_t};
+use std::ffi::{CStr, CString};
+use std::ptr;
+use std::sync::Arc;
+
+// Export the GC and object types to C++
+pub type RustGCHandle = *mut GarbageCollector;
+pub type RustObjectHandle = *mut JSObject;
+pub type RustWeakRefHandle = *mut JSWeakRef;
+
+/// Initialize the memory manager and return a handle to the GC
+#[no_mangle]
+pub extern "C" fn js_memory_init() -> RustGCHandle {
+    let gc = GarbageCollector::new();
+    // Convert Arc<GarbageCollector> to raw pointer
+    Arc::into_raw(gc) as *mut GarbageCollector
+}
+
+/// Clean up and destroy the memory manager
+#[no_mangle]
+pub extern "C" fn js_memory_shutdown(gc_handle: RustGCHandle) {
+    if !gc_handle.is_null() {
+        // Safety: Convert back to Arc and drop it
+        unsafe {
+            let _ = Arc::from_raw(gc_handle);
+        }
+    }
+}
+
+/// Configure the garbage collector
+#[no_mangle]
+pub extern "C" fn js_gc_configure(gc_handle: RustGCHandle, config: *const GCConfiguration) {
+    if gc_handle.is_null() || config.is_null() {
+        return;
+    }
+
+    // Safety: We trust the C++ side to provide a valid configuration
+    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
+    let config = unsafe { &*config };
+    
+    gc.configure(config.clone());
+}
+
+/// Force a garbage collection cycle
+#[no_mangle]
+pub extern "C" fn js_gc_collect(gc_handle: RustGCHandle) {
+    if gc_handle.is_null() {
+        return;
+    }
+
+    // Safety: We trust the gc_handle to be valid
+    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
+    gc.collect();
+}
+
+/// Stop collecting ahead of destroying the collector
+#[no_mangle]
+pub extern "C" fn js_gc_begin_shutdown(gc_handle: RustGCHandle) {
+    if gc_handle.is_null() {
+        return;
+    }
+
+    // Safety: We trust the gc_handle to be valid
+    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
+    gc.begin_shutdown();
+}
+
+/// Add a root object that shouldn't be collected
+#[no_mangle]
+pub extern "C" fn js_gc_add_root(gc_handle: RustGCHandle, obj_handle: RustObjectHandle) {
+    if gc_handle.is_null() || obj_handle.is_null() {
+        return;
+    }
+
+    // Safety: We trust both handles to be valid
+    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
+    gc.add_root(obj_handle);
+}
+
+/// Remove a root object
+#[no_mangle]
+pub extern "C" fn js_gc_remove_root(gc_handle: RustGCHandle, obj_handle: RustObjectHandle) {
+    if gc_handle.is_null() || obj_handle.is_null() {
+        return;
+    }
+
+    // Safety: We trust both handles to be valid
+    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
+    gc.remove_root(obj_handle);
+}
+
+/// Get the number of finalizers waiting to run
+#[no_mangle]
+pub extern "C" fn js_gc_pending_finalizers(gc_handle: RustGCHandle) -> size_t {
+    if gc_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust the handle to be valid
+    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
+    gc.pending_finalizers()
+}
+
+/// Run up to `max` pending finalizers, returning how many ran
+#[no_mangle]
+pub extern "C" fn js_gc_run_finalizers(gc_handle: RustGCHandle, max: size_t) -> size_t {
+    if gc_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust the handle to be valid
+    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
+    gc.run_finalizers(max)
+}
+
+/// Set the handler called when an allocation would exceed `max_heap_kb`
+#[no_mangle]
+pub extern "C" fn js_gc_set_oom_handler(gc_handle: RustGCHandle, handler: Option<OomHandler>) -> c_int {
+    let Some(handler) = handler else {
+        return 0;
+    };
+    if gc_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust the handle to be valid
+    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
+    gc.set_oom_handler(handler);
+    1
+}
+
+/// Call `callback(obj, age)` each time an object is promoted to the old generation
+#[no_mangle]
+pub extern "C" fn js_gc_set_promotion_callback(
+    gc_handle: RustGCHandle,
+    callback: Option<PromotionCallback>,
+) -> c_int {
+    let Some(callback) = callback else {
+        return 0;
+    };
+    if gc_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust the handle to be valid
+    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
+    gc.set_promotion_callback(callback);
+    1
+}
+
+/// Call `callback(user)` once the object has been freed
+#[no_mangle]
+pub extern "C" fn js_gc_register_weak(
+    gc_handle: RustGCHandle,
+    obj_handle: RustObjectHandle,
+    callback: Option<WeakCallback>,
+    user: *mut c_void,
+) -> c_int {
+    let Some(callback) = callback else {
+        return 0;
+    };
+    if gc_handle.is_null() || obj_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust both handles to be valid
+    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
+    match JSObjectHandle::from_raw(obj_handle) {
+        Some(handle) => {
+            gc.register_weak(&handle, callback, user);
+            1
+        }
+        None => 0,
+    }
+}
+
+/// Write recent pause percentiles as JSON, e.g. `{"p50_us":12,"p95_us":40,"p99_us":85}`
+///
+/// The output is truncated to fit and always null-terminated. Returns 1 on
+/// success, 0 for invalid arguments.
+#[no_mangle]
+pub extern "C" fn js_gc_pause_percentiles_json(
+    gc_handle: RustGCHandle,
+    buffer: *mut c_char,
+    buffer_size: size_t,
+) -> c_int {
+    if gc_handle.is_null() || buffer.is_null() || buffer_size == 0 {
+        return 0;
+    }
+
+    // Safety: We trust the handle and buffer to be valid
+    unsafe {
+        let gc = &*(gc_handle as *const GarbageCollector);
+        let (p50, p95, p99) = gc.pause_percentiles();
+        let json = format!("{{\"p50_us\":{},\"p95_us\":{},\"p99_us\":{}}}", p50, p95, p99);
+        
+        let bytes = json.as_bytes();
+        let copy_size = bytes.len().min(buffer_size - 1);
+        ptr::copy_nonoverlapping(bytes.as_ptr(), buffer as *mut u8, copy_size);
+        *buffer.add(copy_size) = 0;
+        
+        1
+    }
+}
+
+/// Get garbage collector statistics
+#[no_mangle]
+pub extern "C" fn js_gc_get_stats(gc_handle: RustGCHandle) -> GCStatistics {
+    if gc_handle.is_null() {
+        return GCStatistics::default();
+    }
+
+    // Safety: We trust the handle to be valid
+    let gc = unsafe { &*(gc_handle as *const GarbageCollector) };
+    gc.statistics()
+}
+
+/// Convert the C encoding of an object type, as returned by `js_get_object_type`
+fn object_type_from_c(obj_type: c_int) -> Option<JSObjectType> {
+    match obj_type {
+        0 => Some(JSObjectType::Object),
+        1 => Some(JSObjectType::Array),
+        2 => Some(JSObjectType::Function),
+        3 => Some(JSObjectType::String),
+        4 => Some(JSObjectType::Number),
+        5 => Some(JSObjectType::Boolean),
+        6 => Some(JSObjectType::Null),
+        7 => Some(JSObjectType::Undefined),
+        _ => None,
+    }
+}
+
+/// Create a new JavaScript object
+#[no_mangle]
+pub extern "C" fn js_create_object(gc_handle: RustGCHandle, obj_type: c_int) -> RustObjectHandle {
+    if gc_handle.is_null() {
+        return ptr::null_mut();
+    }
+    
+    unsafe {
+        let gc = &*(gc_handle);
+        let obj_type = object_type_from_c(obj_type).unwrap_or(JSObjectType::Undefined);
+        
+        match gc.try_create_object(obj_type) {
+            Some(obj) => Arc::into_raw(obj.ptr) as *mut JSObject,
+            None => ptr::null_mut(),
+        }
+    }
+}
+
+/// Release an object handle
+#[no_mangle]
+pub extern "C" fn js_release_object(obj_handle: RustObjectHandle) {
+    if !obj_handle.is_null() {
+        // Safety: Convert raw pointer back to Arc and let it drop
+        unsafe {
+            let _ = Arc::from_raw(obj_handle);
+        }
+    }
+}
+
+/// Create a weak reference to an object
+///
+/// The weak reference doesn't keep the object alive. Free it with
+/// `js_release_weak_ref`.
+#[no_mangle]
+pub extern "C" fn js_create_weak_ref(obj_handle: RustObjectHandle) -> RustWeakRefHandle {
+    match JSObjectHandle::from_raw(obj_handle) {
+        Some(handle) => Box::into_raw(Box::new(handle.downgrade())),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Get the object a weak reference points to, or null if it has been collected
+///
+/// A non-null result is a new handle that must be released with `js_release_object`.
+#[no_mangle]
+pub extern "C" fn js_weak_ref_deref(weak_handle: RustWeakRefHandle) -> RustObjectHandle {
+    if weak_handle.is_null() {
+        return ptr::null_mut();
+    }
+
+    // Safety: We trust the handle to come from js_create_weak_ref
+    let weak = unsafe { &*weak_handle };
+    match weak.upgrade() {
+        Some(handle) => Arc::into_raw(handle.ptr) as *mut JSObject,
+        None => ptr::null_mut(),
+    }
+}
+
+/// Free a weak reference created by `js_create_weak_ref`
+#[no_mangle]
+pub extern "C" fn js_release_weak_ref(weak_handle: RustWeakRefHandle) {
+    if !weak_handle.is_null() {
+        // Safety: The handle was created by Box::into_raw in js_create_weak_ref
+        unsafe {
+            drop(Box::from_raw(weak_handle));
+        }
+    }
+}
+
+/// Outcome of `js_set_property_value`
+///
+/// The discriminants are part of the FFI contract and must not change. `Ok`
+/// is 0, so compare against it rather than testing the result for truth.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JSSetStatus {
+    /// The value was stored
+    Ok = 0,
+    /// The object handle is null or doesn't refer to a live object
+    InvalidHandle = 1,
+    /// The key or value pointer is null, or the value handle is unusable
+    InvalidArgument = 2,
+    /// The object is frozen, so none of its properties can be written
+    Frozen = 3,
+    /// The key is new and the object's shape is frozen, so it can't be added
+    NotExtensible = 4,
+}
+
+/// Check the arguments shared by the property setters
+///
+/// `key` is the key pointer, or for the batch setter the array of them.
+fn check_set_arguments<K>(obj_handle: RustObjectHandle, key: *const K) -> Option<JSSetStatus> {
+    if obj_handle.is_null() || !is_live_handle(obj_handle) {
+        return Some(JSSetStatus::InvalidHandle);
+    }
+    if key.is_null() {
+        return Some(JSSetStatus::InvalidArgument);
+    }
+    None
+}
+
+/// Write a property and report why the object refused it, if it did
+fn set_property_status(obj: &JSObject, key: &str, value: JSValue) -> JSSetStatus {
+    if obj.set_property(key, value) {
+        JSSetStatus::Ok
+    } else if obj.is_frozen() {
+        JSSetStatus::Frozen
+    } else {
+        JSSetStatus::NotExtensible
+    }
+}
+
+/// Set a property on an object with a string value
+///
+/// Returns 1 if the value was stored and 0 otherwise; `js_set_property_value`
+/// reports why a write was refused.
+#[no_mangle]
+pub extern "C" fn js_set_property_string(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    value: *const c_char,
+) -> c_int {
+    if check_set_arguments(obj_handle, key).is_some() || value.is_null() {
+        return 0;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        let val_str = CStr::from_ptr(value).to_str().unwrap_or("");
+        
+        // Use interned strings for both keys and values
+        obj.set_property(key_str, JSValue::String(InternedString::new(val_str))) as c_int
+    }
+}
+
+/// Set a property on an object with a number value
+///
+/// Returns 1 if the value was stored and 0 otherwise; `js_set_property_value`
+/// reports why a write was refused.
+#[no_mangle]
+pub extern "C" fn js_set_property_number(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    value: c_double,
+) -> c_int {
+    if check_set_arguments(obj_handle, key).is_some() {
+        return 0;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        
+        obj.set_property(key_str, JSValue::Number(value)) as c_int
+    }
+}
+
+/// Set a property on an object with a boolean value
+///
+/// Returns 1 if the value was stored and 0 otherwise; `js_set_property_value`
+/// reports why a write was refused.
+#[no_mangle]
+pub extern "C" fn js_set_property_boolean(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    value: c_int,
+) -> c_int {
+    if check_set_arguments(obj_handle, key).is_some() {
+        return 0;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        
+        obj.set_property(key_str, JSValue::Boolean(value != 0)) as c_int
+    }
+}
+
+/// Set a property on an object with an object value
+///
+/// Returns 1 if the value was stored and 0 otherwise; `js_set_property_value`
+/// reports why a write was refused.
+#[no_mangle]
+pub extern "C" fn js_set_property_object(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    value: RustObjectHandle,
+) -> c_int {
+    if check_set_arguments(obj_handle, key).is_some() {
+        return 0;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        
+        // Create a handle from the raw pointer
+        match JSObjectHandle::from_raw(value) {
+            Some(value_handle) => obj.set_property(key_str, JSValue::Object(value_handle)) as c_int,
+            None => 0,
+        }
+    }
+}
+
+/// Payload of a `JSValueFFI`; the tag says which member is set
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union JSValuePayload {
+    pub number: c_double,
+    pub boolean: c_int,
+    pub string: *const c_char,
+    pub object: RustObjectHandle,
+}
+
+/// A value passed in from C++, tagged with a `JSValueTag`
+///
+/// `Undefined` and `Null` ignore the payload. Strings are copied into the
+/// interner, so the pointer only needs to outlive the call.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct JSValueFFI {
+    pub tag: c_int,
+    pub payload: JSValuePayload,
+}
+
+impl JSValueFFI {
+    /// Convert to a `JSValue`, or `None` for an unsupported tag or a null pointer
+    ///
+    /// # Safety
+    ///
+    /// The payload member selected by `tag` must be initialized, and a string
+    /// or object pointer must be valid.
+    unsafe fn to_value(self) -> Option<JSValue> {
+        let value = match self.tag {
+            tag if tag == JSValueTag::Undefined as c_int => JSValue::Undefined,
+            tag if tag == JSValueTag::Null as c_int => JSValue::Null,
+            tag if tag == JSValueTag::Boolean as c_int => JSValue::Boolean(self.payload.boolean != 0),
+            tag if tag == JSValueTag::Number as c_int => JSValue::Number(self.payload.number),
+            tag if tag == JSValueTag::String as c_int && !self.payload.string.is_null() => {
+                let text = CStr::from_ptr(self.payload.string).to_str().unwrap_or("");
+                JSValue::String(InternedString::new(text))
+            }
+            tag if tag == JSValueTag::Object as c_int => {
+                JSValue::Object(JSObjectHandle::from_raw(self.payload.object)?)
+            }
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// Set a property from a tagged value, reporting why the write was refused
+///
+/// Unlike the typed setters, which return 1 or 0, this returns a
+/// `JSSetStatus`, and `JSSetStatus::Ok` is 0.
+#[no_mangle]
+pub extern "C" fn js_set_property_value(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    value: *const JSValueFFI,
+) -> JSSetStatus {
+    if let Some(status) = check_set_arguments(obj_handle, key) {
+        return status;
+    }
+    if value.is_null() {
+        return JSSetStatus::InvalidArgument;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        
+        match (*value).to_value() {
+            Some(value) => set_property_status(obj, key_str, value),
+            None => JSSetStatus::InvalidArgument,
+        }
+    }
+}
+
+/// Set `count` properties from parallel arrays of keys and values in one call
+///
+/// The object's lock is taken once for the whole batch instead of once per
+/// property. Pairs with a null key or an unusable value are skipped, as are
+/// new keys once the shape is frozen. Stores how many properties were set in
+/// `out_written`, if it isn't null. Returns `JSSetStatus::Ok` when the batch
+/// was applied, even partly; otherwise nothing was written and the status says
+/// why: a bad handle, null arrays, or a frozen object.
+#[no_mangle]
+pub extern "C" fn js_set_properties_batch(
+    obj_handle: RustObjectHandle,
+    keys: *const *const c_char,
+    values: *const JSValueFFI,
+    count: size_t,
+    out_written: *mut size_t,
+) -> JSSetStatus {
+    if !out_written.is_null() {
+        // Safety: We trust a non-null output pointer to be valid
+        unsafe { *out_written = 0 };
+    }
+    if let Some(status) = check_set_arguments(obj_handle, keys) {
+        return status;
+    }
+    if values.is_null() {
+        return JSSetStatus::InvalidArgument;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let keys = std::slice::from_raw_parts(keys, count);
+        let values = std::slice::from_raw_parts(values, count);
+        
+        // Convert and intern everything before taking the object's lock
+        let properties: Vec<(&str, JSValue)> = keys.iter()
+            .zip(values)
+            .filter(|(key, _)| !key.is_null())
+            .filter_map(|(&key, value)| {
+                let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+                Some((key_str, value.to_value()?))
+            })
+            .collect();
+        let written = obj.set_properties(properties);
+        if !out_written.is_null() {
+            *out_written = written;
+        }
+        if written == 0 && count > 0 && obj.is_frozen() {
+            return JSSetStatus::Frozen;
+        }
+        JSSetStatus::Ok
+    }
+}
+
+/// Copy as much of `text` as fits into `buffer`, always null-terminating it
+///
+/// Truncation happens at a character boundary so the copy stays valid UTF-8.
+/// Returns whether the whole string fit.
+///
+/// # Safety
+///
+/// `buffer` must be valid for writes of `buffer_size` bytes, which must be nonzero.
+unsafe fn copy_to_buffer(text: &str, buffer: *mut c_char, buffer_size: size_t) -> bool {
+    let mut copy_size = text.len().min(buffer_size - 1);
+    while !text.is_char_boundary(copy_size) {
+        copy_size -= 1;
+    }
+    ptr::copy_nonoverlapping(text.as_ptr(), buffer as *mut u8, copy_size);
+    *buffer.add(copy_size) = 0;
+    copy_size == text.len()
+}
+
+/// How a getter resolves a key: `JSObject::get_property` for own properties
+/// only, or `JSObject::get_property_with_proto` to search the prototype chain
+type Lookup = fn(&JSObject, &str) -> JSValue;
+
+/// Get a string property from an object
+///
+/// Returns the buffer size the whole string needs, terminator included. If
+/// the buffer was too small the string is truncated at a character boundary
+/// and that size is returned negated, so callers can retry with a bigger
+/// buffer. Returns 0 if the property isn't a string or the arguments are invalid.
+#[no_mangle]
+pub extern "C" fn js_get_property_string(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    buffer: *mut c_char,
+    buffer_size: size_t,
+) -> c_int {
+    get_string_property(obj_handle, key, buffer, buffer_size, JSObject::get_property)
+}
+
+/// Like `js_get_property_string`, but inherited properties are found too
+#[no_mangle]
+pub extern "C" fn js_get_property_string_with_proto(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    buffer: *mut c_char,
+    buffer_size: size_t,
+) -> c_int {
+    get_string_property(obj_handle, key, buffer, buffer_size, JSObject::get_property_with_proto)
+}
+
+fn get_string_property(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    buffer: *mut c_char,
+    buffer_size: size_t,
+    lookup: Lookup,
+) -> c_int {
+    if obj_handle.is_null() || key.is_null() || buffer.is_null() || buffer_size == 0 {
+        return 0;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        
+        // Get the property
+        let value = lookup(obj, key_str);
+        
+        // Extract string value
+        if let JSValue::String(s) = value {
+            let required = c_int::try_from(s.len() + 1).unwrap_or(c_int::MAX);
+            if copy_to_buffer(&s, buffer, buffer_size) {
+                required
+            } else {
+                -required
+            }
+        } else {
+            0
+        }
+    }
+}
+
+/// Get a number property from an object
+#[no_mangle]
+pub extern "C" fn js_get_property_number(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    out_value: *mut c_double,
+) -> c_int {
+    get_number_property(obj_handle, key, out_value, JSObject::get_property)
+}
+
+/// Like `js_get_property_number`, but inherited properties are found too
+#[no_mangle]
+pub extern "C" fn js_get_property_number_with_proto(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    out_value: *mut c_double,
+) -> c_int {
+    get_number_property(obj_handle, key, out_value, JSObject::get_property_with_proto)
+}
+
+fn get_number_property(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    out_value: *mut c_double,
+    lookup: Lookup,
+) -> c_int {
+    if obj_handle.is_null() || key.is_null() || out_value.is_null() {
+        return 0;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        
+        // Get the property
+        let value = lookup(obj, key_str);
+        
+        // Extract number value
+        match value {
+            JSValue::Number(n) => {
+                *out_value = n;
+                1
+            }
+            JSValue::Integer(i) => {
+                *out_value = i as c_double;
+                1
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Get a boolean property from an object
+#[no_mangle]
+pub extern "C" fn js_get_property_boolean(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    out_value: *mut c_int,
+) -> c_int {
+    get_boolean_property(obj_handle, key, out_value, JSObject::get_property)
+}
+
+/// Like `js_get_property_boolean`, but inherited properties are found too
+#[no_mangle]
+pub extern "C" fn js_get_property_boolean_with_proto(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    out_value: *mut c_int,
+) -> c_int {
+    get_boolean_property(obj_handle, key, out_value, JSObject::get_property_with_proto)
+}
+
+fn get_boolean_property(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    out_value: *mut c_int,
+    lookup: Lookup,
+) -> c_int {
+    if obj_handle.is_null() || key.is_null() || out_value.is_null() {
+        return 0;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        
+        // Get the property
+        let value = lookup(obj, key_str);
+        
+        // Extract boolean value
+        if let JSValue::Boolean(b) = value {
+            *out_value = if b { 1 } else { 0 };
+            1
+        } else {
+            0
+        }
+    }
+}
+
+/// Get an object property from an object
+///
+/// Each successful call hands out one reference that must be released with
+/// `js_release_object`. A handle is the object's own address, so fetching the
+/// same object again yields an equal pointer and handles can be compared for
+/// identity; each copy still needs its own release.
+#[no_mangle]
+pub extern "C" fn js_get_property_object(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    out_value: *mut RustObjectHandle,
+) -> c_int {
+    get_object_property(obj_handle, key, out_value, JSObject::get_property)
+}
+
+/// Like `js_get_property_object`, but inherited properties are found too
+#[no_mangle]
+pub extern "C" fn js_get_property_object_with_proto(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    out_value: *mut RustObjectHandle,
+) -> c_int {
+    get_object_property(obj_handle, key, out_value, JSObject::get_property_with_proto)
+}
+
+fn get_object_property(
+    obj_handle: RustObjectHandle,
+    key: *const c_char,
+    out_value: *mut RustObjectHandle,
+    lookup: Lookup,
+) -> c_int {
+    if obj_handle.is_null() || key.is_null() || out_value.is_null() {
+        return 0;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        
+        // Get the property
+        let value = lookup(obj, key_str);
+        
+        // Extract object value
+        if let JSValue::Object(handle) = value {
+            // Increment ref count to avoid dropping when this function returns
+            let ptr = Arc::into_raw(handle.ptr.clone()) as *mut JSObject;
+            *out_value = ptr;
+            1
+        } else {
+            *out_value = ptr::null_mut();
+            0
+        }
+    }
+}
+
+/// Get the stable id of an object, or 0 for a null handle
+#[no_mangle]
+pub extern "C" fn js_object_id(obj_handle: RustObjectHandle) -> u64 {
+    if obj_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust the handle to be valid
+    let obj = unsafe { &*(obj_handle as *const JSObject) };
+    obj.id()
+}
+
+/// Get the number of own properties of an object, or 0 for a null handle
+///
+/// Array elements count too. Valid indices for `js_get_property_name_at` are
+/// below this count.
+#[no_mangle]
+pub extern "C" fn js_get_property_count(obj_handle: RustObjectHandle) -> size_t {
+    if obj_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust the handle to be valid
+    let obj = unsafe { &*(obj_handle as *const JSObject) };
+    obj.property_count()
+}
+
+/// Copy the name of the own property at `index` in enumeration order
+///
+/// The order is `JSObject::own_keys_spec_order`: integer keys ascending, then
+/// the rest in insertion order. Indices are only stable until the object's
+/// properties next change. The name is truncated to fit at a character
+/// boundary and always null-terminated. Each call looks up only the requested
+/// key, so walking every index is linear. Returns 1 on success, 0 for an index
+/// out of range or invalid arguments.
+#[no_mangle]
+pub extern "C" fn js_get_property_name_at(
+    obj_handle: RustObjectHandle,
+    index: size_t,
+    buffer: *mut c_char,
+    buffer_size: size_t,
+) -> c_int {
+    if obj_handle.is_null() || !is_live_handle(obj_handle) || buffer.is_null() || buffer_size == 0 {
+        return 0;
+    }
+
+    // Safety: We trust the handle and buffer to be valid
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let Some(key) = obj.own_key_at(index) else {
+            return 0;
+        };
+        
+        match key {
+            PropertyKey::Index(index) => copy_to_buffer(&index.to_string(), buffer, buffer_size),
+            PropertyKey::Name(name) => copy_to_buffer(&name, buffer, buffer_size),
+        };
+        1
+    }
+}
+
+/// Check whether an object has an own property, ignoring its prototype
+///
+/// Returns 1 if it does, 0 if it doesn't or the arguments are invalid.
+#[no_mangle]
+pub extern "C" fn js_has_own_property(obj_handle: RustObjectHandle, key: *const c_char) -> c_int {
+    if obj_handle.is_null() || key.is_null() {
+        return 0;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        obj.has_own_property(key_str) as c_int
+    }
+}
+
+/// Check whether an object or its prototype chain has a property
+///
+/// Unlike the getters this never copies the value out. Returns 1 if the
+/// property is found, 0 if it isn't or the arguments are invalid.
+#[no_mangle]
+pub extern "C" fn js_has_property(obj_handle: RustObjectHandle, key: *const c_char) -> c_int {
+    if obj_handle.is_null() || key.is_null() {
+        return 0;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        obj.has_property(key_str) as c_int
+    }
+}
+
+/// Set an object's prototype, or clear it with a null `proto_handle`
+///
+/// Returns 1 on success, 0 for invalid arguments, and -1 if the object is
+/// frozen or already on the prototype's chain, since linking it would form a
+/// cycle.
+#[no_mangle]
+pub extern "C" fn js_set_prototype(obj_handle: RustObjectHandle, proto_handle: RustObjectHandle) -> c_int {
+    if obj_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust both handles to be valid
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let prototype = JSObjectHandle::from_raw(proto_handle);
+        if obj.try_set_prototype(prototype) { 1 } else { -1 }
+    }
+}
+
+/// Get an object's prototype
+///
+/// Returns 1 and stores a handle that must be released with `js_release_object`,
+/// or 0 and stores null if the object has no prototype or the arguments are invalid.
+#[no_mangle]
+pub extern "C" fn js_get_prototype(obj_handle: RustObjectHandle, out_proto: *mut RustObjectHandle) -> c_int {
+    if out_proto.is_null() {
+        return 0;
+    }
+    
+    // Safety: We trust the handle and output pointer to be valid
+    unsafe {
+        *out_proto = ptr::null_mut();
+        if obj_handle.is_null() {
+            return 0;
+        }
+        
+        let obj = &*(obj_handle as *const JSObject);
+        match obj.get_prototype() {
+            Some(proto) => {
+                *out_proto = Arc::into_raw(proto.ptr) as *mut JSObject;
+                1
+            }
+            None => 0,
+        }
+    }
+}
+
+/// Call `callback(obj, old_shape_id, new_shape_id)` whenever the object changes shape
+#[no_mangle]
+pub extern "C" fn js_set_shape_change_callback(
+    obj_handle: RustObjectHandle,
+    callback: Option<ShapeChangeCallback>,
+) -> c_int {
+    let Some(callback) = callback else {
+        return 0;
+    };
+    if obj_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust the handle to be valid
+    let obj = unsafe { &*(obj_handle as *const JSObject) };
+    obj.set_shape_change_callback(callback);
+    1
+}
+
+/// Delete an own property
+///
+/// Returns 1 if the property existed, 0 if it didn't or the arguments are invalid.
+#[no_mangle]
+pub extern "C" fn js_delete_property(obj_handle: RustObjectHandle, key: *const c_char) -> c_int {
+    if obj_handle.is_null() || key.is_null() {
+        return 0;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        obj.delete_property(key_str) as c_int
+    }
+}
+
+/// Lock an object's layout so only existing properties can be written
+///
+/// Afterwards the property setters return 0 for keys the object doesn't have,
+/// and `js_set_property_value` returns `JSSetStatus::NotExtensible`.
+#[no_mangle]
+pub extern "C" fn js_freeze_shape(obj_handle: RustObjectHandle) -> c_int {
+    if obj_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust the handle to be valid
+    let obj = unsafe { &*(obj_handle as *const JSObject) };
+    obj.freeze_shape();
+    1
+}
+
+/// Make an object immutable, like `Object.freeze`
+///
+/// Afterwards the typed property setters, `js_delete_property` and
+/// `js_array_push` return 0, `js_set_property_value` returns
+/// `JSSetStatus::Frozen`, and `js_set_prototype` returns -1.
+#[no_mangle]
+pub extern "C" fn js_freeze_object(obj_handle: RustObjectHandle) -> c_int {
+    if obj_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust the handle to be valid
+    let obj = unsafe { &*(obj_handle as *const JSObject) };
+    obj.freeze();
+    1
+}
+
+/// Whether an object has been frozen with `js_freeze_object`
+#[no_mangle]
+pub extern "C" fn js_is_frozen(obj_handle: RustObjectHandle) -> c_int {
+    if obj_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust the handle to be valid
+    let obj = unsafe { &*(obj_handle as *const JSObject) };
+    obj.is_frozen() as c_int
+}
+
+/// Get the type tag of a property value, or -1 for invalid arguments
+///
+/// Missing properties report `JSValueTag::Undefined`.
+#[no_mangle]
+pub extern "C" fn js_get_value_tag(obj_handle: RustObjectHandle, key: *const c_char) -> c_int {
+    if obj_handle.is_null() || key.is_null() {
+        return -1;
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        
+        obj.get_property(key_str).tag() as c_int
+    }
+}
+
+/// Get the `typeof` string of a property value, or null for invalid arguments
+///
+/// Missing properties report `"undefined"`. The returned string is static and
+/// must not be freed.
+#[no_mangle]
+pub extern "C" fn js_value_type_of(obj_handle: RustObjectHandle, key: *const c_char) -> *const c_char {
+    if obj_handle.is_null() || key.is_null() {
+        return ptr::null();
+    }
+
+    // Safety: Convert raw pointers to Rust types
+    let type_of = unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let key_str = CStr::from_ptr(key).to_str().unwrap_or("");
+        
+        obj.get_property(key_str).type_of()
+    };
+    let name: &'static [u8] = match type_of {
+        "undefined" => b"undefined\0",
+        "boolean" => b"boolean\0",
+        "number" => b"number\0",
+        "string" => b"string\0",
+        "function" => b"function\0",
+        _ => b"object\0",
+    };
+    name.as_ptr() as *const c_char
+}
+
+/// Callback receiving one property key and its value tag
+pub type JSEntryCallback = extern "C" fn(key: *const c_char, tag: c_int, user_data: *mut c_void);
+
+/// Call `callback` for each own property in `Object.entries` order
+///
+/// Values can be read with the typed getters using the key. The callback runs
+/// after the object's lock is released, so it may access the object. Returns the
+/// number of entries visited, or 0 for invalid arguments.
+#[no_mangle]
+pub extern "C" fn js_object_entries(
+    obj_handle: RustObjectHandle,
+    callback: Option<JSEntryCallback>,
+    user_data: *mut c_void,
+) -> size_t {
+    let Some(callback) = callback else {
+        return 0;
+    };
+    if obj_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust the handle to be valid
+    let obj = unsafe { &*(obj_handle as *const JSObject) };
+    
+    let mut visited = 0;
+    for (key, value) in obj.entries() {
+        // Keys with interior NULs can't be represented as C strings
+        if let Ok(key) = CString::new(key.to_string()) {
+            callback(key.as_ptr(), value.tag() as c_int, user_data);
+            visited += 1;
+        }
+    }
+    visited
+}
+
+/// Append a number element to an array, returning the new length
+///
+/// Other value types can be stored with the `js_set_property_*` setters using
+/// the index as the key. Returns 0, appending nothing, if the array is frozen.
+#[no_mangle]
+pub extern "C" fn js_array_push(obj_handle: RustObjectHandle, value: c_double) -> size_t {
+    if obj_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust the handle to be valid
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        if obj.is_frozen() {
+            return 0;
+        }
+        obj.array_push(JSValue::Number(value))
+    }
+}
+
+/// Set a finalizer function for an object
+#[no_mangle]
+pub extern "C" fn js_set_finalizer(
+    obj_handle: RustObjectHandle,
+    finalizer: extern "C" fn(*mut JSObject)
+) -> c_int {
+    if obj_handle.is_null() {
+        return 0;
+    }
+
+    // Safety: We trust the handle to be valid
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        obj.set_finalizer(finalizer);
+        1
+    }
+}
+
+/// Get the type of an object, or -1 for a null or released handle
+///
+/// Released handles are only detected with the `handle-validation` feature.
+#[no_mangle]
+pub extern "C" fn js_get_object_type(obj_handle: RustObjectHandle) -> c_int {
+    if obj_handle.is_null() || !is_live_handle(obj_handle) {
+        return -1;
+    }
+
+    // Safety: We trust the handle to be valid
+    unsafe {
+        let obj = &*(obj_handle as *const JSObject);
+        let obj_type = obj.inner.read().obj_type;
+        
+        // Convert JSObjectType to C int
+        match obj_type {
+            JSObjectType::Object => 0,
+            JSObjectType::Array => 1,
+            JSObjectType::Function => 2,
+            JSObjectType::String => 3,
+            JSObjectType::Number => 4,
+            JSObjectType::Boolean => 5,
+            JSObjectType::Null => 6,
+            JSObjectType::Undefined => 7,
+        }
+    }
+}
+
+/// Change the type of an object, returning 1 on success
+///
+/// Returns 0 for an unknown type or when `JSObject::set_object_type` refuses,
+/// e.g. for an array that still has elements.
+#[no_mangle]
+pub extern "C" fn js_set_object_type(obj_handle: RustObjectHandle, obj_type: c_int) -> c_int {
+    if obj_handle.is_null() || !is_live_handle(obj_handle) {
+        return 0;
+    }
+    let Some(obj_type) = object_type_from_c(obj_type) else {
+        return 0;
+    };
+
+    // Safety: We trust the handle to be valid
+    let obj = unsafe { &*(obj_handle as *const JSObject) };
+    obj.set_object_type(obj_type) as c_int
+}
+
+/// Compare two object handles with SameValueZero, which for objects is identity
+///
+/// Returns 1 if both refer to the same object, 0 otherwise, including when
+/// either handle is null.
+#[no_mangle]
+pub extern "C" fn js_value_equals(a: RustObjectHandle, b: RustObjectHandle) -> c_int {
+    (!a.is_null() && ptr::eq(a, b)) as c_int
+}
+
+/// Get the number of unique strings in the string interner
+#[no_mangle]
+pub extern "C" fn js_get_interned_string_count() -> size_t {
+    let (count, _) = get_interner_stats();
+    count
+}
+
+/// Get the approximate memory usage of the string interner
+#[no_mangle]
+pub extern "C" fn js_get_interned_string_memory() -> size_t {
+    let (_, memory) = get_interner_stats();
+    memory
+}
+
+/// Drop interned strings nothing refers to anymore, returning how many were dropped
+///
+/// Property names stay alive while any shape uses them. Interning an evicted
+/// string again allocates a fresh entry.
+#[no_mangle]
+pub extern "C" fn js_intern_sweep() -> size_t {
+    sweep_unused_strings()
+}
+
+/// Get process-wide shape and transition cache counts
+#[no_mangle]
+pub extern "C" fn js_get_shape_stats() -> ShapeStats {
+    get_shape_stats()
 }
\ No newline at end of file
@@ -1,334 +1,2338 @@
-use crate::object::{JSObject, JSObjectHandle, JSObjectType};
-use libc::{c_char, c_void};
-use parking_lot::{Mutex, RwLock};
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::ffi::{CStr, CString};
-use std::mem;
-use std::sync::{Arc, Weak};
-use std::time::{Duration, Instant};
-
-/// Configuration options for the garbage collector
-#[derive(Debug, Clone)]
-pub struct GCConfiguration {
-    /// Size threshold (KB) for young generation collection
-    pub young_gen_threshold_kb: usize,
-    /// Size threshold (KB) for old generation collection
-    pub old_gen_threshold_kb: usize,
-    /// Maximum pause time in milliseconds
-    pub max_pause_ms: u64,
-    /// Whether to use incremental collection
-    pub incremental: bool,
-    /// Whether to print verbose GC debugging information
-    pub verbose: bool,
-}
-
-impl Default for GCConfiguration {
-    fn default() -> Self {
-        Self {
-            young_gen_threshold_kb: 256,   // 256KB
-            old_gen_threshold_kb: 4096,    // 4MB
-            max_pause_ms: 10,              // 10ms
-            incremental: true,
-            verbose: false,
-        }
-    }
-}
-
-/// Statistics about garbage collection
-#[derive(Debug, Clone, Copy)]
-pub struct GCStatistics {
-    /// Total number of allocations
-    pub allocation_count: usize,
-    /// Total number of collections performed
-    pub collection_count: usize,
-    /// Total number of objects freed
-    pub objects_freed: usize,
-    /// Current size of young generation in bytes
-    pub young_generation_size: usize,
-    /// Current size of old generation in bytes
-    pub old_generation_size: usize,
-}
-
-impl Default for GCStatistics {
-    fn default() -> Self {
-        Self {
-            allocation_count: 0,
-            collection_count: 0,
-            objects_freed: 0,
-            young_generation_size: 0,
-            old_generation_size: 0,
-        }
-    }
-}
-
-/// Generational garbage collector for JavaScript objects
-pub struct GarbageCollector {
-    /// Young generation objects (recently allocated)
-    young_generation: Mutex<Vec<Arc<JSObject>>>,
-    
-    /// Old generation objects (survived several collections)
-    old_generation: Mutex<Vec<Arc<JSObject>>>,
-    
-    /// Objects that should never be collected (roots)
-    roots: Mutex<HashSet<*const JSObject>>,
-    
-    /// Configuration options
-    config: RwLock<GCConfiguration>,
-    
-    /// Collection statistics
-    stats: RwLock<GCStatistics>,
-    
-    /// Whether the GC is currently running a collection
-    collecting: Mutex<bool>,
-}
-
-impl GarbageCollector {
-    /// Create a new garbage collector with default configuration
-    pub fn new() -> Arc<Self> {
-        Arc::new(Self {
-            young_generation: Mutex::new(Vec::new()),
-            old_generation: Mutex::new(Vec::new()),
-            roots: Mutex::new(HashSet::new()),
-            config: RwLock::new(GCConfiguration::default()),
-            stats: RwLock::new(GCStatistics::default()),
-            collecting: Mutex::new(false),
-        })
-    }
-    
-    /// Update the GC configuration
-    pub fn configure(&self, config: GCConfiguration) {
-        let mut current_config = self.config.write();
-        *current_config = config;
-    }
-    
-    /// Get current statistics
-    pub fn statistics(&self) -> GCStatistics {
-        *self.stats.read()
-    }
-    
-    /// Create a new JavaScript object and add it to the young generation
-    pub fn create_object(&self, obj_type: JSObjectType) -> JSObjectHandle {
-        // Create the new object
-        let obj = JSObject::new(obj_type);
-        
-        // Track the object in the young generation
-        {
-            let mut young = self.young_generation.lock();
-            young.push(obj.clone());
-            
-            // Update allocation statistics
-            let mut stats = self.stats.write();
-            stats.allocation_count += 1;
-            stats.young_generation_size += self.estimate_object_size(&obj);
-            
-            // Check if we need to trigger a young generation collection
-            if stats.young_generation_size > self.config.read().young_gen_threshold_kb * 1024 {
-                // Drop the lock before collecting
-                drop(stats);
-                drop(young);
-                self.collect_young();
-            }
-        }
-        
-        JSObjectHandle { ptr: obj }
-    }
-    
-    /// Add a root object that shouldn't be collected
-    pub fn add_root(&self, ptr: *mut JSObject) {
-        if !ptr.is_null() {
-            let mut roots = self.roots.lock();
-            roots.insert(ptr as *const JSObject);
-        }
-    }
-    
-    /// Remove a root object
-    pub fn remove_root(&self, ptr: *mut JSObject) {
-        if !ptr.is_null() {
-            let mut roots = self.roots.lock();
-            roots.remove(&(ptr as *const JSObject));
-        }
-    }
-    
-    /// Trigger a garbage collection
-    pub fn collect(&self) {
-        // Make sure we're not already collecting
-        let mut collecting = self.collecting.lock();
-        if *collecting {
-            return;
-        }
-        *collecting = true;
-        
-        // Collect both generations
-        self.collect_young();
-        self.collect_old();
-        
-        // Update stats
-        let mut stats = self.stats.write();
-        stats.collection_count += 1;
-        
-        // Reset collection flag
-        *collecting = false;
-    }
-    
-    /// Collect only the young generation (minor collection)
-    fn collect_young(&self) {
-        let start_time = Instant::now();
-        let config = self.config.read();
-        
-        if config.verbose {
-            println!("Starting young generation collection");
-        }
-        
-        // Mark phase - mark all reachable objects
-        self.mark_roots();
-        
-        // Sweep phase for young generation
-        let mut survivors = Vec::new();
-        let mut freed = 0;
-        let mut young_gen_size = 0;
-        
-        {
-            let mut young = self.young_generation.lock();
-            
-            // Process each object
-            for obj in young.drain(..) {
-                if obj.is_marked() {
-                    // Object is alive, unmark and either promote or keep in young gen
-                    obj.unmark();
-                    
-                    // Promote to old generation after surviving several collections
-                    // This is a simplification - in a real GC we would track ages
-                    if Arc::strong_count(&obj) > 2 {
-                        let mut old = self.old_generation.lock();
-                        old.push(obj);
-                    } else {
-                        survivors.push(obj);
-                    }
-                } else {
-                    // Object is unreachable, will be dropped
-                    freed += 1;
-                }
-            }
-            
-            // Put survivors back in young generation
-            *young = survivors;
-            
-            // Calculate new size
-            for obj in &*young {
-                young_gen_size += self.estimate_object_size(obj);
-            }
-        }
-        
-        // Update statistics
-        let mut stats = self.stats.write();
-        stats.objects_freed += freed;
-        stats.young_generation_size = young_gen_size;
-        
-        if config.verbose {
-            println!("Young generation collection completed in {}ms, freed {} objects",
-                     start_time.elapsed().as_millis(), freed);
-        }
-    }
-    
-    /// Collect the old generation (major collection)
-    fn collect_old(&self) {
-        let start_time = Instant::now();
-        let config = self.config.read();
-        
-        // Check if we need to run a major collection based on old gen size
-        {
-            let stats = self.stats.read();
-            if stats.old_generation_size < config.old_gen_threshold_kb * 1024 {
-                return;
-            }
-        }
-        
-        if config.verbose {
-            println!("Starting old generation collection");
-        }
-        
-        // Mark phase - mark all reachable objects
-        // (roots should already be marked by young gen collection)
-        
-        // Sweep phase for old generation
-        let mut survivors = Vec::new();
-        let mut freed = 0;
-        let mut old_gen_size = 0;
-        
-        {
-            let mut old = self.old_generation.lock();
-            
-            // Process each object
-            for obj in old.drain(..) {
-                if obj.is_marked() {
-                    // Object is alive, unmark and keep in old gen
-                    obj.unmark();
-                    survivors.push(obj);
-                } else {
-                    // Object is unreachable, will be dropped
-                    freed += 1;
-                }
-            }
-            
-            // Put survivors back in old generation
-            *old = survivors;
-            
-            // Calculate new size
-            for obj in &*old {
-                old_gen_size += self.estimate_object_size(obj);
-            }
-        }
-        
-        // Update statistics
-        let mut stats = self.stats.write();
-        stats.objects_freed += freed;
-        stats.old_generation_size = old_gen_size;
-        
-        if config.verbose {
-            println!("Old generation collection completed in {}ms, freed {} objects",
-                     start_time.elapsed().as_millis(), freed);
-        }
-    }
-    
-    /// Mark all root objects and their references
-    fn mark_roots(&self) {
-        // Get local copies of roots to avoid holding lock during marking
-        let roots: Vec<*const JSObject> = {
-            let roots = self.roots.lock();
-            roots.iter().cloned().collect()
-        };
-        
-        // Mark each root object
-        for &root_ptr in &roots {
-            // Safety: The root pointers should be valid JSObjects
-            let obj = unsafe { &*(root_ptr) };
-            obj.mark();
-        }
-    }
-    
-    /// Estimate the memory size of an object
-    fn estimate_object_size(&self, obj: &JSObject) -> usize {
-        // Base size of the object
-        let mut size = mem::size_of::<JSObject>();
-        
-        // Add size of properties
-        let inner = obj.inner.read();
-        let properties = &inner.properties;
-        size += properties.len() * (mem::size_of::<String>() + mem::size_of::<JSObject>());
-        
-        // Approximate size of property keys and values
-        for (key, value) in properties {
-            size += key.len();
-            match value {
-                crate::object::JSValue::String(s) => {
-                    size += s.len();
-                }
-                _ => {
-                    size += mem::size_of::<crate::object::JSValue>();
-                }
-            }
-        }
-        
-        size
-    }
-}
\ No newline at end of file
+use crate::object::{self, JSObject, JSObjectHandle, JSObjectType, JSValue, LazyProperty};
+use crate::string_interner::{self, InternedString};
+use parking_lot::{Mutex, RwLock};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::{c_int, c_void};
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+/// Configuration options for the garbage collector
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct GCConfiguration {
+    /// Size threshold (KB) for young generation collection
+    pub young_gen_threshold_kb: usize,
+    /// Size threshold (KB) for old generation collection
+    pub old_gen_threshold_kb: usize,
+    /// Maximum pause time in milliseconds
+    pub max_pause_ms: u64,
+    /// Whether `collect` runs one `max_pause_ms` slice of an incremental cycle
+    /// instead of a full stop-the-world collection
+    ///
+    /// Off by default. The flag used to default to true while having no effect,
+    /// so hosts that never set it keep getting full collections.
+    pub incremental: bool,
+    /// Whether to print verbose GC debugging information; same as `log_level`
+    /// `LOG_SUMMARY` unless that is set higher
+    pub verbose: bool,
+    /// How much the collector logs, from `LOG_OFF` to `LOG_TRACE`
+    pub log_level: u8,
+    /// Young collections closer together than this (ms) count towards thrashing
+    pub thrash_window_ms: u64,
+    /// Young collections freeing less than this percentage of objects are low-yield
+    pub thrash_min_freed_percent: usize,
+    /// Upper bound (KB) when raising the young threshold under thrashing
+    pub max_young_gen_threshold_kb: usize,
+    /// Queue unreachable objects with finalizers instead of finalizing them during sweeps
+    pub deferred_finalization: bool,
+    /// Number of young collections an object must survive to be promoted
+    pub promotion_age: u8,
+    /// After a major collection, set the young threshold to the live heap size
+    /// times this factor; 0 keeps the threshold fixed
+    pub heap_growth_factor: f64,
+    /// Lower bound (KB) for the young threshold chosen by the growth policy
+    pub min_young_gen_threshold_kb: usize,
+    /// Hard cap (KB) on the tracked heap; allocations beyond it fail. 0 means no cap
+    pub max_heap_kb: usize,
+    /// Keep unreachable objects inspectable for this many further collections before
+    /// dropping them; 0 drops them right away
+    pub deferred_free_generations: u32,
+    /// After a full collection, release a generation's spare capacity once its
+    /// capacity is more than this many times its length; 0 never releases it
+    pub compact_threshold_ratio: f64,
+}
+
+impl Default for GCConfiguration {
+    fn default() -> Self {
+        Self {
+            young_gen_threshold_kb: 256,   // 256KB
+            old_gen_threshold_kb: 4096,    // 4MB
+            max_pause_ms: 10,              // 10ms
+            incremental: false,            // full collections unless opted in
+            verbose: false,
+            log_level: GCConfiguration::LOG_OFF,
+            thrash_window_ms: 50,          // 50ms
+            thrash_min_freed_percent: 10,
+            max_young_gen_threshold_kb: 8192, // 8MB
+            deferred_finalization: false,
+            promotion_age: 2,
+            heap_growth_factor: 0.0,
+            min_young_gen_threshold_kb: 64, // 64KB
+            max_heap_kb: 0,
+            deferred_free_generations: 0,
+            compact_threshold_ratio: 4.0,
+        }
+    }
+}
+
+impl GCConfiguration {
+    /// Log nothing
+    pub const LOG_OFF: u8 = 0;
+    /// Log one line per collection
+    pub const LOG_SUMMARY: u8 = 1;
+    /// Also log when collections start and the generation sizes after them
+    pub const LOG_DETAILED: u8 = 2;
+    /// Also log every promotion and every freed object
+    pub const LOG_TRACE: u8 = 3;
+    
+    /// Get the log level in effect, taking `verbose` into account
+    pub fn effective_log_level(&self) -> u8 {
+        if self.verbose {
+            self.log_level.max(Self::LOG_SUMMARY)
+        } else {
+            self.log_level
+        }
+    }
+}
+
+/// Statistics about garbage collection
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GCStatistics {
+    /// Total number of allocations
+    pub allocation_count: usize,
+    /// Total number of collections performed
+    pub collection_count: usize,
+    /// Total number of objects freed
+    pub objects_freed: usize,
+    /// Current size of young generation in bytes
+    pub young_generation_size: usize,
+    /// Current size of old generation in bytes
+    pub old_generation_size: usize,
+    /// Number of times thrashing raised the young generation threshold
+    pub threshold_raises: usize,
+    /// Stores of a young object into one of this collector's old objects
+    /// recorded by the write barrier
+    pub write_barrier_hits: usize,
+    /// Old objects referencing young ones as of the last young collection
+    pub remembered_set_size: usize,
+    /// Weak map entries dropped by major collections because their key died
+    pub weak_entries_pruned: usize,
+    /// Time spent in collection pauses, in nanoseconds; each young or old
+    /// collection and each incremental step is one pause
+    pub total_pause_ns: u64,
+    /// Longest single pause, in nanoseconds
+    pub max_pause_ns: u64,
+    /// Length of the most recent pause, in nanoseconds
+    pub last_collection_ns: u64,
+}
+
+/// Progress reported by an incremental collection step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectProgress {
+    /// The cycle has more work left; call `try_collect` or `gc_step` again
+    InProgress,
+    /// The cycle finished and garbage has been freed
+    Complete,
+}
+
+/// Generation an object is tracked in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generation {
+    /// Recently allocated objects
+    Young,
+    /// Objects promoted after surviving collections
+    Old,
+}
+
+/// An object whose strong count is lower than the references the heap holds to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RefcountViolation {
+    /// The object with the suspicious count
+    pub object: *const JSObject,
+    /// The object's current strong count
+    pub strong_count: usize,
+    /// The number of strong references held by the GC and other tracked objects
+    pub expected_min: usize,
+}
+
+/// Object count and estimated bytes for one object type
+///
+/// Signed so that a diff can report shrinkage as well as growth.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TypeSummary {
+    /// Number of objects
+    pub count: isize,
+    /// Estimated bytes used by those objects
+    pub bytes: isize,
+}
+
+/// What each step of `GarbageCollector::run_full_maintenance` reclaimed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// Objects freed by the full collection
+    pub objects_freed: usize,
+    /// Weak map entries dropped because their key was freed
+    pub weak_entries_pruned: usize,
+    /// Strings removed from the global interner
+    pub strings_swept: usize,
+    /// Spare storage capacity released by compacting surviving objects
+    pub bytes_compacted: usize,
+}
+
+/// Per-type object counts and sizes of the tracked heap at one point in time
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeapSummary {
+    /// Totals per object type; types with no objects are absent
+    pub by_type: HashMap<JSObjectType, TypeSummary>,
+}
+
+impl HeapSummary {
+    /// Get the totals for one object type
+    pub fn get(&self, obj_type: JSObjectType) -> TypeSummary {
+        self.by_type.get(&obj_type).copied().unwrap_or_default()
+    }
+    
+    /// Get the per-type change since `baseline`, omitting types that didn't change
+    pub fn diff(&self, baseline: &HeapSummary) -> HeapSummary {
+        let types: HashSet<JSObjectType> = self.by_type.keys()
+            .chain(baseline.by_type.keys())
+            .copied()
+            .collect();
+        
+        let by_type = types.into_iter()
+            .filter_map(|obj_type| {
+                let now = self.get(obj_type);
+                let before = baseline.get(obj_type);
+                let change = TypeSummary {
+                    count: now.count - before.count,
+                    bytes: now.bytes - before.bytes,
+                };
+                (change != TypeSummary::default()).then_some((obj_type, change))
+            })
+            .collect();
+        
+        HeapSummary { by_type }
+    }
+}
+
+/// Phase of the incremental collection cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CollectionPhase {
+    Idle,
+    Marking,
+    Sweeping,
+}
+
+/// Work carried over between incremental collection steps
+struct IncrementalState {
+    /// Current phase of the cycle
+    phase: CollectionPhase,
+    /// Marked objects whose references haven't been traced yet
+    mark_stack: Vec<Arc<JSObject>>,
+    /// Young generation objects still waiting to be swept
+    pending_young: VecDeque<Arc<JSObject>>,
+    /// Old generation objects still waiting to be swept
+    pending_old: VecDeque<Arc<JSObject>>,
+    /// Whether unreachable old generation objects are freed in this cycle
+    major: bool,
+    /// Number of objects freed so far in this cycle
+    freed: usize,
+    /// When the cycle started
+    started: Option<Instant>,
+    /// Whether finalizers of objects freed in this cycle are deferred
+    defer_finalizers: bool,
+    /// Age at which young survivors of this cycle are promoted
+    promotion_age: u8,
+    /// Collections that objects freed in this cycle stay condemned for
+    free_grace: u32,
+    /// Unreachable objects swept so far, freed together when the cycle finishes
+    garbage: Vec<Arc<JSObject>>,
+}
+
+impl IncrementalState {
+    fn new() -> Self {
+        Self {
+            phase: CollectionPhase::Idle,
+            mark_stack: Vec::new(),
+            pending_young: VecDeque::new(),
+            pending_old: VecDeque::new(),
+            major: false,
+            freed: 0,
+            started: None,
+            defer_finalizers: false,
+            promotion_age: 0,
+            free_grace: 0,
+            garbage: Vec::new(),
+        }
+    }
+}
+
+/// Number of recent pause samples kept for percentile reporting
+const PAUSE_SAMPLE_CAPACITY: usize = 256;
+
+/// Generation vectors with no more capacity than this are never shrunk
+const COMPACT_MIN_CAPACITY: usize = 256;
+
+/// Host callback to run once a weakly watched object has been freed
+pub type WeakCallback = extern "C" fn(user: *mut c_void);
+
+/// Host callback run when an allocation would exceed `max_heap_kb`
+///
+/// Returns nonzero if it released memory and the allocation should be retried.
+pub type OomHandler = extern "C" fn() -> c_int;
+
+/// Host callback run for each object promoted to the old generation, with the
+/// number of young collections it survived
+pub type PromotionCallback = extern "C" fn(obj: *const JSObject, age: u32);
+
+/// Host sink for collector log messages, one line per call
+pub type LogCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Host hook that reports the values held outside the object graph
+pub type TraceCallback = Arc<dyn Fn(&mut Tracer) + Send + Sync>;
+
+/// Collects objects that host containers keep alive during marking
+///
+/// Passed to callbacks registered with `GarbageCollector::add_trace_callback`.
+pub struct Tracer {
+    /// Newly marked objects whose references still have to be traced
+    mark_stack: Vec<Arc<JSObject>>,
+}
+
+impl Tracer {
+    fn new() -> Self {
+        Self { mark_stack: Vec::new() }
+    }
+    
+    /// Keep the object `value` refers to alive; primitives are ignored
+    pub fn trace_value(&mut self, value: &JSValue) {
+        if let JSValue::Object(handle) = value {
+            self.trace_object(handle);
+        }
+    }
+    
+    /// Keep `handle`'s object, and everything it references, alive
+    pub fn trace_object(&mut self, handle: &JSObjectHandle) {
+        if handle.ptr.try_mark() {
+            self.mark_stack.push(handle.ptr.clone());
+        }
+    }
+}
+
+/// A `WeakMap`: values keyed by objects without keeping the keys alive
+///
+/// Create one with `GarbageCollector::create_weak_map` so the collector can see
+/// it. Values are kept alive while their entry exists, and major collections
+/// drop the entries whose key has been freed. A value that references its own
+/// key keeps that key alive, since entries aren't traced as ephemerons.
+pub struct JSWeakMap {
+    /// Entries by key address; the weak key confirms the address wasn't reused
+    entries: Mutex<HashMap<usize, (Weak<JSObject>, JSValue)>>,
+}
+
+impl JSWeakMap {
+    fn new() -> Self {
+        Self { entries: Mutex::new(HashMap::new()) }
+    }
+    
+    /// Associate `value` with `key`, replacing any previous value
+    pub fn set(&self, key: &JSObjectHandle, value: JSValue) {
+        self.entries.lock().insert(Arc::as_ptr(&key.ptr) as usize, (Arc::downgrade(&key.ptr), value));
+    }
+    
+    /// Get the value associated with `key`
+    pub fn get(&self, key: &JSObjectHandle) -> Option<JSValue> {
+        let entries = self.entries.lock();
+        let (weak_key, value) = entries.get(&(Arc::as_ptr(&key.ptr) as usize))?;
+        // A dead entry at the same address belonged to a freed object
+        (weak_key.strong_count() > 0).then(|| value.clone())
+    }
+    
+    /// Whether `key` has an associated value
+    pub fn has(&self, key: &JSObjectHandle) -> bool {
+        self.get(key).is_some()
+    }
+    
+    /// Remove `key`'s entry, returning whether there was one
+    pub fn delete(&self, key: &JSObjectHandle) -> bool {
+        let mut entries = self.entries.lock();
+        match entries.remove(&(Arc::as_ptr(&key.ptr) as usize)) {
+            Some((weak_key, _)) => weak_key.strong_count() > 0,
+            None => false,
+        }
+    }
+    
+    /// Get the number of entries, including dead ones not yet pruned
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+    
+    /// Whether the map has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+    
+    /// Drop entries whose key has been freed, returning how many were dropped
+    fn prune(&self) -> usize {
+        // Dropped values may free objects, so release them outside the lock
+        let dead: Vec<_> = {
+            let mut entries = self.entries.lock();
+            let dead_keys: Vec<usize> = entries.iter()
+                .filter(|(_, (weak_key, _))| weak_key.strong_count() == 0)
+                .map(|(&key, _)| key)
+                .collect();
+            dead_keys.iter().filter_map(|key| entries.remove(key)).collect()
+        };
+        dead.len()
+    }
+    
+    /// Mark every value held by the map
+    fn trace(&self, tracer: &mut Tracer) {
+        for (_, value) in self.entries.lock().values() {
+            tracer.trace_value(value);
+        }
+    }
+}
+
+/// A host weak reference waiting for its target to be freed
+struct WeakRegistration {
+    target: Weak<JSObject>,
+    callback: WeakCallback,
+    user: *mut c_void,
+}
+
+/// Handle to the background collection thread
+#[cfg(feature = "background-gc")]
+struct BackgroundCollector {
+    /// Dropping or sending on this wakes the thread and tells it to exit
+    stop: crossbeam::channel::Sender<()>,
+    /// The collection thread, joined on stop
+    thread: std::thread::JoinHandle<()>,
+}
+
+/// Generational garbage collector for JavaScript objects
+pub struct GarbageCollector {
+    /// Young generation objects (recently allocated)
+    young_generation: Mutex<Vec<Arc<JSObject>>>,
+    
+    /// Old generation objects (survived several collections)
+    old_generation: Mutex<Vec<Arc<JSObject>>>,
+    
+    /// Objects that should never be collected (roots)
+    roots: Mutex<HashSet<*const JSObject>>,
+    
+    /// Configuration options
+    config: RwLock<GCConfiguration>,
+    
+    /// Collection statistics
+    stats: RwLock<GCStatistics>,
+    
+    /// Whether the GC is currently running a collection
+    collecting: Mutex<bool>,
+    
+    /// State of the in-progress incremental collection, if any
+    incremental: Mutex<IncrementalState>,
+    
+    /// When the last young collection finished and whether it freed little
+    last_young_collection: Mutex<Option<(Instant, bool)>>,
+    
+    /// Unreachable objects waiting for their finalizers to run
+    finalizer_queue: Mutex<VecDeque<Arc<JSObject>>>,
+    
+    /// Unreachable objects kept for inspection, with the collections left before they're dropped
+    condemned: Mutex<Vec<(Arc<JSObject>, u32)>>,
+    
+    /// Objects created by this collector, by id
+    objects_by_id: Mutex<HashMap<u64, Weak<JSObject>>>,
+    
+    /// Host weak references whose callbacks haven't fired yet
+    weak_callbacks: Mutex<Vec<WeakRegistration>>,
+    
+    /// Host hooks that mark values held outside the object graph
+    trace_callbacks: Mutex<Vec<TraceCallback>>,
+    
+    /// Weak maps created by this collector, pruned on major collections
+    weak_maps: Mutex<Vec<Weak<JSWeakMap>>>,
+    
+    /// Most recent pause durations in microseconds, oldest first
+    pause_samples: Mutex<VecDeque<u64>>,
+    
+    /// Called when the heap cap is hit even after collecting
+    oom_handler: Mutex<Option<OomHandler>>,
+    
+    /// Called for each promoted object once collector locks are released
+    promotion_callback: Mutex<Option<PromotionCallback>>,
+    
+    /// Promotions waiting to be reported to the promotion callback
+    promoted: Mutex<Vec<(Arc<JSObject>, u8)>>,
+    
+    /// Set by `begin_shutdown`; no new collections start once it's set
+    shutting_down: AtomicBool,
+    
+    /// Write barrier hits taken from old objects so far
+    write_barrier_hits: AtomicUsize,
+    
+    /// `config.effective_log_level()`, readable while the config is locked
+    log_level: AtomicU8,
+    
+    /// Receives log messages instead of stdout, if set
+    log_callback: Mutex<Option<LogCallback>>,
+    
+    /// Background collection thread, if running
+    #[cfg(feature = "background-gc")]
+    background: Mutex<Option<BackgroundCollector>>,
+    
+    /// The `Arc` this collector lives in, handed to the background thread
+    #[cfg(feature = "background-gc")]
+    weak_self: Weak<GarbageCollector>,
+    
+    /// Objects that survived the most recent collection cycle, by id (test-only)
+    #[cfg(test)]
+    last_survivors: Mutex<HashMap<u64, Weak<JSObject>>>,
+}
+
+// Safety: root pointers are only dereferenced while marking. The user pointers
+// of weak registrations are never dereferenced here, only handed back to the
+// host callback, which `register_weak` requires to accept them on whichever
+// thread collects. All other shared state is guarded by locks, so the collector
+// can be shared across threads
+unsafe impl Send for GarbageCollector {}
+unsafe impl Sync for GarbageCollector {}
+
+impl GarbageCollector {
+    /// Create a new garbage collector with default configuration
+    pub fn new() -> Arc<Self> {
+        // Only the background thread needs a handle back to the collector
+        Arc::new_cyclic(|#[cfg_attr(not(feature = "background-gc"), allow(unused_variables))] weak_self| Self {
+            young_generation: Mutex::new(Vec::new()),
+            old_generation: Mutex::new(Vec::new()),
+            roots: Mutex::new(HashSet::new()),
+            config: RwLock::new(GCConfiguration::default()),
+            stats: RwLock::new(GCStatistics::default()),
+            collecting: Mutex::new(false),
+            incremental: Mutex::new(IncrementalState::new()),
+            last_young_collection: Mutex::new(None),
+            finalizer_queue: Mutex::new(VecDeque::new()),
+            condemned: Mutex::new(Vec::new()),
+            objects_by_id: Mutex::new(HashMap::new()),
+            weak_callbacks: Mutex::new(Vec::new()),
+            trace_callbacks: Mutex::new(Vec::new()),
+            weak_maps: Mutex::new(Vec::new()),
+            pause_samples: Mutex::new(VecDeque::with_capacity(PAUSE_SAMPLE_CAPACITY)),
+            oom_handler: Mutex::new(None),
+            promotion_callback: Mutex::new(None),
+            promoted: Mutex::new(Vec::new()),
+            shutting_down: AtomicBool::new(false),
+            write_barrier_hits: AtomicUsize::new(0),
+            log_level: AtomicU8::new(GCConfiguration::default().effective_log_level()),
+            log_callback: Mutex::new(None),
+            #[cfg(feature = "background-gc")]
+            background: Mutex::new(None),
+            #[cfg(feature = "background-gc")]
+            weak_self: weak_self.clone(),
+            #[cfg(test)]
+            last_survivors: Mutex::new(HashMap::new()),
+        })
+    }
+    
+    /// Update the GC configuration, returning the previous one
+    pub fn configure(&self, config: GCConfiguration) -> GCConfiguration {
+        let mut current_config = self.config.write();
+        self.log_level.store(config.effective_log_level(), Ordering::Relaxed);
+        mem::replace(&mut *current_config, config)
+    }
+    
+    /// Set how much the collector logs, from `GCConfiguration::LOG_OFF` to `LOG_TRACE`
+    pub fn set_log_level(&self, log_level: u8) {
+        let mut config = self.config.write();
+        config.log_level = log_level;
+        self.log_level.store(config.effective_log_level(), Ordering::Relaxed);
+    }
+    
+    /// Send log messages to `callback` instead of stdout
+    ///
+    /// Trace messages are logged mid-sweep, so the callback may run while the
+    /// collector holds its locks and must not call back into the collector.
+    pub fn set_log_callback(&self, callback: LogCallback) {
+        *self.log_callback.lock() = Some(callback);
+    }
+    
+    /// Log the message built by `message` if the log level is at least `level`
+    pub(crate) fn log(&self, level: u8, message: impl FnOnce() -> String) {
+        if self.log_level.load(Ordering::Relaxed) < level {
+            return;
+        }
+        let message = message();
+        let callback = self.log_callback.lock().clone();
+        match callback {
+            Some(callback) => callback(&message),
+            None => println!("{}", message),
+        }
+    }
+    
+    /// Log the size of each generation at the detailed level
+    fn log_generation_sizes(&self) {
+        self.log(GCConfiguration::LOG_DETAILED, || {
+            let young_count = self.young_generation.lock().len();
+            let old_count = self.old_generation.lock().len();
+            let stats = self.stats.read();
+            format!("  young generation: {} objects, {}KB; old generation: {} objects, {}KB",
+                    young_count, stats.young_generation_size / 1024,
+                    old_count, stats.old_generation_size / 1024)
+        });
+    }
+    
+    /// Run `f` with `config` applied, then restore the previous configuration
+    ///
+    /// The previous configuration is restored even if `f` panics.
+    pub fn with_temp_config<F: FnOnce()>(&self, config: GCConfiguration, f: F) {
+        struct Restore<'a> {
+            gc: &'a GarbageCollector,
+            previous: Option<GCConfiguration>,
+        }
+        
+        impl Drop for Restore<'_> {
+            fn drop(&mut self) {
+                if let Some(previous) = self.previous.take() {
+                    self.gc.configure(previous);
+                }
+            }
+        }
+        
+        let _restore = Restore { gc: self, previous: Some(self.configure(config)) };
+        f();
+    }
+    
+    /// Apply tuning overrides from `JSGC_*` environment variables
+    ///
+    /// Recognizes `JSGC_YOUNG_KB`, `JSGC_OLD_KB`, `JSGC_MAX_PAUSE_MS`,
+    /// `JSGC_PROMOTION_AGE`, `JSGC_INCREMENTAL`, `JSGC_VERBOSE` and
+    /// `JSGC_LOG_LEVEL`; booleans
+    /// accept `1`/`0` or `true`/`false`. Unset or unparsable variables leave the
+    /// current value alone, so this is safe to call unconditionally at startup.
+    pub fn configure_from_env(&self) {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok()?.trim().parse().ok()
+        }
+        fn flag(name: &str) -> Option<bool> {
+            match std::env::var(name).ok()?.trim() {
+                "1" | "true" => Some(true),
+                "0" | "false" => Some(false),
+                _ => None,
+            }
+        }
+        
+        let mut config = self.config.write();
+        if let Some(kb) = var("JSGC_YOUNG_KB") {
+            config.young_gen_threshold_kb = kb;
+        }
+        if let Some(kb) = var("JSGC_OLD_KB") {
+            config.old_gen_threshold_kb = kb;
+        }
+        if let Some(ms) = var("JSGC_MAX_PAUSE_MS") {
+            config.max_pause_ms = ms;
+        }
+        if let Some(age) = var("JSGC_PROMOTION_AGE") {
+            config.promotion_age = age;
+        }
+        if let Some(incremental) = flag("JSGC_INCREMENTAL") {
+            config.incremental = incremental;
+        }
+        if let Some(verbose) = flag("JSGC_VERBOSE") {
+            config.verbose = verbose;
+        }
+        if let Some(log_level) = var("JSGC_LOG_LEVEL") {
+            config.log_level = log_level;
+        }
+        self.log_level.store(config.effective_log_level(), Ordering::Relaxed);
+    }
+    
+    /// Get the current configuration, including any automatic threshold adjustments
+    pub fn config(&self) -> GCConfiguration {
+        self.config.read().clone()
+    }
+    
+    /// Size the young threshold from the live heap after each major collection
+    ///
+    /// The next threshold becomes the live bytes times `heap_growth_factor`,
+    /// clamped between `min_young_gen_threshold_kb` and `max_young_gen_threshold_kb`,
+    /// so the heap tracks the working set. A factor of 0 turns the policy off.
+    pub fn set_growth_policy(&self, heap_growth_factor: f64) {
+        self.config.write().heap_growth_factor = heap_growth_factor;
+    }
+    
+    /// Get current statistics
+    pub fn statistics(&self) -> GCStatistics {
+        let mut stats = *self.stats.read();
+        stats.write_barrier_hits = self.count_write_barrier_hits();
+        stats
+    }
+    
+    /// Add up the write barrier hits recorded on the old generation so far
+    ///
+    /// Objects keep their own counts so the barrier doesn't need to know their
+    /// collector; they are moved here when counted or freed.
+    fn count_write_barrier_hits(&self) -> usize {
+        let taken: usize = self.old_generation.lock().iter().map(|obj| obj.take_barrier_hits()).sum();
+        self.write_barrier_hits.fetch_add(taken, Ordering::Relaxed) + taken
+    }
+    
+    /// Count the old objects currently in the remembered set
+    ///
+    /// Young collections treat these objects as extra roots, since the old
+    /// generation isn't swept in a minor cycle.
+    pub fn remembered_set_size(&self) -> usize {
+        self.old_generation.lock().iter().filter(|obj| obj.is_remembered()).count()
+    }
+    
+    /// Create a new JavaScript object and add it to the young generation
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_heap_kb` is set and the heap is still full after collecting
+    /// and consulting the OOM handler; use `try_create_object` to handle that.
+    pub fn create_object(&self, obj_type: JSObjectType) -> JSObjectHandle {
+        self.try_create_object(obj_type)
+            .expect("JS heap exceeds max_heap_kb even after collecting")
+    }
+    
+    /// Create a new JavaScript object, or `None` if the heap cap can't be met
+    ///
+    /// When the allocation would push the heap past `max_heap_kb`, a full
+    /// collection runs first. If that isn't enough, the OOM handler is called and,
+    /// if it reports freeing memory, the collection and check are retried once.
+    pub fn try_create_object(&self, obj_type: JSObjectType) -> Option<JSObjectHandle> {
+        if !self.make_room(mem::size_of::<JSObject>()) {
+            return None;
+        }
+        Some(self.allocate(obj_type))
+    }
+    
+    /// Set the handler called when an allocation would exceed `max_heap_kb`
+    pub fn set_oom_handler(&self, handler: OomHandler) {
+        *self.oom_handler.lock() = Some(handler);
+    }
+    
+    /// Whether `size` more bytes fit under the heap cap
+    fn has_room(&self, size: usize) -> bool {
+        let cap_kb = self.config.read().max_heap_kb;
+        if cap_kb == 0 {
+            return true;
+        }
+        let stats = self.stats.read();
+        stats.young_generation_size + stats.old_generation_size + size <= cap_kb * 1024
+    }
+    
+    /// Collect, and then ask the host, until `size` more bytes fit under the heap cap
+    fn make_room(&self, size: usize) -> bool {
+        if self.has_room(size) {
+            return true;
+        }
+        
+        // Full collections, since the old generation may hold most of the garbage
+        self.collect_cycle(true);
+        if self.has_room(size) {
+            return true;
+        }
+        
+        // Copy the handler out so it can call back into the collector
+        let handler = *self.oom_handler.lock();
+        match handler {
+            Some(handler) if handler() != 0 => {
+                self.collect_cycle(true);
+                self.has_room(size)
+            }
+            _ => false,
+        }
+    }
+    
+    /// Create a shallow copy of `handle` that shares its shape, tracked in the young generation
+    ///
+    /// See `JSObject::shallow_clone` for what is copied. Like `create_object`,
+    /// this may trigger a young collection, so root the copy to keep it.
+    pub fn clone_object(&self, handle: &JSObjectHandle) -> JSObjectHandle {
+        self.track_new(handle.ptr.shallow_clone())
+    }
+    
+    /// Track a new object in the young generation, collecting if it has outgrown its threshold
+    fn allocate(&self, obj_type: JSObjectType) -> JSObjectHandle {
+        self.track_new(JSObject::new(obj_type))
+    }
+    
+    /// Track a freshly created object in the young generation
+    fn track_new(&self, obj: Arc<JSObject>) -> JSObjectHandle {
+        self.objects_by_id.lock().insert(obj.id(), Arc::downgrade(&obj));
+        
+        // Track the object in the young generation
+        {
+            let mut incremental = self.incremental.lock();
+            let mut young = self.young_generation.lock();
+            young.push(obj.clone());
+            
+            // Objects allocated while marking are live for the current cycle. A
+            // clone already holds references, so they are traced like any other
+            // marked object rather than counted as done.
+            if incremental.phase == CollectionPhase::Marking && obj.try_mark() {
+                incremental.mark_stack.push(obj.clone());
+            }
+            
+            // Update allocation statistics
+            let mut stats = self.stats.write();
+            stats.allocation_count += 1;
+            stats.young_generation_size += self.estimate_object_size(&obj);
+            
+            // Check if we need to trigger a young generation collection
+            if stats.young_generation_size > self.config.read().young_gen_threshold_kb * 1024 {
+                // Drop the lock before collecting
+                drop(stats);
+                drop(young);
+                drop(incremental);
+                self.collect_young();
+            }
+        }
+        
+        JSObjectHandle { ptr: obj }
+    }
+    
+    /// Add a root object that shouldn't be collected
+    pub fn add_root(&self, ptr: *mut JSObject) {
+        if !ptr.is_null() {
+            let mut roots = self.roots.lock();
+            roots.insert(ptr as *const JSObject);
+        }
+    }
+    
+    /// Remove a root object
+    pub fn remove_root(&self, ptr: *mut JSObject) {
+        if !ptr.is_null() {
+            let mut roots = self.roots.lock();
+            roots.remove(&(ptr as *const JSObject));
+        }
+    }
+    
+    /// Trigger a garbage collection
+    ///
+    /// With `incremental` configured this does one `gc_step`, and later calls
+    /// resume the cycle until it completes; otherwise both generations are
+    /// collected before returning.
+    pub fn collect(&self) {
+        if self.config.read().incremental {
+            if self.gc_step() == CollectProgress::Complete {
+                self.compact_generations();
+            }
+        } else {
+            self.collect_cycle(false);
+        }
+    }
+    
+    /// Do one slice of incremental collection, bounded by `max_pause_ms`
+    ///
+    /// Like `try_collect` with the configured pause budget, so a slice may
+    /// overrun it by at most one object's worth of work.
+    pub fn gc_step(&self) -> CollectProgress {
+        let budget_us = self.config.read().max_pause_ms.saturating_mul(1000);
+        self.try_collect(budget_us)
+    }
+    
+    /// Collect both generations, including the old one when `force_major` even if
+    /// it's under its threshold
+    fn collect_cycle(&self, force_major: bool) {
+        if self.is_shutting_down() {
+            return;
+        }
+        
+        // Make sure we're not already collecting. The lock only guards the flag,
+        // so weak callbacks fired during the collection can safely call back in.
+        {
+            let mut collecting = self.collecting.lock();
+            if *collecting {
+                return;
+            }
+            *collecting = true;
+        }
+        
+        if self.incremental.lock().phase != CollectionPhase::Idle {
+            // Finish the in-progress incremental cycle instead of starting another
+            self.try_collect(u64::MAX);
+        } else {
+            // Collect both generations
+            self.collect_young();
+            if force_major {
+                self.collect_old_now();
+            } else {
+                self.collect_old();
+            }
+        }
+        
+        // Update stats
+        self.stats.write().collection_count += 1;
+        self.compact_generations();
+        
+        // Reset collection flag
+        *self.collecting.lock() = false;
+    }
+    
+    /// Release the generations' spare capacity where it dwarfs what they hold
+    ///
+    /// Young collections keep the young generation's allocation for the next
+    /// round of allocations, so after a burst of short-lived objects it stays
+    /// at its peak. Only full collections call this, since shrinking means
+    /// reallocating, and only vectors over `compact_threshold_ratio` shrink.
+    /// Returns the bytes released.
+    fn compact_generations(&self) -> usize {
+        let ratio = self.config.read().compact_threshold_ratio;
+        if ratio <= 0.0 {
+            return 0;
+        }
+        
+        let mut released = 0;
+        for generation in [&self.young_generation, &self.old_generation] {
+            let mut objects = generation.lock();
+            let capacity = objects.capacity();
+            if capacity > COMPACT_MIN_CAPACITY && capacity as f64 > objects.len() as f64 * ratio {
+                objects.shrink_to_fit();
+                released += (capacity - objects.capacity()) * mem::size_of::<Arc<JSObject>>();
+            }
+        }
+        
+        if released > 0 {
+            self.log(GCConfiguration::LOG_DETAILED, || format!("Released {} bytes of generation capacity", released));
+        }
+        released
+    }
+    
+    /// Clean up as thoroughly as possible, for hosts with a long idle period
+    ///
+    /// Finishes any incremental cycle, collects both generations, sweeps unused
+    /// strings from the global interner (which every collector shares), and
+    /// compacts the surviving objects' storage. This is far slower than `collect`.
+    pub fn run_full_maintenance(&self) -> MaintenanceReport {
+        let before = self.statistics();
+        if self.incremental.lock().phase != CollectionPhase::Idle {
+            self.try_collect(u64::MAX);
+        }
+        self.collect_cycle(true);
+        let after = self.statistics();
+        
+        let strings_swept = string_interner::sweep_unused_strings();
+        
+        let objects: Vec<Arc<JSObject>> = {
+            let young = self.young_generation.lock();
+            let old = self.old_generation.lock();
+            young.iter().chain(old.iter()).cloned().collect()
+        };
+        let bytes_compacted = objects.iter().map(|obj| obj.compact()).sum();
+        
+        MaintenanceReport {
+            objects_freed: after.objects_freed - before.objects_freed,
+            weak_entries_pruned: after.weak_entries_pruned - before.weak_entries_pruned,
+            strings_swept,
+            bytes_compacted,
+        }
+    }
+    
+    /// Run a young collection if the young generation has outgrown its threshold
+    ///
+    /// Hosts call this at natural boundaries (such as the end of a task) to spread
+    /// pauses out instead of relying only on allocation-time triggers. Returns
+    /// whether a collection ran.
+    pub fn collect_if_needed(&self) -> bool {
+        if self.is_shutting_down() || self.incremental.lock().phase != CollectionPhase::Idle {
+            return false;
+        }
+        
+        let threshold = self.config.read().young_gen_threshold_kb * 1024;
+        if self.stats.read().young_generation_size <= threshold {
+            return false;
+        }
+        
+        self.collect_young();
+        true
+    }
+    
+    /// Start a background thread that calls `collect_if_needed` every `interval`
+    ///
+    /// Does nothing if the thread is already running. The thread is stopped and
+    /// joined by `stop_background_gc` or when the collector is dropped. It only
+    /// holds a weak reference, so it never keeps the collector alive.
+    #[cfg(feature = "background-gc")]
+    pub fn start_background_gc(&self, interval: Duration) {
+        let mut background = self.background.lock();
+        if background.is_some() {
+            return;
+        }
+        
+        let (stop, stopped) = crossbeam::channel::bounded::<()>(1);
+        let gc = self.weak_self.clone();
+        let thread = std::thread::Builder::new()
+            .name("js-gc".to_string())
+            .spawn(move || {
+                while let Err(crossbeam::channel::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    // Exit once the collector has been dropped
+                    let Some(gc) = gc.upgrade() else {
+                        break;
+                    };
+                    gc.collect_if_needed();
+                }
+            })
+            .expect("failed to spawn background GC thread");
+        
+        *background = Some(BackgroundCollector { stop, thread });
+    }
+    
+    /// Stop the background collection thread and wait for it to exit
+    #[cfg(feature = "background-gc")]
+    pub fn stop_background_gc(&self) {
+        // Take the handle first so the thread can finish a collection without us holding the lock
+        let background = self.background.lock().take();
+        if let Some(BackgroundCollector { stop, thread }) = background {
+            let _ = stop.send(());
+            // If the host let go of the collector mid-collection, the thread drops
+            // the last reference itself and can't wait for its own exit
+            if thread.thread().id() != std::thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+    
+    /// Stop collecting so the collector can be torn down safely
+    ///
+    /// An incremental cycle in progress is abandoned at its next step: objects
+    /// not yet swept are kept and unmarked rather than freed, leaving the heap
+    /// consistent. Collections requested afterwards do nothing, and the
+    /// background thread, if any, is stopped.
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        #[cfg(feature = "background-gc")]
+        self.stop_background_gc();
+    }
+    
+    /// Whether `begin_shutdown` has been called
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+    
+    /// Number of unreachable objects whose finalizers are waiting to run
+    ///
+    /// Only grows when `deferred_finalization` is enabled. Hosts can check this
+    /// before a latency-sensitive section and pump the queue with `run_finalizers`.
+    pub fn pending_finalizers(&self) -> usize {
+        self.finalizer_queue.lock().len()
+    }
+    
+    /// Run up to `max` queued finalizers, returning how many ran
+    pub fn run_finalizers(&self, max: usize) -> usize {
+        let mut ran = 0;
+        while ran < max {
+            // Pop under the lock but finalize outside it, so finalizers can call back in
+            let Some(obj) = self.finalizer_queue.lock().pop_front() else {
+                break;
+            };
+            self.free_garbage(vec![obj]);
+            ran += 1;
+        }
+        
+        if ran > 0 {
+            self.run_weak_callbacks();
+        }
+        ran
+    }
+    
+    /// Call `callback(user)` once `target` has been freed
+    ///
+    /// Unlike finalizers, the callback gets no access to the object; it lets the
+    /// host clear its own pointers to it. Callbacks run after a collection frees
+    /// the target, with no collector locks held, on whichever thread collected,
+    /// so `user` must be safe to use from any thread.
+    pub fn register_weak(&self, target: &JSObjectHandle, callback: WeakCallback, user: *mut c_void) {
+        self.weak_callbacks.lock().push(WeakRegistration {
+            target: Arc::downgrade(&target.ptr),
+            callback,
+            user,
+        });
+    }
+    
+    /// Register a hook that marks `JSValue`s the host stores in its own containers
+    ///
+    /// Every collection calls `callback` while marking, and it should pass each
+    /// value it holds to `Tracer::trace_value`; those objects then survive as if
+    /// they were roots. Incremental cycles call it again once marking runs out of
+    /// work, to pick up values stored since it started. Collector locks are held
+    /// while it runs, so it must not call back into the collector.
+    pub fn add_trace_callback<F>(&self, callback: F)
+    where
+        F: Fn(&mut Tracer) + Send + Sync + 'static,
+    {
+        self.trace_callbacks.lock().push(Arc::new(callback));
+    }
+    
+    /// Run the host trace callbacks and trace weak map values, returning the
+    /// objects newly marked
+    fn trace_host(&self) -> Vec<Arc<JSObject>> {
+        let callbacks = self.trace_callbacks.lock().clone();
+        let mut tracer = Tracer::new();
+        for callback in callbacks {
+            callback(&mut tracer);
+        }
+        for map in self.live_weak_maps() {
+            map.trace(&mut tracer);
+        }
+        tracer.mark_stack
+    }
+    
+    /// Create a weak map whose dead entries are pruned by this collector
+    pub fn create_weak_map(&self) -> Arc<JSWeakMap> {
+        let map = Arc::new(JSWeakMap::new());
+        self.weak_maps.lock().push(Arc::downgrade(&map));
+        map
+    }
+    
+    /// Get the weak maps still in use, forgetting dropped ones
+    fn live_weak_maps(&self) -> Vec<Arc<JSWeakMap>> {
+        let mut maps = self.weak_maps.lock();
+        maps.retain(|map| map.strong_count() > 0);
+        maps.iter().filter_map(Weak::upgrade).collect()
+    }
+    
+    /// Drop weak map entries whose keys have been freed and count them
+    fn prune_weak_entries(&self) {
+        let pruned: usize = self.live_weak_maps().iter().map(|map| map.prune()).sum();
+        self.stats.write().weak_entries_pruned += pruned;
+    }
+    
+    /// Call `callback(obj, age)` each time an object is promoted to the old generation
+    ///
+    /// Callbacks run once the collection step that promoted the objects has
+    /// released its locks, so they may call back into the collector.
+    pub fn set_promotion_callback(&self, callback: PromotionCallback) {
+        *self.promotion_callback.lock() = Some(callback);
+    }
+    
+    /// Remember a promotion to report once locks are released
+    fn record_promotion(&self, obj: &Arc<JSObject>, age: u8) {
+        if self.promotion_callback.lock().is_some() {
+            self.promoted.lock().push((obj.clone(), age));
+        }
+    }
+    
+    /// Report queued promotions to the promotion callback
+    fn notify_promotions(&self) {
+        let promoted = mem::take(&mut *self.promoted.lock());
+        let callback = *self.promotion_callback.lock();
+        if let Some(callback) = callback {
+            for (obj, age) in promoted {
+                callback(Arc::as_ptr(&obj), age as u32);
+            }
+        }
+    }
+    
+    /// Fire and forget the weak callbacks whose targets have been freed
+    fn run_weak_callbacks(&self) {
+        let fired: Vec<WeakRegistration> = {
+            let mut registrations = self.weak_callbacks.lock();
+            let (dead, live) = mem::take(&mut *registrations)
+                .into_iter()
+                .partition(|registration| registration.target.strong_count() == 0);
+            *registrations = live;
+            dead
+        };
+        
+        for registration in fired {
+            (registration.callback)(registration.user);
+        }
+    }
+    
+    /// Perform at most `budget_us` microseconds of incremental collection work
+    ///
+    /// The first call starts a new cycle; subsequent calls resume it where the
+    /// previous one stopped. At least one unit of work is done per call so the
+    /// cycle always makes progress. This never blocks for a whole collection, so
+    /// a single-threaded async host can poll it from a task and yield between
+    /// calls until it reports `Complete`.
+    pub fn try_collect(&self, budget_us: u64) -> CollectProgress {
+        let start_time = Instant::now();
+        let budget = Duration::from_micros(budget_us);
+        let mut state = self.incremental.lock();
+        
+        if state.phase == CollectionPhase::Idle {
+            if self.is_shutting_down() {
+                return CollectProgress::Complete;
+            }
+            self.begin_incremental(&mut state, true);
+        }
+        
+        loop {
+            if self.is_shutting_down() {
+                self.abort_incremental(&mut state);
+                drop(state);
+                self.record_pause(start_time.elapsed());
+                self.notify_promotions();
+                return CollectProgress::Complete;
+            }
+            
+            match state.phase {
+                CollectionPhase::Marking => self.incremental_mark_step(&mut state),
+                CollectionPhase::Sweeping => {
+                    if self.incremental_sweep_step(&mut state) {
+                        self.finish_incremental(&mut state);
+                        drop(state);
+                        self.record_pause(start_time.elapsed());
+                        self.notify_promotions();
+                        self.run_weak_callbacks();
+                        return CollectProgress::Complete;
+                    }
+                }
+                CollectionPhase::Idle => return CollectProgress::Complete,
+            }
+            
+            if start_time.elapsed() >= budget {
+                drop(state);
+                self.record_pause(start_time.elapsed());
+                self.notify_promotions();
+                return CollectProgress::InProgress;
+            }
+        }
+    }
+    
+    /// Run a young collection in steps of at most `max_objects` young objects
+    ///
+    /// The first call marks everything reachable and starts a minor cycle; each
+    /// call then sweeps up to `max_objects` young objects, resuming where the last
+    /// one stopped. Old generation objects are only unmarked, which happens in the
+    /// call that sweeps the last young object. Returns true once the cycle is
+    /// complete. Unlike `try_collect`, step sizes are deterministic. If a cycle
+    /// started by `try_collect` is in progress, this continues that cycle instead.
+    pub fn collect_young_incremental(&self, max_objects: usize) -> bool {
+        let start_time = Instant::now();
+        let mut state = self.incremental.lock();
+        
+        if state.phase == CollectionPhase::Idle {
+            if self.is_shutting_down() {
+                return true;
+            }
+            self.begin_incremental(&mut state, false);
+        }
+        
+        // Marking isn't bounded by object count; finish it before sweeping
+        while state.phase == CollectionPhase::Marking && !self.is_shutting_down() {
+            self.incremental_mark_step(&mut state);
+        }
+        
+        let mut swept = 0;
+        while swept < max_objects.max(1) && !state.pending_young.is_empty() && !self.is_shutting_down() {
+            self.incremental_sweep_step(&mut state);
+            swept += 1;
+        }
+        
+        if self.is_shutting_down() {
+            self.abort_incremental(&mut state);
+            drop(state);
+            self.record_pause(start_time.elapsed());
+            self.notify_promotions();
+            return true;
+        }
+        
+        if !state.pending_young.is_empty() {
+            drop(state);
+            self.record_pause(start_time.elapsed());
+            self.notify_promotions();
+            return false;
+        }
+        
+        // Only old objects are left
+        while !self.incremental_sweep_step(&mut state) {}
+        self.finish_incremental(&mut state);
+        drop(state);
+        self.record_pause(start_time.elapsed());
+        self.notify_promotions();
+        self.run_weak_callbacks();
+        true
+    }
+    
+    /// Start an incremental cycle by marking the roots
+    ///
+    /// The cycle is major if `allow_major` and the old generation is over its
+    /// threshold. Minor cycles also trace from the remembered set.
+    fn begin_incremental(&self, state: &mut IncrementalState, allow_major: bool) {
+        let config = self.config.read();
+        self.log(GCConfiguration::LOG_DETAILED, || "Starting incremental collection".to_string());
+        
+        state.major = allow_major
+            && self.stats.read().old_generation_size >= config.old_gen_threshold_kb * 1024;
+        state.freed = 0;
+        state.started = Some(Instant::now());
+        state.defer_finalizers = config.deferred_finalization;
+        state.promotion_age = config.promotion_age;
+        state.free_grace = config.deferred_free_generations;
+        self.age_condemned(config.deferred_finalization, &mut state.garbage);
+        self.clear_marks();
+        #[cfg(test)]
+        self.last_survivors.lock().clear();
+        state.phase = CollectionPhase::Marking;
+        object::begin_incremental_marking();
+        
+        let roots: Vec<*const JSObject> = self.roots.lock().iter().cloned().collect();
+        for root_ptr in roots {
+            // Safety: root pointers come from live Arc<JSObject> allocations
+            let root = unsafe {
+                Arc::increment_strong_count(root_ptr);
+                Arc::from_raw(root_ptr)
+            };
+            if root.try_mark() {
+                state.mark_stack.push(root);
+            }
+        }
+        state.mark_stack.extend(self.trace_host());
+        
+        if !state.major {
+            for obj in self.old_generation.lock().iter().filter(|obj| obj.is_remembered()) {
+                for child in obj.referenced_objects() {
+                    if child.try_mark() {
+                        state.mark_stack.push(child);
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Trace the references of one marked object
+    fn incremental_mark_step(&self, state: &mut IncrementalState) {
+        if let Some(obj) = state.mark_stack.pop() {
+            for child in obj.referenced_objects() {
+                if child.try_mark() {
+                    state.mark_stack.push(child);
+                }
+            }
+        }
+        
+        if state.mark_stack.is_empty() {
+            // Host containers may have gained values since the cycle started
+            state.mark_stack = self.trace_host();
+        }
+        
+        if state.mark_stack.is_empty() {
+            // Marking is complete; sweep the objects that existed up to now
+            object::end_incremental_marking();
+            state.pending_young = mem::take(&mut *self.young_generation.lock()).into();
+            state.pending_old = mem::take(&mut *self.old_generation.lock()).into();
+            state.phase = CollectionPhase::Sweeping;
+        }
+    }
+    
+    /// Sweep one object, returning true once nothing is left to sweep
+    fn incremental_sweep_step(&self, state: &mut IncrementalState) -> bool {
+        if let Some(obj) = state.pending_young.pop_front() {
+            if obj.is_marked() {
+                obj.unmark();
+                #[cfg(test)]
+                self.last_survivors.lock().insert(obj.id(), Arc::downgrade(&obj));
+                if self.tenure(&obj, state.promotion_age) {
+                    self.old_generation.lock().push(obj);
+                } else {
+                    self.young_generation.lock().push(obj);
+                }
+            } else {
+                self.release_object(obj, state.defer_finalizers, state.free_grace, &mut state.garbage);
+                state.freed += 1;
+            }
+        } else if let Some(obj) = state.pending_old.pop_front() {
+            if obj.is_marked() || !state.major {
+                obj.unmark();
+                // Minor cycles keep old objects without collecting them
+                #[cfg(test)]
+                if state.major {
+                    self.last_survivors.lock().insert(obj.id(), Arc::downgrade(&obj));
+                }
+                self.old_generation.lock().push(obj);
+            } else {
+                self.release_object(obj, state.defer_finalizers, state.free_grace, &mut state.garbage);
+                state.freed += 1;
+            }
+        }
+        
+        state.pending_young.is_empty() && state.pending_old.is_empty()
+    }
+    
+    /// Abandon the incremental cycle, keeping every object not yet swept
+    fn abort_incremental(&self, state: &mut IncrementalState) {
+        match state.phase {
+            CollectionPhase::Idle => return,
+            CollectionPhase::Marking => {
+                object::end_incremental_marking();
+                state.mark_stack.clear();
+                for obj in self.young_generation.lock().iter().chain(self.old_generation.lock().iter()) {
+                    obj.unmark();
+                }
+            }
+            CollectionPhase::Sweeping => {
+                let mut young = self.young_generation.lock();
+                for obj in state.pending_young.drain(..) {
+                    obj.unmark();
+                    young.push(obj);
+                }
+                drop(young);
+                
+                let mut old = self.old_generation.lock();
+                for obj in state.pending_old.drain(..) {
+                    obj.unmark();
+                    old.push(obj);
+                }
+            }
+        }
+        
+        self.log(GCConfiguration::LOG_SUMMARY, || "Incremental collection abandoned for shutdown".to_string());
+        self.finish_incremental(state);
+    }
+    
+    /// Finish the incremental cycle and publish its statistics
+    fn finish_incremental(&self, state: &mut IncrementalState) {
+        state.phase = CollectionPhase::Idle;
+        self.free_garbage(mem::take(&mut state.garbage));
+        
+        let young_gen_size = self.young_generation.lock().iter()
+            .map(|obj| self.estimate_object_size(obj))
+            .sum();
+        let old_gen_size = self.old_generation.lock().iter()
+            .map(|obj| self.estimate_object_size(obj))
+            .sum();
+        
+        self.prune_object_ids();
+        if state.major {
+            self.prune_weak_entries();
+        }
+        
+        {
+            let mut stats = self.stats.write();
+            stats.objects_freed += state.freed;
+            stats.young_generation_size = young_gen_size;
+            stats.old_generation_size = old_gen_size;
+            stats.collection_count += 1;
+        }
+        
+        let elapsed = state.started.take().map(|t| t.elapsed().as_millis()).unwrap_or(0);
+        self.log(GCConfiguration::LOG_SUMMARY, || {
+            format!("Incremental collection completed in {}ms, freed {} objects", elapsed, state.freed)
+        });
+        self.log_generation_sizes();
+        
+        if state.major {
+            self.apply_growth_policy();
+        }
+    }
+    
+    /// Resize the young threshold from the live heap size, if a growth factor is set
+    fn apply_growth_policy(&self) {
+        let live_bytes = {
+            let stats = self.stats.read();
+            stats.young_generation_size + stats.old_generation_size
+        };
+        
+        let mut config = self.config.write();
+        if config.heap_growth_factor <= 0.0 {
+            return;
+        }
+        
+        let target_kb = (live_bytes as f64 * config.heap_growth_factor / 1024.0) as usize;
+        let max_kb = config.max_young_gen_threshold_kb.max(config.min_young_gen_threshold_kb);
+        config.young_gen_threshold_kb = target_kb.clamp(config.min_young_gen_threshold_kb, max_kb);
+    }
+    
+    /// Remember how long a collection pause took
+    fn record_pause(&self, pause: Duration) {
+        {
+            let mut samples = self.pause_samples.lock();
+            if samples.len() == PAUSE_SAMPLE_CAPACITY {
+                samples.pop_front();
+            }
+            samples.push_back(pause.as_micros() as u64);
+        }
+        
+        let pause_ns = u64::try_from(pause.as_nanos()).unwrap_or(u64::MAX);
+        let mut stats = self.stats.write();
+        stats.total_pause_ns = stats.total_pause_ns.saturating_add(pause_ns);
+        stats.max_pause_ns = stats.max_pause_ns.max(pause_ns);
+        stats.last_collection_ns = pause_ns;
+    }
+    
+    /// Get the 50th, 95th and 99th percentile of recent pauses, in microseconds
+    ///
+    /// Computed over the last few hundred pauses (each young or old collection and
+    /// each incremental step counts as one), using the nearest-rank method. All
+    /// zero before the first pause.
+    pub fn pause_percentiles(&self) -> (u64, u64, u64) {
+        let mut samples: Vec<u64> = self.pause_samples.lock().iter().copied().collect();
+        if samples.is_empty() {
+            return (0, 0, 0);
+        }
+        samples.sort_unstable();
+        
+        let rank = |percentile: usize| {
+            let index = (percentile * samples.len()).div_ceil(100);
+            samples[index.saturating_sub(1)]
+        };
+        (rank(50), rank(95), rank(99))
+    }
+    
+    /// Look up a live object created by this collector by its id
+    pub fn object_by_id(&self, id: u64) -> Option<JSObjectHandle> {
+        let ptr = self.objects_by_id.lock().get(&id)?.upgrade()?;
+        Some(JSObjectHandle { ptr })
+    }
+    
+    /// Forget the ids of objects that have been freed
+    fn prune_object_ids(&self) {
+        self.objects_by_id.lock().retain(|_, obj| obj.strong_count() > 0);
+    }
+    
+    /// Find which generation tracks an object, or `None` if it isn't tracked
+    pub fn generation_of(&self, handle: &JSObjectHandle) -> Option<Generation> {
+        let incremental = self.incremental.lock();
+        let is_same = |obj: &Arc<JSObject>| Arc::ptr_eq(obj, &handle.ptr);
+        
+        if self.young_generation.lock().iter().any(is_same)
+            || incremental.pending_young.iter().any(is_same) {
+            Some(Generation::Young)
+        } else if self.old_generation.lock().iter().any(is_same)
+            || incremental.pending_old.iter().any(is_same) {
+            Some(Generation::Old)
+        } else {
+            None
+        }
+    }
+    
+    /// Promote every young object to the old generation without collecting
+    ///
+    /// For hosts that know everything alive right now is long-lived, e.g. after
+    /// module initialization, so later young collections don't keep re-scanning
+    /// it. Garbage among those objects is only reclaimed by an old collection.
+    /// Does nothing while an incremental cycle is running. Returns the number
+    /// of objects promoted.
+    pub fn tenure_all_young(&self) -> usize {
+        let incremental = self.incremental.lock();
+        if incremental.phase != CollectionPhase::Idle {
+            return 0;
+        }
+        
+        let mut young = self.young_generation.lock();
+        let promoted = young.len();
+        let promoted_size: usize = young.iter()
+            .inspect(|obj| {
+                obj.set_tenured();
+                self.record_promotion(obj, obj.age());
+            })
+            .map(|obj| self.estimate_object_size(obj))
+            .sum();
+        self.old_generation.lock().append(&mut young);
+        drop(young);
+        drop(incremental);
+        
+        {
+            let mut stats = self.stats.write();
+            stats.young_generation_size = 0;
+            stats.old_generation_size += promoted_size;
+        }
+        self.notify_promotions();
+        promoted
+    }
+    
+    /// Track pre-built objects in `generation`, e.g. when restoring a snapshot
+    ///
+    /// The batch is added under one lock with a single size pass, and never
+    /// triggers a collection. Objects imported into the old generation count as
+    /// tenured. Objects already tracked by this collector must not be imported again.
+    pub fn import_objects(&self, objects: Vec<Arc<JSObject>>, generation: Generation) {
+        if objects.is_empty() {
+            return;
+        }
+        
+        {
+            let mut by_id = self.objects_by_id.lock();
+            for obj in &objects {
+                by_id.insert(obj.id(), Arc::downgrade(obj));
+            }
+        }
+        
+        let size: usize = objects.iter()
+            .map(|obj| self.estimate_object_size(obj))
+            .sum();
+        
+        let mut incremental = self.incremental.lock();
+        // Like fresh allocations, objects imported while marking are live for the
+        // current cycle, and their references still need tracing
+        if incremental.phase == CollectionPhase::Marking {
+            for obj in &objects {
+                if obj.try_mark() {
+                    incremental.mark_stack.push(obj.clone());
+                }
+            }
+        }
+        
+        match generation {
+            Generation::Young => {
+                self.young_generation.lock().extend(objects);
+                self.stats.write().young_generation_size += size;
+            }
+            Generation::Old => {
+                for obj in &objects {
+                    obj.set_tenured();
+                }
+                self.old_generation.lock().extend(objects);
+                self.stats.write().old_generation_size += size;
+            }
+        }
+    }
+    
+    /// Summarize the tracked heap by object type
+    ///
+    /// Take one as a baseline, run an operation, then `diff` a second summary
+    /// against it to see which types grew.
+    pub fn heap_snapshot_summary(&self) -> HeapSummary {
+        let incremental = self.incremental.lock();
+        let young = self.young_generation.lock();
+        let old = self.old_generation.lock();
+        
+        let mut summary = HeapSummary::default();
+        for obj in young.iter()
+            .chain(old.iter())
+            .chain(incremental.pending_young.iter())
+            .chain(incremental.pending_old.iter()) {
+            let obj_type = obj.inner.read().obj_type;
+            let entry = summary.by_type.entry(obj_type).or_default();
+            entry.count += 1;
+            entry.bytes += self.estimate_object_size(obj) as isize;
+        }
+        summary
+    }
+    
+    /// Check that every tracked object's strong count covers the references the heap holds
+    ///
+    /// Each generation entry and each object-valued property of a tracked object owns
+    /// one strong reference. A lower count means something (typically an unbalanced
+    /// `js_release_object`) dropped a reference it didn't own, and the object will be
+    /// freed while still in use. This is a debugging aid; callers assert on the result.
+    pub fn verify_refcounts(&self) -> Result<(), Vec<RefcountViolation>> {
+        let incremental = self.incremental.lock();
+        let young = self.young_generation.lock();
+        let old = self.old_generation.lock();
+        
+        let tracked = || young.iter()
+            .chain(old.iter())
+            .chain(incremental.pending_young.iter())
+            .chain(incremental.pending_old.iter());
+        
+        // Count the places the GC itself holds each object
+        let mut expected: HashMap<*const JSObject, (&Arc<JSObject>, usize)> = HashMap::new();
+        for obj in tracked() {
+            expected.entry(Arc::as_ptr(obj)).or_insert((obj, 0)).1 += 1;
+        }
+        
+        // Count references from other tracked objects
+        for obj in tracked() {
+            let children: Vec<*const JSObject> = obj.referenced_objects()
+                .iter()
+                .map(Arc::as_ptr)
+                .collect();
+            for child in children {
+                if let Some((_, count)) = expected.get_mut(&child) {
+                    *count += 1;
+                }
+            }
+        }
+        
+        let violations: Vec<RefcountViolation> = expected.into_iter()
+            .filter_map(|(ptr, (obj, expected_min))| {
+                let strong_count = Arc::strong_count(obj);
+                (strong_count < expected_min).then_some(RefcountViolation {
+                    object: ptr,
+                    strong_count,
+                    expected_min,
+                })
+            })
+            .collect();
+        
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+    
+    /// Render the tracked heap as a Graphviz DOT digraph
+    ///
+    /// Each tracked object becomes a node labeled with its type and a sequential id,
+    /// and each object-valued property pointing at another tracked object becomes
+    /// an edge labeled with the property key (or element index for arrays).
+    pub fn export_dot(&self) -> String {
+        let objects: Vec<Arc<JSObject>> = {
+            let young = self.young_generation.lock();
+            let old = self.old_generation.lock();
+            young.iter().chain(old.iter()).cloned().collect()
+        };
+        
+        let ids: HashMap<*const JSObject, usize> = objects.iter()
+            .enumerate()
+            .map(|(id, obj)| (Arc::as_ptr(obj), id))
+            .collect();
+        
+        let mut dot = String::from("digraph heap {\n");
+        for (id, obj) in objects.iter().enumerate() {
+            let obj_type = obj.inner.read().obj_type;
+            dot.push_str(&format!("    n{} [label=\"{:?} #{}\"];\n", id, obj_type, id));
+        }
+        
+        for (id, obj) in objects.iter().enumerate() {
+            let inner = obj.inner.read();
+            let mut edges: Vec<(usize, String, *const JSObject)> = Vec::new();
+            for (key, &index) in inner.shape.get_property_map() {
+                if let Some(JSValue::Object(target)) = inner.values.get(index) {
+                    edges.push((index, key.to_string(), Arc::as_ptr(&target.ptr)));
+                }
+            }
+            edges.sort_by_key(|(index, _, _)| *index);
+            if let Some(dictionary) = &inner.dictionary {
+                for (key, value) in dictionary.iter() {
+                    if let JSValue::Object(target) = value {
+                        edges.push((0, key.to_string(), Arc::as_ptr(&target.ptr)));
+                    }
+                }
+            }
+            for (index, value) in inner.elements.boxed_values().iter().enumerate() {
+                if let JSValue::Object(target) = value {
+                    edges.push((index, index.to_string(), Arc::as_ptr(&target.ptr)));
+                }
+            }
+            
+            for (_, label, target) in edges {
+                if let Some(target_id) = ids.get(&target) {
+                    let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+                    dot.push_str(&format!("    n{} -> n{} [label=\"{}\"];\n", id, target_id, label));
+                }
+            }
+        }
+        
+        dot.push_str("}\n");
+        dot
+    }
+    
+    /// Find the tracked objects that hold a direct reference to `target`
+    ///
+    /// Answers "why is this still alive?" together with `is_root`; references
+    /// held by the host outside the heap aren't visible here.
+    pub fn retainers(&self, target: &JSObjectHandle) -> Vec<JSObjectHandle> {
+        let incremental = self.incremental.lock();
+        let young = self.young_generation.lock();
+        let old = self.old_generation.lock();
+        
+        young.iter()
+            .chain(old.iter())
+            .chain(incremental.pending_young.iter())
+            .chain(incremental.pending_old.iter())
+            .filter(|obj| obj.referenced_objects().iter().any(|child| Arc::ptr_eq(child, &target.ptr)))
+            .map(|obj| JSObjectHandle { ptr: obj.clone() })
+            .collect()
+    }
+    
+    /// Get the objects reachable from `root` within `max_depth` references
+    ///
+    /// Objects directly referenced by `root` are at depth 1; nothing deeper than
+    /// `max_depth` is visited, so a debugger can expand the heap a few levels at a
+    /// time. The result is in breadth-first order, excludes `root` itself unless
+    /// it's reachable through a cycle, and lists each object once.
+    pub fn reachable_from_limited(&self, root: &JSObjectHandle, max_depth: usize) -> Vec<JSObjectHandle> {
+        let mut visited: HashSet<*const JSObject> = HashSet::new();
+        let mut reachable = Vec::new();
+        let mut frontier = vec![root.ptr.clone()];
+        
+        for _ in 0..max_depth {
+            let mut next = Vec::new();
+            for obj in &frontier {
+                for child in obj.referenced_objects() {
+                    if visited.insert(Arc::as_ptr(&child)) {
+                        reachable.push(JSObjectHandle { ptr: child.clone() });
+                        next.push(child);
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+        reachable
+    }
+    
+    /// Whether `obj` is registered as a root
+    pub fn is_root(&self, obj: &JSObjectHandle) -> bool {
+        self.roots.lock().contains(&Arc::as_ptr(&obj.ptr))
+    }
+    
+    /// Phase of the incremental cycle in progress, if any (test-only)
+    #[cfg(test)]
+    pub(crate) fn collection_phase(&self) -> CollectionPhase {
+        self.incremental.lock().phase
+    }
+    
+    /// Run a full collection and panic if `weak`'s object survived it (test-only)
+    ///
+    /// The panic message says whether the object is a root and lists the objects
+    /// retaining it, so a failing test points at the leak.
+    #[cfg(test)]
+    pub(crate) fn assert_collected(&self, weak: &object::WeakJSObjectHandle) {
+        self.collect();
+        let Some(survivor) = weak.upgrade() else {
+            return;
+        };
+        
+        let retainers: Vec<String> = self.retainers(&survivor)
+            .iter()
+            .map(|holder| format!("#{} ({:?})", holder.ptr.id(), holder.ptr.inner.read().obj_type))
+            .collect();
+        panic!(
+            "object #{} survived a full collection: {}, retained by {} object(s) [{}]",
+            survivor.ptr.id(),
+            if self.is_root(&survivor) { "is a root" } else { "not a root" },
+            retainers.len(),
+            retainers.join(", "),
+        );
+    }
+    
+    /// Get the capacities of the young and old generation vectors (test-only)
+    #[cfg(test)]
+    pub(crate) fn generation_capacities(&self) -> (usize, usize) {
+        (self.young_generation.lock().capacity(), self.old_generation.lock().capacity())
+    }
+    
+    /// Take the young generation, leaving it empty (test-only)
+    #[cfg(test)]
+    pub(crate) fn take_young(&self) -> Vec<Arc<JSObject>> {
+        mem::take(&mut *self.young_generation.lock())
+    }
+    
+    /// Replace the young generation with exactly these objects (test-only)
+    ///
+    /// Size statistics are not updated; tests set up the heap state they need.
+    #[cfg(test)]
+    pub(crate) fn set_young(&self, objects: Vec<Arc<JSObject>>) {
+        *self.young_generation.lock() = objects;
+    }
+    
+    /// Take the old generation, leaving it empty (test-only)
+    #[cfg(test)]
+    pub(crate) fn take_old(&self) -> Vec<Arc<JSObject>> {
+        mem::take(&mut *self.old_generation.lock())
+    }
+    
+    /// Replace the old generation with exactly these objects (test-only)
+    ///
+    /// Size statistics are not updated; tests set up the heap state they need.
+    #[cfg(test)]
+    pub(crate) fn set_old(&self, objects: Vec<Arc<JSObject>>) {
+        *self.old_generation.lock() = objects;
+    }
+    
+    /// Get the objects that survived the most recent collection cycle (test-only)
+    ///
+    /// A cycle is a young collection, plus the old one if it ran, or a whole
+    /// incremental cycle. Objects freed since are left out.
+    #[cfg(test)]
+    pub(crate) fn last_collection_survivors(&self) -> Vec<JSObjectHandle> {
+        self.last_survivors.lock()
+            .values()
+            .filter_map(|weak| weak.upgrade())
+            .map(|ptr| JSObjectHandle { ptr })
+            .collect()
+    }
+    
+    /// Decide whether a surviving young object should move to the old generation
+    fn tenure(&self, obj: &Arc<JSObject>, promotion_age: u8) -> bool {
+        // Promote to old generation after surviving enough young collections
+        let age = obj.increment_age();
+        let promote = age >= promotion_age;
+        if promote {
+            obj.set_tenured();
+            self.record_promotion(obj, age);
+            self.log(GCConfiguration::LOG_TRACE, || format!("Promoted object #{} at age {}", obj.id(), age));
+        }
+        promote
+    }
+    
+    /// Collect only the young generation (minor collection)
+    pub(crate) fn collect_young(&self) {
+        // An in-progress incremental cycle owns the mark bits
+        if self.incremental.lock().phase != CollectionPhase::Idle {
+            return;
+        }
+        
+        let start_time = Instant::now();
+        let config = self.config.read();
+        
+        self.log(GCConfiguration::LOG_DETAILED, || "Starting young generation collection".to_string());
+        
+        let mut garbage = Vec::new();
+        self.age_condemned(config.deferred_finalization, &mut garbage);
+        
+        // Mark phase - mark everything reachable from the roots and from old
+        // objects that may reference young ones
+        self.clear_marks();
+        self.mark_roots();
+        self.mark_remembered();
+        
+        // Sweep phase for young generation
+        let mut survivors = Vec::new();
+        let mut freed = 0;
+        let mut young_gen_size = 0;
+        let mut promoted_size = 0;
+        let survivor_count;
+        #[cfg(test)]
+        let mut recorded = Vec::new();
+        
+        {
+            let mut young = self.young_generation.lock();
+            // Promoted objects survived too, so count everything examined
+            survivor_count = young.len();
+            
+            // Process each object
+            for obj in young.drain(..) {
+                if obj.is_marked() {
+                    // Object is alive, unmark and either promote or keep in young gen
+                    obj.unmark();
+                    #[cfg(test)]
+                    recorded.push((obj.id(), Arc::downgrade(&obj)));
+                    
+                    if self.tenure(&obj, config.promotion_age) {
+                        promoted_size += self.estimate_object_size(&obj);
+                        let mut old = self.old_generation.lock();
+                        old.push(obj);
+                    } else {
+                        survivors.push(obj);
+                    }
+                } else {
+                    // Object is unreachable, will be dropped
+                    self.release_object(obj, config.deferred_finalization, config.deferred_free_generations, &mut garbage);
+                    freed += 1;
+                }
+            }
+            
+            // Put survivors back in young generation, keeping its allocation so
+            // the next round of allocations doesn't have to grow it again
+            young.append(&mut survivors);
+            
+            // Calculate new size
+            for obj in &*young {
+                young_gen_size += self.estimate_object_size(obj);
+            }
+        }
+        
+        // Clear marks left on old generation objects so the next trace starts fresh,
+        // and drop remembered objects whose young referents have all been promoted
+        let mut remembered_set_size = 0;
+        for obj in self.old_generation.lock().iter() {
+            obj.unmark();
+            if obj.refresh_remembered() {
+                remembered_set_size += 1;
+            }
+        }
+        
+        // Every stop-the-world cycle starts with a young collection, so this
+        // starts a new set that the old collection adds to
+        #[cfg(test)]
+        {
+            *self.last_survivors.lock() = recorded.into_iter().collect();
+        }
+        
+        self.free_garbage(garbage);
+        self.prune_object_ids();
+        
+        // Update statistics
+        {
+            let mut stats = self.stats.write();
+            stats.objects_freed += freed;
+            stats.young_generation_size = young_gen_size;
+            stats.old_generation_size += promoted_size;
+            stats.remembered_set_size = remembered_set_size;
+        }
+        
+        self.log(GCConfiguration::LOG_SUMMARY, || {
+            format!("Young generation collection completed in {}ms, freed {} objects",
+                    start_time.elapsed().as_millis(), freed)
+        });
+        self.log_generation_sizes();
+        self.record_pause(start_time.elapsed());
+        
+        // Scaling writes the config, so release our read guard first
+        drop(config);
+        self.trigger_threshold_scaling(freed, survivor_count - freed);
+        self.notify_promotions();
+        self.run_weak_callbacks();
+    }
+    
+    /// Raise the young generation threshold if young collections are thrashing
+    ///
+    /// Two consecutive young collections within `thrash_window_ms` that each free
+    /// less than `thrash_min_freed_percent` of the objects they examined mean the
+    /// threshold is too small to do useful work per cycle. The threshold is then
+    /// doubled, capped at `max_young_gen_threshold_kb`. Returns whether it was raised.
+    pub(crate) fn trigger_threshold_scaling(&self, freed: usize, surviving: usize) -> bool {
+        let now = Instant::now();
+        let mut config = self.config.write();
+        
+        let examined = freed + surviving;
+        let low_yield = examined > 0 && freed * 100 < examined * config.thrash_min_freed_percent;
+        
+        let previous = self.last_young_collection.lock().replace((now, low_yield));
+        let thrashing = match previous {
+            Some((last, last_low_yield)) => {
+                low_yield
+                    && last_low_yield
+                    && now.duration_since(last) < Duration::from_millis(config.thrash_window_ms)
+            }
+            None => false,
+        };
+        
+        if !thrashing || config.young_gen_threshold_kb >= config.max_young_gen_threshold_kb {
+            return false;
+        }
+        
+        let raised = (config.young_gen_threshold_kb.max(1) * 2).min(config.max_young_gen_threshold_kb);
+        self.log(GCConfiguration::LOG_SUMMARY, || {
+            format!("GC thrashing detected, raising young threshold from {}KB to {}KB",
+                    config.young_gen_threshold_kb, raised)
+        });
+        config.young_gen_threshold_kb = raised;
+        self.stats.write().threshold_raises += 1;
+        
+        // Start a fresh window so the next raise needs two more low-yield collections
+        *self.last_young_collection.lock() = None;
+        true
+    }
+    
+    /// Collect the old generation (major collection)
+    pub(crate) fn collect_old(&self) {
+        // Check if we need to run a major collection based on old gen size
+        let threshold = self.config.read().old_gen_threshold_kb * 1024;
+        if self.stats.read().old_generation_size < threshold {
+            return;
+        }
+        
+        self.collect_old_now();
+    }
+    
+    /// Collect the old generation regardless of its size
+    fn collect_old_now(&self) {
+        // An in-progress incremental cycle owns the mark bits
+        if self.incremental.lock().phase != CollectionPhase::Idle {
+            return;
+        }
+        
+        let start_time = Instant::now();
+        let config = self.config.read();
+        
+        self.log(GCConfiguration::LOG_DETAILED, || "Starting old generation collection".to_string());
+        
+        // Mark phase - mark all reachable objects
+        self.clear_marks();
+        self.mark_roots();
+        
+        // Sweep phase for old generation
+        let mut survivors = Vec::new();
+        let mut garbage = Vec::new();
+        let reshape: Vec<Arc<JSObject>>;
+        let mut freed = 0;
+        let mut old_gen_size = 0;
+        
+        {
+            let mut old = self.old_generation.lock();
+            
+            // Process each object
+            for obj in old.drain(..) {
+                if obj.is_marked() {
+                    // Object is alive, unmark and keep in old gen
+                    obj.unmark();
+                    survivors.push(obj);
+                } else {
+                    // Object is unreachable, will be dropped
+                    self.release_object(obj, config.deferred_finalization, config.deferred_free_generations, &mut garbage);
+                    freed += 1;
+                }
+            }
+            
+            // Put survivors back in old generation
+            *old = survivors;
+            
+            // Long-lived objects that left the shared shapes are worth moving back
+            reshape = old.iter()
+                .filter(|obj| {
+                    let inner = obj.inner.read();
+                    inner.dictionary.is_some() || !inner.shape.is_canonical()
+                })
+                .cloned()
+                .collect();
+            
+            #[cfg(test)]
+            {
+                self.last_survivors.lock().extend(old.iter().map(|obj| (obj.id(), Arc::downgrade(obj))));
+            }
+            
+            // Calculate new size
+            for obj in &*old {
+                old_gen_size += self.estimate_object_size(obj);
+            }
+        }
+        
+        // Clear marks left on young generation objects
+        for obj in self.young_generation.lock().iter() {
+            obj.unmark();
+        }
+        
+        self.free_garbage(garbage);
+        self.prune_object_ids();
+        self.prune_weak_entries();
+        
+        // Update statistics
+        {
+            let mut stats = self.stats.write();
+            stats.objects_freed += freed;
+            stats.old_generation_size = old_gen_size;
+        }
+        
+        self.log(GCConfiguration::LOG_SUMMARY, || {
+            format!("Old generation collection completed in {}ms, freed {} objects",
+                    start_time.elapsed().as_millis(), freed)
+        });
+        self.log_generation_sizes();
+        self.record_pause(start_time.elapsed());
+        
+        // The growth policy writes the config, so release our read guard first
+        drop(config);
+        self.apply_growth_policy();
+        
+        // Shape change callbacks may fire, so only now that no locks are held
+        for obj in reshape {
+            obj.try_restore_shape(self);
+        }
+        self.run_weak_callbacks();
+    }
+    
+    /// Unmark every tracked object before a trace starts
+    ///
+    /// The write barrier is on while any collector is marking, and tracing
+    /// follows references into other collectors' objects, so this collector's
+    /// objects can carry marks it didn't set. Marking stops at marked objects,
+    /// so a stale mark would hide everything behind it.
+    fn clear_marks(&self) {
+        let young = self.young_generation.lock();
+        let old = self.old_generation.lock();
+        for obj in young.iter().chain(old.iter()) {
+            obj.unmark();
+        }
+    }
+    
+    /// Mark all root objects and their references
+    fn mark_roots(&self) {
+        // Get local copies of roots to avoid holding lock during marking
+        let roots: Vec<*const JSObject> = {
+            let roots = self.roots.lock();
+            roots.iter().cloned().collect()
+        };
+        
+        let mut mark_stack = Vec::new();
+        for root_ptr in roots {
+            // Safety: root pointers come from live Arc<JSObject> allocations
+            let root = unsafe {
+                Arc::increment_strong_count(root_ptr);
+                Arc::from_raw(root_ptr)
+            };
+            if root.try_mark() {
+                mark_stack.push(root);
+            }
+        }
+        mark_stack.extend(self.trace_host());
+        Self::trace(mark_stack);
+    }
+    
+    /// Mark everything referenced by remembered old objects
+    ///
+    /// A minor collection doesn't sweep the old generation, so young objects
+    /// held by old ones must survive even when the old object itself is no
+    /// longer reachable from a root.
+    fn mark_remembered(&self) {
+        let remembered: Vec<Arc<JSObject>> = self.old_generation.lock().iter()
+            .filter(|obj| obj.is_remembered())
+            .cloned()
+            .collect();
+        
+        let mut mark_stack = Vec::new();
+        for obj in remembered {
+            for child in obj.referenced_objects() {
+                if child.try_mark() {
+                    mark_stack.push(child);
+                }
+            }
+        }
+        Self::trace(mark_stack);
+    }
+    
+    /// Mark everything reachable from already-marked objects
+    ///
+    /// Uses an explicit stack so deep object graphs can't overflow the call stack.
+    fn trace(mut mark_stack: Vec<Arc<JSObject>>) {
+        while let Some(obj) = mark_stack.pop() {
+            for child in obj.referenced_objects() {
+                if child.try_mark() {
+                    mark_stack.push(child);
+                }
+            }
+        }
+    }
+    
+    /// Move an unreachable object to `garbage`, or queue it if its finalizer should be deferred
+    ///
+    /// With a non-zero `grace` the object is condemned instead, and only released
+    /// once that many more collections have started.
+    fn release_object(&self, obj: Arc<JSObject>, defer: bool, grace: u32, garbage: &mut Vec<Arc<JSObject>>) {
+        self.write_barrier_hits.fetch_add(obj.take_barrier_hits(), Ordering::Relaxed);
+        if grace > 0 {
+            self.log(GCConfiguration::LOG_TRACE, || format!("Condemned object #{} for {} collections", obj.id(), grace));
+            self.condemned.lock().push((obj, grace));
+        } else if defer && obj.has_finalizer() {
+            self.log(GCConfiguration::LOG_TRACE, || format!("Queued object #{} for finalization", obj.id()));
+            self.finalizer_queue.lock().push_back(obj);
+        } else {
+            self.log(GCConfiguration::LOG_TRACE, || format!("Freed object #{}", obj.id()));
+            garbage.push(obj);
+        }
+    }
+    
+    /// Free swept objects, breaking reference cycles among them
+    ///
+    /// Objects referencing each other would otherwise keep their `Arc`s alive
+    /// forever. Before dropping, each object's remaining strong count is compared
+    /// with the references it gets from other garbage, including objects waiting
+    /// in the finalizer queue. Any excess means the host still holds it, so it
+    /// and everything it reaches are left intact; the rest that other garbage
+    /// still references have their object references cleared, which lets the
+    /// whole cycle drop. The dead objects are then finalized.
+    fn free_garbage(&self, garbage: Vec<Arc<JSObject>>) {
+        if garbage.is_empty() {
+            return;
+        }
+        
+        // Queued objects are garbage too, but their finalizers may still read them
+        let queued: Vec<Arc<JSObject>> = self.finalizer_queue.lock().iter().cloned().collect();
+        let index: HashMap<*const JSObject, usize> = garbage.iter()
+            .chain(queued.iter())
+            .enumerate()
+            .map(|(i, obj)| (Arc::as_ptr(obj), i))
+            .collect();
+        
+        // Strong references not accounted for by our own lists (the queue holds one more)
+        let mut external: Vec<usize> = garbage.iter().map(|obj| Arc::strong_count(obj) - 1)
+            .chain(queued.iter().map(|obj| Arc::strong_count(obj) - 2))
+            .collect();
+        let children: Vec<Vec<usize>> = garbage.iter()
+            .chain(queued.iter())
+            .map(|obj| {
+                obj.referenced_objects().iter()
+                    .filter_map(|child| index.get(&Arc::as_ptr(child)).copied())
+                    .collect()
+            })
+            .collect();
+        for &child in children.iter().flatten() {
+            external[child] = external[child].saturating_sub(1);
+        }
+        
+        // Keep whatever the host can still reach
+        let mut kept = vec![false; external.len()];
+        let mut stack: Vec<usize> = (0..external.len()).filter(|&i| external[i] > 0).collect();
+        while let Some(i) = stack.pop() {
+            if !mem::replace(&mut kept[i], true) {
+                stack.extend(children[i].iter().copied().filter(|&child| !kept[child]));
+            }
+        }
+        
+        drop(queued);
+        for (obj, &kept) in garbage.iter().zip(&kept) {
+            // An object only we hold is freed by the drop below with its state intact
+            if !kept && Arc::strong_count(obj) > 1 {
+                obj.clear_references();
+            }
+        }
+        
+        // Finalize the dead objects now rather than leaving it to whoever drops
+        // them last; objects the host still holds finalize when it lets go
+        for (obj, kept) in garbage.iter().zip(kept) {
+            if !kept {
+                obj.finalize();
+            }
+        }
+    }
+    
+    /// Count a collection against every condemned object, releasing those whose grace ran out
+    fn age_condemned(&self, defer: bool, garbage: &mut Vec<Arc<JSObject>>) {
+        let expired: Vec<Arc<JSObject>> = {
+            let mut condemned = self.condemned.lock();
+            if condemned.is_empty() {
+                return;
+            }
+            let (expired, kept) = mem::take(&mut *condemned)
+                .into_iter()
+                .map(|(obj, left)| (obj, left - 1))
+                .partition(|(_, left)| *left == 0);
+            *condemned = kept;
+            expired.into_iter().map(|(obj, _)| obj).collect()
+        };
+        
+        for obj in expired {
+            self.release_object(obj, defer, 0, garbage);
+        }
+    }
+    
+    /// Get the unreachable objects still held by `deferred_free_generations`
+    ///
+    /// Their finalizers haven't run and weak callbacks for them haven't fired.
+    pub fn condemned_objects(&self) -> Vec<JSObjectHandle> {
+        self.condemned.lock()
+            .iter()
+            .map(|(obj, _)| JSObjectHandle { ptr: obj.clone() })
+            .collect()
+    }
+    
+    /// Estimate the memory size of an object
+    ///
+    /// Counts the storage the object owns: its allocation, the capacity of its
+    /// value, element and dictionary storage, and its share of the shape, split
+    /// across the objects using it. Strings and property names are interned and
+    /// may be shared by any number of objects, so only their handles count; the
+    /// backing bytes belong to the interner.
+    fn estimate_object_size(&self, obj: &JSObject) -> usize {
+        let inner = obj.inner.read();
+        let mut size = mem::size_of::<JSObject>();
+        
+        size += inner.values.capacity() * mem::size_of::<JSValue>();
+        size += inner.overridden.capacity() * mem::size_of::<u64>();
+        size += inner.lazy_properties.capacity() * mem::size_of::<(InternedString, LazyProperty)>();
+        size += inner.elements.size_bytes();
+        if let Some(dictionary) = &inner.dictionary {
+            size += dictionary.size_bytes();
+        }
+        
+        size += inner.shape.size_bytes() / inner.shape.reference_count().max(1);
+        size
+    }
+}
+
+impl Drop for GarbageCollector {
+    fn drop(&mut self) {
+        // Wake the background thread now rather than at its next tick
+        #[cfg(feature = "background-gc")]
+        self.stop_background_gc();
+        
+        // Don't leave the write barrier enabled for a cycle that will never finish
+        if self.incremental.get_mut().phase == CollectionPhase::Marking {
+            object::end_incremental_marking();
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::gc::{GarbageCollector, Generation};
+use crate::object::{JSObject, JSObjectHandle, JSObjectType, JSValue};
+use serde_json::{Map, Number, Value};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+/// Why an object graph couldn't be converted to or from JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonError {
+    /// An object is reachable from itself, which JSON can't represent
+    Cycle,
+    /// `from_json` was given a JSON value that isn't an object or an array
+    NotAnObject,
+    /// Objects are nested more than `MAX_JSON_DEPTH` levels deep
+    TooDeep,
+}
+
+/// How many objects `to_json` nests before failing with `JsonError::TooDeep`
+///
+/// The conversion recurses once per level, so this bounds its stack use.
+pub const MAX_JSON_DEPTH: usize = 512;
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::Cycle => f.write_str("object graph contains a cycle"),
+            JsonError::NotAnObject => f.write_str("JSON value is not an object or array"),
+            JsonError::TooDeep => write!(f, "objects are nested more than {} levels deep", MAX_JSON_DEPTH),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl JSObject {
+    /// Convert this object and everything it references to JSON
+    ///
+    /// Follows `JSON.stringify`: arrays become JSON arrays of their elements and
+    /// other objects become JSON objects keyed in `own_keys_spec_order`. Named
+    /// properties of arrays are dropped, as are `undefined` and function values
+    /// in objects; in arrays those become `null`, like non-finite numbers.
+    /// Objects referenced more than once are written out each time, but an
+    /// object reachable from itself fails with `JsonError::Cycle`. Nesting
+    /// deeper than `MAX_JSON_DEPTH` fails with `JsonError::TooDeep`.
+    pub fn to_json(&self) -> Result<Value, JsonError> {
+        let mut ancestors = HashSet::new();
+        object_to_json(self, &mut ancestors)
+    }
+}
+
+impl GarbageCollector {
+    /// Build objects and arrays from JSON, returning the outermost one
+    ///
+    /// JSON objects become `Object`s with their keys in document order, and
+    /// JSON arrays become `Array`s. Integers that fit in an `i64` are stored as
+    /// `JSValue::Integer`. The whole graph is tracked in the young generation
+    /// at once, so no collection can run while it is half built; root the
+    /// result to keep it. Fails with `JsonError::NotAnObject` if `json` is a
+    /// primitive.
+    pub fn from_json(&self, json: &Value) -> Result<JSObjectHandle, JsonError> {
+        if !matches!(json, Value::Object(_) | Value::Array(_)) {
+            return Err(JsonError::NotAnObject);
+        }
+
+        let mut created = Vec::new();
+        let JSValue::Object(handle) = json_to_value(json, &mut created) else {
+            unreachable!("objects and arrays convert to object values");
+        };
+        self.import_objects(created, Generation::Young);
+        Ok(handle)
+    }
+}
+
+fn object_to_json(obj: &JSObject, ancestors: &mut HashSet<*const JSObject>) -> Result<Value, JsonError> {
+    let ptr = obj as *const JSObject;
+    if ancestors.contains(&ptr) {
+        return Err(JsonError::Cycle);
+    }
+    if ancestors.len() >= MAX_JSON_DEPTH {
+        return Err(JsonError::TooDeep);
+    }
+
+    // Snapshot the contents so no lock is held while recursing
+    let elements = {
+        let inner = obj.inner.read();
+        (inner.obj_type == JSObjectType::Array).then(|| inner.elements.to_vec())
+    };
+
+    ancestors.insert(ptr);
+    let json = match elements {
+        Some(elements) => {
+            let mut array = Vec::with_capacity(elements.len());
+            for element in &elements {
+                array.push(value_to_json(element, ancestors)?.unwrap_or(Value::Null));
+            }
+            Value::Array(array)
+        }
+        None => {
+            let mut map = Map::new();
+            for (key, value) in obj.entries() {
+                if let Some(json) = value_to_json(&value, ancestors)? {
+                    map.insert(key.to_string(), json);
+                }
+            }
+            Value::Object(map)
+        }
+    };
+    ancestors.remove(&ptr);
+    Ok(json)
+}
+
+/// Convert one value, or `None` for values `JSON.stringify` skips
+fn value_to_json(value: &JSValue, ancestors: &mut HashSet<*const JSObject>) -> Result<Option<Value>, JsonError> {
+    let json = match value {
+        JSValue::Undefined => return Ok(None),
+        JSValue::Null => Value::Null,
+        JSValue::Boolean(b) => Value::Bool(*b),
+        JSValue::Integer(i) => Value::Number(Number::from(*i)),
+        // Integral doubles print without a fraction, as JavaScript does
+        JSValue::Number(n) if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 => {
+            Value::Number(Number::from(*n as i64))
+        }
+        JSValue::Number(n) => Number::from_f64(*n).map_or(Value::Null, Value::Number),
+        JSValue::String(s) => Value::String(s.to_string()),
+        JSValue::Object(handle) if handle.ptr.inner.read().obj_type == JSObjectType::Function => {
+            return Ok(None);
+        }
+        JSValue::Object(handle) => object_to_json(&handle.ptr, ancestors)?,
+    };
+    Ok(Some(json))
+}
+
+/// Convert one JSON value, collecting the objects created for it
+fn json_to_value(json: &Value, created: &mut Vec<Arc<JSObject>>) -> JSValue {
+    match json {
+        Value::Null => JSValue::Null,
+        Value::Bool(b) => JSValue::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => JSValue::Integer(i),
+            None => JSValue::Number(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(s) => JSValue::from(s.as_str()),
+        Value::Array(items) => {
+            let array = JSObject::new(JSObjectType::Array);
+            created.push(array.clone());
+            for item in items {
+                array.array_push(json_to_value(item, created));
+            }
+            JSValue::Object(JSObjectHandle { ptr: array })
+        }
+        Value::Object(map) => {
+            let obj = JSObject::new(JSObjectType::Object);
+            created.push(obj.clone());
+            for (key, value) in map {
+                obj.set_property(key, json_to_value(value, created));
+            }
+            JSValue::Object(JSObjectHandle { ptr: obj })
+        }
+    }
+}
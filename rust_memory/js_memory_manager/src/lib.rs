@@ -1,165 +1,3520 @@
-//! JavaScript Memory Manager implemented in Rust
-//!
-//! This library provides memory management and garbage collection
-//! capabilities for the JavaScript Compiler project.
-
-mod gc;
-mod object;
-mod ffi;
-mod shape;
-mod string_interner;
-
-// Re-export items that need to be accessible from the FFI boundary
-pub use ffi::*;
-pub use gc::GarbageCollector;
-pub use object::{JSObject, JSObjectHandle, JSObjectType, JSValue};
-pub use shape::PropertyShape;
-pub use string_interner::{InternedString, get_interner_stats};
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::string_interner::InternedString;
-    use std::mem::size_of;
-    use std::sync::Arc;
-    use std::ops::Deref;
-
-    #[test]
-    fn test_create_object() {
-        let gc = GarbageCollector::new();
-        let obj = gc.create_object(JSObjectType::Object);
-        assert!(!obj.is_null());
-    }
-
-    #[test]
-    fn test_shape_based_properties() {
-        use crate::object::{JSObject, JSValue};
-        use crate::shape::PropertyShape;
-
-        // Create a basic object
-        let obj1 = JSObject::new(JSObjectType::Object);
-        
-        // Add some properties
-        obj1.set_property("name", JSValue::String("Object 1".to_string()));
-        obj1.set_property("value", JSValue::Number(42.0));
-        
-        // Create another object with the same property names
-        let obj2 = JSObject::new(JSObjectType::Object);
-        obj2.set_property("name", JSValue::String("Object 2".to_string()));
-        obj2.set_property("value", JSValue::Number(100.0));
-        
-        // Both objects should have the same shape
-        {
-            let inner1 = obj1.inner.read();
-            let inner2 = obj2.inner.read();
-            assert_eq!(inner1.shape.get_property_map().len(), inner2.shape.get_property_map().len());
-            
-            // Get shape IDs through debug output (implementation detail)
-            let shape1_dbg = format!("{:?}", inner1.shape);
-            let shape2_dbg = format!("{:?}", inner2.shape);
-            
-            // For objects with same property names added in same order, shapes should be identical
-            assert_eq!(shape1_dbg, shape2_dbg);
-        }
-        
-        // Values should be correctly stored and retrieved
-        assert!(matches!(obj1.get_property("name"), JSValue::String(s) if s == "Object 1"));
-        assert!(matches!(obj1.get_property("value"), JSValue::Number(n) if n == 42.0));
-        
-        assert!(matches!(obj2.get_property("name"), JSValue::String(s) if s == "Object 2"));
-        assert!(matches!(obj2.get_property("value"), JSValue::Number(n) if n == 100.0));
-        
-        // Add an additional property to obj2
-        obj2.set_property("extra", JSValue::Boolean(true));
-        
-        // Now shapes should be different
-        {
-            let inner1 = obj1.inner.read();
-            let inner2 = obj2.inner.read();
-            
-            // obj2 should have one more property than obj1
-            assert_eq!(inner1.shape.get_property_map().len() + 1, inner2.shape.get_property_map().len());
-            
-            // Shape objects should be different
-            let shape1_dbg = format!("{:?}", inner1.shape);
-            let shape2_dbg = format!("{:?}", inner2.shape);
-            assert_ne!(shape1_dbg, shape2_dbg);
-        }
-        
-        // Original properties still accessible
-        assert!(matches!(obj2.get_property("name"), JSValue::String(s) if s == "Object 2"));
-        assert!(matches!(obj2.get_property("value"), JSValue::Number(n) if n == 100.0));
-        
-        // New property also accessible
-        assert!(matches!(obj2.get_property("extra"), JSValue::Boolean(b) if b == true));
-        
-        // Property shouldn't exist on obj1
-        assert!(matches!(obj1.get_property("extra"), JSValue::Undefined));
-    }
-    
-    #[test]
-    fn test_string_interning() {
-        // Create multiple identical strings
-        let s1 = InternedString::new("hello world");
-        let s2 = InternedString::new("hello world");
-        let s3 = InternedString::new("hello world");
-        
-        // Different content should be different interned strings
-        let s4 = InternedString::new("different");
-        
-        // Test pointer equality - all identical strings should share the same storage
-        assert!(Arc::ptr_eq(&s1.inner, &s2.inner));
-        assert!(Arc::ptr_eq(&s1.inner, &s3.inner));
-        
-        // Different content should not be pointer equal
-        assert!(!Arc::ptr_eq(&s1.inner, &s4.inner));
-        
-        // Test value equality
-        assert_eq!(s1.deref(), "hello world");
-        assert_eq!(s2.deref(), "hello world");
-        assert_eq!(s3.deref(), "hello world");
-        assert_eq!(s4.deref(), "different");
-        
-        // Test that we can use them in hash maps
-        use std::collections::HashMap;
-        let mut map = HashMap::new();
-        map.insert(s1.clone(), 1);
-        map.insert(s2.clone(), 2);  // Should overwrite the first entry since they're equal
-        
-        assert_eq!(map.len(), 1);   // Only one entry should exist
-        assert_eq!(map.get(&s3), Some(&2));  // s3 should find the entry even though we inserted s2
-    }
-    
-    #[test]
-    fn test_interned_strings_with_jsvalue() {
-        // Create objects with string properties that have the same content
-        let obj1 = JSObject::new(JSObjectType::Object);
-        let obj2 = JSObject::new(JSObjectType::Object);
-        
-        // Set properties with identical content
-        obj1.set_property("name", JSValue::from("John Doe"));
-        obj1.set_property("city", JSValue::from("New York"));
-        
-        obj2.set_property("name", JSValue::from("John Doe"));
-        obj2.set_property("city", JSValue::from("New York"));
-        
-        // Access the properties and verify they're interned
-        if let JSValue::String(s1) = obj1.get_property("name") {
-            if let JSValue::String(s2) = obj2.get_property("name") {
-                // Both should point to the same string in memory
-                assert!(Arc::ptr_eq(&s1.inner, &s2.inner));
-            } else {
-                panic!("Expected string value");
-            }
-        } else {
-            panic!("Expected string value");
-        }
-        
-        // Check interning stats
-        let (count, memory) = get_interner_stats();
-        println!("Interned strings: {}, Memory usage: {} bytes", count, memory);
-        
-        // We should have 2 unique strings (not 4), since "John Doe" and "New York" are each used twice
-        assert_eq!(count, 2);
-    }
-}
\ No newline at end of file
+//! JavaScript Memory Manager implemented in Rust
+//!
+//! This library provides memory management and garbage collection
+//! capabilities for the JavaScript Compiler project.
+
+mod gc;
+mod object;
+mod ffi;
+mod json;
+mod shape;
+mod string_interner;
+
+// Re-export items that need to be accessible from the FFI boundary
+pub use ffi::*;
+pub use json::{JsonError, MAX_JSON_DEPTH};
+pub use gc::{CollectProgress, GarbageCollector, Generation, HeapSummary, JSWeakMap, LogCallback, MaintenanceReport, OomHandler, PromotionCallback, RefcountViolation, TraceCallback, Tracer, TypeSummary, WeakCallback};
+pub use object::{Elements, JSObject, JSObjectHandle, JSObjectType, JSValue, JSValueTag, JSWeakRef, LazyInitializer, LazyProperty, PropertyDictionary, PropertyKey, ShapeChangeCallback, WeakJSObjectHandle, dictionary_mode_threshold, set_dictionary_mode_threshold};
+pub use shape::{PropertyShape, ShapeStats, get_shape_stats};
+pub use string_interner::{InternedString, StringInterner, WELL_KNOWN, WellKnownNames, export_table, get_interner_stats, hit_miss_stats, import_table, prepopulate, sweep_unused_strings};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::string_interner::InternedString;
+    use std::sync::Arc;
+    use std::ops::Deref;
+
+    #[test]
+    fn test_create_object() {
+        let gc = GarbageCollector::new();
+        let obj = gc.create_object(JSObjectType::Object);
+        assert_eq!(obj.ptr.inner.read().obj_type, JSObjectType::Object);
+    }
+
+    #[test]
+    fn test_shape_based_properties() {
+        use crate::object::{JSObject, JSValue};
+
+        // Create a basic object
+        let obj1 = JSObject::new(JSObjectType::Object);
+        
+        // Add some properties
+        obj1.set_property("name", JSValue::from("Object 1"));
+        obj1.set_property("value", JSValue::Number(42.0));
+        
+        // Create another object with the same property names
+        let obj2 = JSObject::new(JSObjectType::Object);
+        obj2.set_property("name", JSValue::from("Object 2"));
+        obj2.set_property("value", JSValue::Number(100.0));
+        
+        // Both objects should have the same shape
+        {
+            let inner1 = obj1.inner.read();
+            let inner2 = obj2.inner.read();
+            assert_eq!(inner1.shape.get_property_map().len(), inner2.shape.get_property_map().len());
+            
+            // Get shape IDs through debug output (implementation detail)
+            let shape1_dbg = format!("{:?}", inner1.shape);
+            let shape2_dbg = format!("{:?}", inner2.shape);
+            
+            // For objects with same property names added in same order, shapes should be identical
+            assert_eq!(shape1_dbg, shape2_dbg);
+        }
+        
+        // Values should be correctly stored and retrieved
+        assert!(matches!(obj1.get_property("name"), JSValue::String(s) if s == "Object 1"));
+        assert!(matches!(obj1.get_property("value"), JSValue::Number(n) if n == 42.0));
+        
+        assert!(matches!(obj2.get_property("name"), JSValue::String(s) if s == "Object 2"));
+        assert!(matches!(obj2.get_property("value"), JSValue::Number(n) if n == 100.0));
+        
+        // Add an additional property to obj2
+        obj2.set_property("extra", JSValue::Boolean(true));
+        
+        // Now shapes should be different
+        {
+            let inner1 = obj1.inner.read();
+            let inner2 = obj2.inner.read();
+            
+            // obj2 should have one more property than obj1
+            assert_eq!(inner1.shape.get_property_map().len() + 1, inner2.shape.get_property_map().len());
+            
+            // Shape objects should be different
+            let shape1_dbg = format!("{:?}", inner1.shape);
+            let shape2_dbg = format!("{:?}", inner2.shape);
+            assert_ne!(shape1_dbg, shape2_dbg);
+        }
+        
+        // Original properties still accessible
+        assert!(matches!(obj2.get_property("name"), JSValue::String(s) if s == "Object 2"));
+        assert!(matches!(obj2.get_property("value"), JSValue::Number(n) if n == 100.0));
+        
+        // New property also accessible
+        assert!(matches!(obj2.get_property("extra"), JSValue::Boolean(b) if b));
+        
+        // Property shouldn't exist on obj1
+        assert!(matches!(obj1.get_property("extra"), JSValue::Undefined));
+    }
+    
+    #[test]
+    fn test_string_interning() {
+        // Create multiple identical strings
+        let s1 = InternedString::new("hello world");
+        let s2 = InternedString::new("hello world");
+        let s3 = InternedString::new("hello world");
+        
+        // Different content should be different interned strings
+        let s4 = InternedString::new("different");
+        
+        // Test pointer equality - all identical strings should share the same storage
+        assert!(Arc::ptr_eq(&s1.inner, &s2.inner));
+        assert!(Arc::ptr_eq(&s1.inner, &s3.inner));
+        
+        // Different content should not be pointer equal
+        assert!(!Arc::ptr_eq(&s1.inner, &s4.inner));
+        
+        // Test value equality
+        assert_eq!(s1.deref(), "hello world");
+        assert_eq!(s2.deref(), "hello world");
+        assert_eq!(s3.deref(), "hello world");
+        assert_eq!(s4.deref(), "different");
+        
+        // Test that we can use them in hash maps
+        use std::collections::HashMap;
+        let mut map = HashMap::new();
+        map.insert(s1.clone(), 1);
+        map.insert(s2.clone(), 2);  // Should overwrite the first entry since they're equal
+        
+        assert_eq!(map.len(), 1);   // Only one entry should exist
+        assert_eq!(map.get(&s3), Some(&2));  // s3 should find the entry even though we inserted s2
+    }
+    
+    #[test]
+    fn test_interned_strings_with_jsvalue() {
+        // Create objects with string properties that have the same content
+        let obj1 = JSObject::new(JSObjectType::Object);
+        let obj2 = JSObject::new(JSObjectType::Object);
+        
+        // Set properties with identical content
+        obj1.set_property("name", JSValue::from("John Doe"));
+        obj1.set_property("city", JSValue::from("New York"));
+        
+        obj2.set_property("name", JSValue::from("John Doe"));
+        obj2.set_property("city", JSValue::from("New York"));
+        
+        // Access the properties and verify they're interned
+        for key in ["name", "city"] {
+            if let (JSValue::String(s1), JSValue::String(s2)) = (obj1.get_property(key), obj2.get_property(key)) {
+                // Both should point to the same string in memory
+                assert!(Arc::ptr_eq(&s1.inner, &s2.inner));
+            } else {
+                panic!("Expected string value");
+            }
+        }
+        
+        // Check interning stats on a private interner, since other tests
+        // intern into the global one concurrently
+        let interner = StringInterner::new();
+        let words = ["name", "John Doe", "city", "New York"];
+        for word in words {
+            interner.intern(word);
+        }
+        let (count_before, _) = interner.stats();
+        for word in words {
+            interner.intern(word);
+        }
+        
+        let (count, memory) = interner.stats();
+        println!("Interned strings: {}, Memory usage: {} bytes", count, memory);
+        
+        // The second round reuses the strings interned by the first one
+        assert_eq!(count, count_before);
+        assert_eq!(count, 4);
+    }
+    
+    #[test]
+    fn test_try_collect_incremental() {
+        let gc = GarbageCollector::new();
+        
+        // One rooted object holding a child, plus garbage
+        let root = gc.create_object(JSObjectType::Object);
+        let child = gc.create_object(JSObjectType::Object);
+        root.ptr.set_property("child", JSValue::Object(child.clone()));
+        gc.add_root(Arc::as_ptr(&root.ptr) as *mut JSObject);
+        for _ in 0..500 {
+            gc.create_object(JSObjectType::Object);
+        }
+        
+        // Poll with a tiny budget until the cycle reports completion
+        let mut polls = 0;
+        while gc.try_collect(1) == CollectProgress::InProgress {
+            polls += 1;
+            assert!(polls < 10_000, "incremental collection never completed");
+        }
+        
+        let stats = gc.statistics();
+        assert_eq!(stats.objects_freed, 500);
+        assert_eq!(stats.collection_count, 1);
+        assert!(!root.ptr.is_marked());
+        assert!(!child.ptr.is_marked());
+        
+        // A fresh cycle with nothing left to free completes without freeing more
+        while gc.try_collect(1) == CollectProgress::InProgress {}
+        assert_eq!(gc.statistics().objects_freed, 500);
+    }
+    
+    #[test]
+    fn test_numeric_keys_use_element_storage() {
+        use crate::object::array_index;
+        
+        assert_eq!(array_index("0"), Some(0));
+        assert_eq!(array_index("42"), Some(42));
+        assert_eq!(array_index("01"), None);
+        assert_eq!(array_index("-1"), None);
+        assert_eq!(array_index("4294967295"), None);
+        assert_eq!(array_index("length"), None);
+        
+        let arr = JSObject::new(JSObjectType::Array);
+        arr.set_property("0", JSValue::from(1.0));
+        arr.set_property("1", JSValue::from(2.0));
+        arr.set_property("name", JSValue::from("list"));
+        
+        {
+            let inner = arr.inner.read();
+            assert_eq!(inner.elements.len(), 2);
+            assert_eq!(inner.shape.property_count(), 1);
+            assert!(inner.shape.get_property_index("0").is_none());
+        }
+        assert!(matches!(arr.get_property("1"), JSValue::Number(n) if n == 2.0));
+        assert!(matches!(arr.get_property("name"), JSValue::String(s) if s == "list"));
+        assert!(matches!(arr.get_property("5"), JSValue::Undefined));
+        
+        // Plain objects keep numeric keys in the property map
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_property("0", JSValue::from(1.0));
+        assert!(obj.inner.read().elements.is_empty());
+        assert!(matches!(obj.get_property("0"), JSValue::Number(n) if n == 1.0));
+    }
+    
+    #[test]
+    fn test_collect_old_with_constructed_generation() {
+        use crate::gc::GCConfiguration;
+        
+        let gc = GarbageCollector::new();
+        gc.configure(GCConfiguration {
+            old_gen_threshold_kb: 0,
+            ..GCConfiguration::default()
+        });
+        
+        // Old generation: a rooted object, the object it references, and two garbage objects
+        let root = JSObject::new(JSObjectType::Object);
+        let referenced = JSObject::new(JSObjectType::Object);
+        let garbage1 = JSObject::new(JSObjectType::Object);
+        let garbage2 = JSObject::new(JSObjectType::Object);
+        root.set_property("ref", JSValue::Object(JSObjectHandle { ptr: referenced.clone() }));
+        gc.set_old(vec![root.clone(), referenced.clone(), garbage1.clone(), garbage2.clone()]);
+        gc.add_root(Arc::as_ptr(&root) as *mut JSObject);
+        
+        // An unreachable young object is left alone by a major-only collection
+        gc.set_young(vec![JSObject::new(JSObjectType::Object)]);
+        
+        gc.collect_old();
+        
+        let old = gc.take_old();
+        assert_eq!(old.len(), 2);
+        assert!(old.iter().any(|obj| Arc::ptr_eq(obj, &root)));
+        assert!(old.iter().any(|obj| Arc::ptr_eq(obj, &referenced)));
+        assert_eq!(gc.statistics().objects_freed, 2);
+        assert_eq!(gc.take_young().len(), 1);
+    }
+    
+    #[test]
+    fn test_verify_refcounts_detects_extra_release() {
+        let c_key = std::ffi::CString::new("child").unwrap();
+        let gc = js_memory_init();
+        let parent = js_create_object(gc, 0);
+        let child = js_create_object(gc, 0);
+        assert_eq!(js_set_property_object(parent, c_key.as_ptr(), child), 1);
+        
+        // Balanced: the host releases the one handle it was given
+        js_release_object(child);
+        let gc_ref = unsafe { &*gc };
+        assert!(gc_ref.verify_refcounts().is_ok());
+        
+        // Unbalanced: a second release steals the reference owned by the parent
+        js_release_object(child);
+        let violations = gc_ref.verify_refcounts().unwrap_err();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].object, child as *const JSObject);
+        assert_eq!(violations[0].strong_count, 1);
+        assert_eq!(violations[0].expected_min, 2);
+        
+        // Restore the stolen reference so teardown stays sound
+        unsafe { Arc::increment_strong_count(child as *const JSObject) };
+        assert!(gc_ref.verify_refcounts().is_ok());
+        
+        js_release_object(parent);
+        js_memory_shutdown(gc);
+    }
+    
+    #[test]
+    fn test_export_dot() {
+        let gc = GarbageCollector::new();
+        let a = gc.create_object(JSObjectType::Object);
+        let b = gc.create_object(JSObjectType::Array);
+        let c = gc.create_object(JSObjectType::Function);
+        a.ptr.set_property("b", JSValue::Object(b.clone()));
+        a.ptr.set_property("c", JSValue::Object(c.clone()));
+        a.ptr.set_property("name", JSValue::from("a"));
+        b.ptr.set_property("0", JSValue::Object(c.clone()));
+        
+        let dot = gc.export_dot();
+        assert!(dot.starts_with("digraph heap {"));
+        assert!(dot.trim_end().ends_with('}'));
+        
+        let nodes = dot.lines().filter(|line| line.contains("[label=") && !line.contains("->")).count();
+        let edges = dot.lines().filter(|line| line.contains("->")).count();
+        assert_eq!(nodes, 3);
+        assert_eq!(edges, 3);
+        assert!(dot.contains("Array #1"));
+        assert!(dot.contains("n0 -> n1 [label=\"b\"]"));
+        assert!(dot.contains("n1 -> n2 [label=\"0\"]"));
+    }
+    
+    #[test]
+    fn test_sharded_interner_concurrency() {
+        use std::collections::HashMap;
+        use std::thread;
+        
+        let words: Arc<Vec<String>> = Arc::new((0..500).map(|i| format!("word-{}", i)).collect());
+        
+        // Intern the same wide workload from several threads
+        let run = |interner: &Arc<StringInterner>| -> Vec<InternedString> {
+            let start = Arc::new(std::sync::Barrier::new(8));
+            let handles: Vec<_> = (0..8)
+                .map(|t| {
+                    let interner = interner.clone();
+                    let words = words.clone();
+                    let start = start.clone();
+                    thread::spawn(move || {
+                        start.wait();
+                        (0..20000)
+                            .map(|i| interner.intern(&words[(i * 7 + t * 13) % words.len()]))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+        };
+        
+        let sharded = Arc::new(StringInterner::new());
+        let sharded_results = run(&sharded);
+        
+        // Every distinct string was interned exactly once, and equal content shares storage
+        assert_eq!(sharded.len(), words.len());
+        assert_eq!(sharded.stats().0, words.len());
+        let mut canonical: HashMap<String, InternedString> = HashMap::new();
+        for s in sharded_results {
+            let first = canonical.entry(s.to_string()).or_insert_with(|| s.clone());
+            assert!(Arc::ptr_eq(&first.inner, &s.inner));
+        }
+        
+        // While one shard is busy, strings living in other shards intern without waiting
+        let busy = sharded.shard("word-0");
+        let busy_index = sharded.shard_index("word-0");
+        let others: Vec<String> = words.iter()
+            .filter(|w| sharded.shard_index(w) != busy_index)
+            .cloned()
+            .collect();
+        let contention_before = sharded.contention_count();
+        {
+            let sharded = sharded.clone();
+            thread::spawn(move || {
+                for w in &others {
+                    sharded.intern(w);
+                }
+            }).join().unwrap();
+        }
+        assert_eq!(sharded.contention_count(), contention_before);
+        drop(busy);
+        
+        // With a single lock the same situation always contends
+        let single = Arc::new(StringInterner::with_shards(1));
+        let busy = single.shard("word-0");
+        let worker = {
+            let single = single.clone();
+            thread::spawn(move || {
+                single.intern("word-1");
+            })
+        };
+        while single.contention_count() == 0 {
+            thread::yield_now();
+        }
+        drop(busy);
+        worker.join().unwrap();
+        assert_eq!(single.contention_count(), 1);
+    }
+    
+    #[test]
+    fn test_global_interner_shared_across_threads() {
+        use std::thread;
+        
+        let spawn = || thread::spawn(|| InternedString::new("interned from two threads"));
+        let (a, b) = (spawn(), spawn());
+        let (a, b) = (a.join().unwrap(), b.join().unwrap());
+        assert!(Arc::ptr_eq(&a.inner, &b.inner));
+        assert_eq!(a, InternedString::new("interned from two threads"));
+    }
+    
+    #[test]
+    fn test_interner_counts_unique_strings() {
+        let interner = StringInterner::new();
+        interner.intern("John Doe");
+        interner.intern("New York");
+        interner.intern("John Doe");
+        interner.intern("New York");
+        
+        // Only 2 unique strings (not 4), since each is interned twice
+        assert_eq!(interner.len(), 2);
+        interner.clear();
+        assert!(interner.is_empty());
+    }
+    
+    #[test]
+    fn test_array_push_pop_shift_unshift() {
+        let arr = JSObject::new(JSObjectType::Array);
+        
+        // Popping or shifting an empty array yields undefined
+        assert!(matches!(arr.array_pop(), JSValue::Undefined));
+        assert!(matches!(arr.array_shift(), JSValue::Undefined));
+        
+        assert_eq!(arr.array_push(JSValue::from(1.0)), 1);
+        assert_eq!(arr.array_push(JSValue::from(2.0)), 2);
+        assert_eq!(arr.array_push(JSValue::from("three")), 3);
+        assert!(matches!(arr.array_pop(), JSValue::String(s) if s == "three"));
+        assert!(matches!(arr.array_pop(), JSValue::Number(n) if n == 2.0));
+        assert_eq!(arr.inner.read().elements.len(), 1);
+        
+        // Unshift moves existing elements up one index
+        assert_eq!(arr.array_unshift(JSValue::from(0.0)), 2);
+        assert!(matches!(arr.get_property("0"), JSValue::Number(n) if n == 0.0));
+        assert!(matches!(arr.get_property("1"), JSValue::Number(n) if n == 1.0));
+        
+        // Shift moves them back down
+        assert!(matches!(arr.array_shift(), JSValue::Number(n) if n == 0.0));
+        assert!(matches!(arr.get_property("0"), JSValue::Number(n) if n == 1.0));
+        assert!(matches!(arr.get_property("1"), JSValue::Undefined));
+        
+        // FFI push appends a number and reports the new length
+        let handle = Arc::into_raw(arr.clone()) as *mut JSObject;
+        assert_eq!(js_array_push(handle, 5.0), 2);
+        assert!(matches!(arr.get_property("1"), JSValue::Number(n) if n == 5.0));
+        assert_eq!(js_array_push(std::ptr::null_mut(), 1.0), 0);
+        js_release_object(handle);
+    }
+    
+    #[test]
+    fn test_generation_of() {
+        let gc = GarbageCollector::new();
+        let obj = gc.create_object(JSObjectType::Object);
+        gc.add_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+        assert_eq!(gc.generation_of(&obj), Some(Generation::Young));
+        
+        // A long-lived object gets promoted after surviving enough collections
+        gc.collect();
+        gc.collect();
+        assert_eq!(gc.generation_of(&obj), Some(Generation::Old));
+        
+        // Objects created outside the collector aren't tracked
+        let untracked = JSObjectHandle { ptr: JSObject::new(JSObjectType::Object) };
+        assert_eq!(gc.generation_of(&untracked), None);
+    }
+    
+    #[test]
+    fn test_intern_owned_string_without_copy() {
+        // On a miss the owned buffer itself becomes the interned storage
+        let owned = String::from("owned string interned on a miss");
+        let buffer = owned.as_ptr();
+        let interned = InternedString::from_owned(owned);
+        assert_eq!(interned.as_ptr(), buffer);
+        
+        // On a hit the existing storage is returned and the new buffer dropped
+        let again = String::from("owned string interned on a miss");
+        let again_buffer = again.as_ptr();
+        let hit = InternedString::from_owned(again);
+        assert!(Arc::ptr_eq(&hit.inner, &interned.inner));
+        assert_ne!(hit.as_ptr(), again_buffer);
+        assert_eq!(InternedString::new("owned string interned on a miss"), interned);
+    }
+    
+    #[test]
+    fn test_collect_if_needed() {
+        use crate::gc::GCConfiguration;
+        
+        let gc = GarbageCollector::new();
+        for _ in 0..10 {
+            gc.create_object(JSObjectType::Object);
+        }
+        
+        // Well below the default threshold nothing happens
+        assert!(!gc.collect_if_needed());
+        assert_eq!(gc.statistics().objects_freed, 0);
+        
+        // Once the threshold is lower than the occupancy, the garbage is collected
+        gc.configure(GCConfiguration {
+            young_gen_threshold_kb: 0,
+            ..GCConfiguration::default()
+        });
+        assert!(gc.collect_if_needed());
+        assert_eq!(gc.statistics().objects_freed, 10);
+        assert_eq!(gc.statistics().young_generation_size, 0);
+        assert!(!gc.collect_if_needed());
+    }
+    
+    #[test]
+    fn test_typed_property_getters() {
+        let obj = JSObject::new(JSObjectType::Object);
+        let child = JSObjectHandle { ptr: JSObject::new(JSObjectType::Array) };
+        obj.set_property("child", JSValue::Object(child.clone()));
+        obj.set_property("count", JSValue::from(3.0));
+        obj.set_property("name", JSValue::from("box"));
+        obj.set_property("open", JSValue::from(true));
+        
+        assert!(Arc::ptr_eq(&obj.get_object_property("child").unwrap().ptr, &child.ptr));
+        assert_eq!(obj.get_number_property("count"), Some(3.0));
+        assert_eq!(obj.get_string_property("name").unwrap(), "box");
+        assert_eq!(obj.get_bool_property("open"), Some(true));
+        
+        // Type mismatches and missing keys return None
+        assert!(obj.get_object_property("count").is_none());
+        assert_eq!(obj.get_number_property("name"), None);
+        assert!(obj.get_string_property("open").is_none());
+        assert_eq!(obj.get_bool_property("child"), None);
+        assert_eq!(obj.get_number_property("missing"), None);
+    }
+    
+    #[test]
+    fn test_thrashing_raises_young_threshold() {
+        use crate::gc::GCConfiguration;
+        
+        let gc = GarbageCollector::new();
+        gc.configure(GCConfiguration {
+            young_gen_threshold_kb: 64,
+            thrash_window_ms: 60_000,
+            max_young_gen_threshold_kb: 256,
+            promotion_age: u8::MAX,
+            ..GCConfiguration::default()
+        });
+        
+        // Every young object is rooted, so each collection frees nothing
+        let objects: Vec<_> = (0..4).map(|_| gc.create_object(JSObjectType::Object)).collect();
+        for obj in &objects {
+            gc.add_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+        }
+        
+        // A single low-yield collection isn't thrashing yet
+        gc.collect_young();
+        assert_eq!(gc.config().young_gen_threshold_kb, 64);
+        
+        // A second one back-to-back doubles the threshold
+        gc.collect_young();
+        assert_eq!(gc.config().young_gen_threshold_kb, 128);
+        assert_eq!(gc.statistics().threshold_raises, 1);
+        
+        // Raises continue in pairs of collections up to the configured bound
+        for _ in 0..6 {
+            gc.collect_young();
+        }
+        assert_eq!(gc.config().young_gen_threshold_kb, 256);
+        assert_eq!(gc.statistics().threshold_raises, 2);
+        
+        // A productive collection doesn't count towards thrashing
+        gc.configure(GCConfiguration {
+            young_gen_threshold_kb: 64,
+            thrash_window_ms: 60_000,
+            ..GCConfiguration::default()
+        });
+        for obj in &objects {
+            gc.remove_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+        }
+        drop(objects);
+        for _ in 0..2 {
+            for _ in 0..4 {
+                gc.create_object(JSObjectType::Object);
+            }
+            gc.collect_young();
+        }
+        assert_eq!(gc.config().young_gen_threshold_kb, 64);
+        assert_eq!(gc.statistics().threshold_raises, 2);
+    }
+    
+    #[test]
+    fn test_reserve_properties() {
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_property("x", JSValue::Number(1.0));
+        obj.reserve_properties(&["x", "y", "z"]);
+        
+        let reserved_shape = obj.inner.read().shape.clone();
+        assert_eq!(reserved_shape.property_count(), 3);
+        assert!(matches!(obj.get_property("y"), JSValue::Undefined));
+        assert_eq!(obj.property_names().len(), 3);
+        
+        // Setting reserved keys only writes values
+        obj.set_property("y", JSValue::Number(2.0));
+        obj.set_property("z", JSValue::Number(3.0));
+        assert!(Arc::ptr_eq(&obj.inner.read().shape, &reserved_shape));
+        assert_eq!(obj.get_number_property("x"), Some(1.0));
+        assert_eq!(obj.get_number_property("z"), Some(3.0));
+        
+        // Objects built key by key end up sharing the reserved shape
+        let other = JSObject::new(JSObjectType::Object);
+        for key in ["x", "y", "z"] {
+            other.set_property(key, JSValue::Null);
+        }
+        assert!(Arc::ptr_eq(&other.inner.read().shape, &reserved_shape));
+    }
+    
+    #[test]
+    fn test_deferred_finalizers() {
+        use crate::gc::GCConfiguration;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        static FINALIZED: AtomicUsize = AtomicUsize::new(0);
+        extern "C" fn count_finalized(_: *mut JSObject) {
+            FINALIZED.fetch_add(1, Ordering::SeqCst);
+        }
+        
+        let gc = GarbageCollector::new();
+        gc.configure(GCConfiguration {
+            deferred_finalization: true,
+            ..GCConfiguration::default()
+        });
+        
+        for _ in 0..3 {
+            let obj = gc.create_object(JSObjectType::Object);
+            obj.ptr.set_finalizer(count_finalized);
+        }
+        gc.create_object(JSObjectType::Object);
+        
+        // Unreachable objects with finalizers are queued instead of finalized
+        gc.collect();
+        assert_eq!(gc.statistics().objects_freed, 4);
+        assert_eq!(gc.pending_finalizers(), 3);
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 0);
+        
+        // Pumping the queue runs the finalizers
+        assert_eq!(gc.run_finalizers(2), 2);
+        assert_eq!(gc.pending_finalizers(), 1);
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 2);
+        assert_eq!(gc.run_finalizers(usize::MAX), 1);
+        assert_eq!(gc.pending_finalizers(), 0);
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 3);
+    }
+    
+    #[test]
+    fn test_value_tags() {
+        let obj = JSObject::new(JSObjectType::Object);
+        let other = JSObject::new(JSObjectType::Object);
+        obj.set_property("undefined", JSValue::Undefined);
+        obj.set_property("null", JSValue::Null);
+        obj.set_property("bool", JSValue::Boolean(true));
+        obj.set_property("number", JSValue::Number(1.5));
+        obj.set_property("string", JSValue::from("text"));
+        obj.set_property("object", JSValue::Object(JSObjectHandle { ptr: other }));
+        
+        let tag_of = |key: &str| {
+            let key = std::ffi::CString::new(key).unwrap();
+            js_get_value_tag(Arc::as_ptr(&obj) as *mut JSObject, key.as_ptr())
+        };
+        assert_eq!(tag_of("undefined"), JSValueTag::Undefined as i32);
+        assert_eq!(tag_of("null"), JSValueTag::Null as i32);
+        assert_eq!(tag_of("bool"), JSValueTag::Boolean as i32);
+        assert_eq!(tag_of("number"), JSValueTag::Number as i32);
+        assert_eq!(tag_of("string"), JSValueTag::String as i32);
+        assert_eq!(tag_of("object"), JSValueTag::Object as i32);
+        assert_eq!(tag_of("missing"), 0);
+        assert_eq!(obj.get_property("number").tag(), JSValueTag::Number);
+        assert_eq!(JSValueTag::Symbol as i32, 7);
+    }
+    
+    #[test]
+    fn test_shape_common_ancestor() {
+        let prefix = PropertyShape::root()
+            .transition_to("ancestor_a")
+            .transition_to("ancestor_b");
+        let left = prefix.transition_to("left").transition_to("left_more");
+        let right = prefix.transition_to("right");
+        
+        let ancestor = left.common_ancestor(&right).unwrap();
+        assert!(Arc::ptr_eq(&ancestor, &prefix));
+        assert_eq!(ancestor.property_count(), 2);
+        assert!(Arc::ptr_eq(&right.common_ancestor(&left).unwrap(), &prefix));
+        
+        // A shape is its own ancestor, and the ancestor of its descendants
+        assert!(Arc::ptr_eq(&prefix.common_ancestor(&left).unwrap(), &prefix));
+        assert!(Arc::ptr_eq(&left.common_ancestor(&left).unwrap(), &left));
+        
+        // Shapes from unrelated roots share nothing
+        let unrelated = PropertyShape::new_empty().transition_to("ancestor_a");
+        assert!(left.common_ancestor(&unrelated).is_none());
+    }
+    
+    #[cfg(feature = "background-gc")]
+    #[test]
+    fn test_background_gc() {
+        use crate::gc::GCConfiguration;
+        use std::time::{Duration, Instant};
+        
+        let gc = GarbageCollector::new();
+        for _ in 0..10 {
+            gc.create_object(JSObjectType::Object);
+        }
+        assert_eq!(gc.statistics().objects_freed, 0);
+        
+        // The thread picks up the garbage once the young generation is over threshold
+        gc.configure(GCConfiguration {
+            young_gen_threshold_kb: 0,
+            ..GCConfiguration::default()
+        });
+        gc.start_background_gc(Duration::from_millis(1));
+        
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while gc.statistics().objects_freed < 10 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(gc.statistics().objects_freed, 10);
+        
+        // Stopping joins the thread, and stopping again is a no-op
+        gc.stop_background_gc();
+        gc.stop_background_gc();
+        
+        // The thread doesn't keep the collector alive, and is joined when it is dropped
+        gc.start_background_gc(Duration::from_millis(1));
+        let weak = Arc::downgrade(&gc);
+        drop(gc);
+        assert!(weak.upgrade().is_none());
+    }
+    
+    #[test]
+    fn test_object_entries() {
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_property("b", JSValue::Number(1.0));
+        obj.set_property("a", JSValue::from("two"));
+        obj.set_property("2", JSValue::Boolean(true));
+        obj.set_property("1", JSValue::Null);
+        
+        // Integer keys ascend first, then string keys keep insertion order
+        let entries = obj.entries();
+        let keys: Vec<String> = entries.iter().map(|(key, _)| key.to_string()).collect();
+        assert_eq!(keys, ["1", "2", "b", "a"]);
+        for (key, value) in &entries {
+            assert_eq!(format!("{:?}", value), format!("{:?}", obj.get_property(&key.to_string())));
+        }
+        assert_eq!(obj.values().len(), 4);
+        assert_eq!(obj.values()[2].tag(), JSValueTag::Number);
+        
+        // Array elements come before named properties
+        let array = JSObject::new(JSObjectType::Array);
+        array.set_property("length_hint", JSValue::Number(2.0));
+        array.array_push(JSValue::Number(10.0));
+        array.array_push(JSValue::Number(20.0));
+        let keys: Vec<String> = array.entries().into_iter().map(|(key, _)| key.to_string()).collect();
+        assert_eq!(keys, ["0", "1", "length_hint"]);
+        assert_eq!(array.entries()[1].0, PropertyKey::Index(1));
+        
+        // The FFI walk reports the same order with value tags
+        extern "C" fn collect_entry(key: *const libc::c_char, tag: libc::c_int, user_data: *mut libc::c_void) {
+            let seen = unsafe { &mut *(user_data as *mut Vec<(String, i32)>) };
+            let key = unsafe { std::ffi::CStr::from_ptr(key) }.to_str().unwrap().to_string();
+            seen.push((key, tag));
+        }
+        let mut seen: Vec<(String, i32)> = Vec::new();
+        let visited = js_object_entries(
+            Arc::as_ptr(&obj) as *mut JSObject,
+            Some(collect_entry),
+            &mut seen as *mut Vec<(String, i32)> as *mut libc::c_void,
+        );
+        assert_eq!(visited, 4);
+        assert_eq!(seen[0], ("1".to_string(), JSValueTag::Null as i32));
+        assert_eq!(seen[3], ("a".to_string(), JSValueTag::String as i32));
+    }
+    
+    #[test]
+    fn test_configure_returns_previous_config() {
+        use crate::gc::GCConfiguration;
+        
+        let gc = GarbageCollector::new();
+        let previous = gc.configure(GCConfiguration {
+            young_gen_threshold_kb: 512,
+            ..GCConfiguration::default()
+        });
+        assert_eq!(previous.young_gen_threshold_kb, 256);
+        
+        // A temporary config applies only inside the closure
+        gc.with_temp_config(GCConfiguration { verbose: true, ..gc.config() }, || {
+            assert!(gc.config().verbose);
+            gc.collect();
+        });
+        assert!(!gc.config().verbose);
+        assert_eq!(gc.config().young_gen_threshold_kb, 512);
+    }
+    
+    #[test]
+    fn test_extra_handles_do_not_promote_early() {
+        let gc = GarbageCollector::new();
+        assert_eq!(gc.config().promotion_age, 2);
+        let obj = gc.create_object(JSObjectType::Object);
+        gc.add_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+        
+        // Handles the host holds on to, e.g. from js_get_property_object
+        let extra_handles: Vec<_> = (0..5).map(|_| obj.clone()).collect();
+        gc.collect_young();
+        assert_eq!(gc.generation_of(&obj), Some(Generation::Young));
+        
+        // Promotion follows survived collections, not handle counts
+        drop(extra_handles);
+        gc.collect_young();
+        assert_eq!(gc.generation_of(&obj), Some(Generation::Old));
+    }
+    
+    #[test]
+    fn test_promotion_age() {
+        use crate::gc::GCConfiguration;
+        
+        let gc = GarbageCollector::new();
+        gc.configure(GCConfiguration {
+            promotion_age: 3,
+            ..GCConfiguration::default()
+        });
+        let obj = gc.create_object(JSObjectType::Object);
+        gc.add_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+        
+        // Each young collection ages survivors until they reach the promotion age
+        gc.collect_young();
+        gc.collect_young();
+        assert_eq!(obj.ptr.age(), 2);
+        assert_eq!(gc.generation_of(&obj), Some(Generation::Young));
+        gc.collect_young();
+        assert_eq!(obj.ptr.age(), 3);
+        assert_eq!(gc.generation_of(&obj), Some(Generation::Old));
+        
+        // Ages saturate instead of overflowing
+        let old_timer = JSObject::new(JSObjectType::Object);
+        for _ in 0..300 {
+            old_timer.increment_age();
+        }
+        assert_eq!(old_timer.age(), u8::MAX);
+    }
+    
+    #[test]
+    fn test_property_count() {
+        let obj = JSObject::new(JSObjectType::Object);
+        let handle = Arc::as_ptr(&obj) as *mut JSObject;
+        assert_eq!(js_get_property_count(handle), 0);
+        
+        // Overwriting a key doesn't add to the count
+        obj.set_property("a", JSValue::Number(1.0));
+        obj.set_property("b", JSValue::Number(2.0));
+        obj.set_property("a", JSValue::Number(3.0));
+        assert_eq!(js_get_property_count(handle), 2);
+        
+        // Array elements count alongside named properties
+        let array = JSObject::new(JSObjectType::Array);
+        array.set_property("0", JSValue::Null);
+        array.set_property("1", JSValue::Null);
+        array.set_property("name", JSValue::Null);
+        assert_eq!(js_get_property_count(Arc::as_ptr(&array) as *mut JSObject), 3);
+        
+        assert_eq!(js_get_property_count(std::ptr::null_mut()), 0);
+    }
+    
+    #[test]
+    fn test_heap_snapshot_diff() {
+        let gc = GarbageCollector::new();
+        let existing = gc.create_object(JSObjectType::Object);
+        gc.add_root(Arc::as_ptr(&existing.ptr) as *mut JSObject);
+        let baseline = gc.heap_snapshot_summary();
+        assert_eq!(baseline.get(JSObjectType::Object).count, 1);
+        
+        // Simulate an operation that leaks a few rooted arrays
+        let leaked: Vec<_> = (0..3).map(|_| {
+            let array = gc.create_object(JSObjectType::Array);
+            array.ptr.array_push(JSValue::Number(1.0));
+            gc.add_root(Arc::as_ptr(&array.ptr) as *mut JSObject);
+            array
+        }).collect();
+        gc.collect();
+        
+        let growth = gc.heap_snapshot_summary().diff(&baseline);
+        assert_eq!(growth.by_type.len(), 1);
+        assert_eq!(growth.get(JSObjectType::Array).count, 3);
+        assert!(growth.get(JSObjectType::Array).bytes > 0);
+        assert_eq!(growth.get(JSObjectType::Object), TypeSummary::default());
+        
+        // Freed objects show up as shrinkage against a later baseline
+        let after = gc.heap_snapshot_summary();
+        for array in &leaked {
+            gc.remove_root(Arc::as_ptr(&array.ptr) as *mut JSObject);
+        }
+        drop(leaked);
+        gc.collect();
+        assert_eq!(gc.heap_snapshot_summary().diff(&after).get(JSObjectType::Array).count, -3);
+    }
+    
+    #[test]
+    fn test_prepopulated_names() {
+        let names = prepopulate(&["prepopulated_a", "prepopulated_b"]);
+        assert_eq!(names.len(), 2);
+        assert!(Arc::ptr_eq(&InternedString::new("prepopulated_b").inner, &names[1].inner));
+        
+        // Well-known names are the same handles the interner returns
+        assert!(Arc::ptr_eq(&InternedString::new("prototype").inner, &WELL_KNOWN.prototype.inner));
+        assert!(Arc::ptr_eq(&InternedString::new("toString").inner, &WELL_KNOWN.to_string.inner));
+        assert_eq!(WELL_KNOWN.length, "length");
+    }
+    
+    #[test]
+    fn test_last_collection_survivors() {
+        use crate::gc::GCConfiguration;
+        use std::collections::HashSet;
+        
+        for incremental in [false, true] {
+            let gc = GarbageCollector::new();
+            gc.configure(GCConfiguration {
+                old_gen_threshold_kb: 0,
+                incremental,
+                max_pause_ms: u64::MAX,
+                ..GCConfiguration::default()
+            });
+            
+            // Survivors and garbage in both generations
+            let old_rooted = gc.create_object(JSObjectType::Object);
+            gc.add_root(Arc::as_ptr(&old_rooted.ptr) as *mut JSObject);
+            gc.create_object(JSObjectType::Object);
+            gc.tenure_all_young();
+            let mut rooted: Vec<_> = (0..3).map(|_| gc.create_object(JSObjectType::Object)).collect();
+            for obj in &rooted {
+                gc.add_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+            }
+            for _ in 0..5 {
+                gc.create_object(JSObjectType::Object);
+            }
+            rooted.push(old_rooted);
+            
+            // Both phases of the cycle add to the same set
+            gc.collect();
+            assert_eq!(gc.statistics().objects_freed, 6);
+            
+            let survivors: HashSet<*const JSObject> = gc.last_collection_survivors()
+                .iter()
+                .map(|handle| Arc::as_ptr(&handle.ptr))
+                .collect();
+            let expected: HashSet<*const JSObject> = rooted.iter().map(|obj| Arc::as_ptr(&obj.ptr)).collect();
+            assert_eq!(survivors, expected, "incremental: {}", incremental);
+        }
+    }
+    
+    #[test]
+    fn test_pretty_print_nested_object() {
+        let inner = JSObject::new(JSObjectType::Object);
+        inner.set_property("city", JSValue::from("Paris"));
+        inner.set_property("zip", JSValue::Number(75001.0));
+        
+        let list = JSObject::new(JSObjectType::Array);
+        list.array_push(JSValue::Number(1.0));
+        list.array_push(JSValue::Undefined);
+        
+        let outer = JSObject::new(JSObjectType::Object);
+        outer.set_property("name", JSValue::from("Ada"));
+        outer.set_property("address", JSValue::Object(JSObjectHandle { ptr: inner }));
+        outer.set_property("list", JSValue::Object(JSObjectHandle { ptr: list }));
+        outer.set_property("empty", JSValue::Object(JSObjectHandle { ptr: JSObject::new(JSObjectType::Object) }));
+        let value = JSValue::Object(JSObjectHandle { ptr: outer.clone() });
+        
+        let expected = "{\n  \"name\": \"Ada\",\n  \"address\": {\n    \"city\": \"Paris\",\n    \"zip\": 75001\n  },\n  \"list\": [\n    1,\n    undefined\n  ],\n  \"empty\": {}\n}";
+        assert_eq!(value.pretty(2), expected);
+        assert_eq!(value.to_string(), "{\"name\": \"Ada\", \"address\": {\"city\": \"Paris\", \"zip\": 75001}, \"list\": [1, undefined], \"empty\": {}}");
+        
+        // Cycles are cut off instead of recursing forever
+        outer.set_property("self", value.clone());
+        assert!(value.to_string().ends_with("\"self\": [Circular]}"));
+        outer.set_property("self", JSValue::Null);
+    }
+    
+    #[test]
+    fn test_object_ids() {
+        let gc = GarbageCollector::new();
+        let kept = gc.create_object(JSObjectType::Object);
+        let other = gc.create_object(JSObjectType::Array);
+        gc.add_root(Arc::as_ptr(&kept.ptr) as *mut JSObject);
+        
+        let id = kept.ptr.id();
+        assert_ne!(id, other.ptr.id());
+        assert_eq!(js_object_id(Arc::as_ptr(&kept.ptr) as *mut JSObject), id);
+        assert_eq!(js_object_id(std::ptr::null_mut()), 0);
+        
+        // Ids stay the same across collections and resolve back to the object
+        let other_id = other.ptr.id();
+        drop(other);
+        gc.collect();
+        assert_eq!(kept.ptr.id(), id);
+        assert!(Arc::ptr_eq(&gc.object_by_id(id).unwrap().ptr, &kept.ptr));
+        
+        // Freed objects no longer resolve
+        assert!(gc.object_by_id(other_id).is_none());
+    }
+    
+    #[test]
+    fn test_lazy_property() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        #[allow(improper_ctypes_definitions)]
+        extern "C" fn compute_area(obj: *mut JSObject) -> JSValue {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            // The initializer may read the object it belongs to
+            let obj = unsafe { &*obj };
+            let width = obj.get_number_property("width").unwrap_or(0.0);
+            JSValue::Number(width * width)
+        }
+        
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_property("width", JSValue::Number(3.0));
+        obj.set_lazy_property("area", compute_area);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+        assert_eq!(obj.property_names().len(), 1);
+        
+        // The first read runs the initializer, later reads use the cached value
+        assert_eq!(obj.get_number_property("area"), Some(9.0));
+        obj.set_property("width", JSValue::Number(4.0));
+        assert_eq!(obj.get_number_property("area"), Some(9.0));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+        assert_eq!(obj.property_names().len(), 2);
+        
+        // Writing the key before it is read discards the initializer
+        obj.set_lazy_property("volume", compute_area);
+        obj.set_property("volume", JSValue::Null);
+        assert!(matches!(obj.get_property("volume"), JSValue::Null));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    }
+    
+    #[test]
+    fn test_lazy_property_keeps_write_made_while_materializing() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        #[allow(improper_ctypes_definitions)]
+        extern "C" fn overwrite_self(obj: *mut JSObject) -> JSValue {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            let obj = unsafe { &*obj };
+            // The key still reads as present while its value is computed
+            assert!(obj.has_own_property("answer"));
+            obj.set_property("answer", JSValue::Number(1.0));
+            JSValue::Number(2.0)
+        }
+        
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_lazy_property("answer", overwrite_self);
+        
+        // The write that landed first is kept over the computed value
+        assert_eq!(obj.get_number_property("answer"), Some(1.0));
+        assert_eq!(obj.get_number_property("answer"), Some(1.0));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    }
+    
+    #[test]
+    fn test_growth_policy_tracks_live_set() {
+        use crate::gc::GCConfiguration;
+        
+        let young_threshold_for = |live_objects: usize| {
+            let gc = GarbageCollector::new();
+            gc.configure(GCConfiguration {
+                old_gen_threshold_kb: 0,
+                min_young_gen_threshold_kb: 1,
+                ..GCConfiguration::default()
+            });
+            gc.set_growth_policy(4.0);
+            
+            let live: Vec<_> = (0..live_objects).map(|_| {
+                let obj = gc.create_object(JSObjectType::Object);
+                obj.ptr.set_property("payload", JSValue::from("some retained data"));
+                gc.add_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+                obj
+            }).collect();
+            
+            // Several rounds of short-lived garbage on top of the live set
+            for _ in 0..3 {
+                for _ in 0..50 {
+                    gc.create_object(JSObjectType::Object);
+                }
+                gc.collect();
+            }
+            
+            drop(live);
+            gc.config().young_gen_threshold_kb
+        };
+        
+        let small = young_threshold_for(10);
+        let large = young_threshold_for(500);
+        assert!(large > small, "{} should exceed {}", large, small);
+        
+        // Without a growth factor the threshold stays put
+        let gc = GarbageCollector::new();
+        gc.configure(GCConfiguration { old_gen_threshold_kb: 0, ..GCConfiguration::default() });
+        gc.collect();
+        assert_eq!(gc.config().young_gen_threshold_kb, 256);
+    }
+    
+    #[cfg(feature = "handle-validation")]
+    #[test]
+    fn test_released_handle_is_rejected() {
+        let handle = Arc::into_raw(JSObject::new(JSObjectType::Array)) as *mut JSObject;
+        assert_eq!(js_get_object_type(handle), 1);
+        
+        // Releasing the last reference frees the object; its handle is now stale
+        js_release_object(handle);
+        assert_eq!(js_get_object_type(handle), -1);
+    }
+    
+    #[test]
+    fn test_has_own_property() {
+        let proto = JSObject::new(JSObjectType::Object);
+        proto.set_property("inherited", JSValue::Number(1.0));
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_property("own", JSValue::Number(2.0));
+        obj.set_prototype(Some(JSObjectHandle { ptr: proto.clone() }));
+        
+        assert!(obj.has_own_property("own"));
+        assert!(!obj.has_own_property("inherited"));
+        assert!(obj.has_property("inherited"));
+        assert!(!obj.has_property("missing"));
+        
+        let key = std::ffi::CString::new("inherited").unwrap();
+        let handle = Arc::as_ptr(&obj) as *mut JSObject;
+        assert_eq!(js_has_own_property(handle, key.as_ptr()), 0);
+        let key = std::ffi::CString::new("own").unwrap();
+        assert_eq!(js_has_own_property(handle, key.as_ptr()), 1);
+        
+        // A cyclic prototype chain still terminates
+        proto.set_prototype(Some(JSObjectHandle { ptr: obj.clone() }));
+        assert!(!obj.has_property("missing"));
+        proto.set_prototype(None);
+    }
+    
+    #[test]
+    fn test_interner_table_round_trip() {
+        // Round trip through a private interner so clearing doesn't disturb other tests
+        let interner = StringInterner::new();
+        for s in ["alpha", "beta", "gamma", "beta"] {
+            interner.intern(s);
+        }
+        let table = interner.export_table();
+        assert_eq!(table, ["alpha", "beta", "gamma"]);
+        
+        interner.clear();
+        assert!(interner.is_empty());
+        interner.import_table(table.clone());
+        assert_eq!(interner.export_table(), table);
+        
+        // Imported strings are the ones later lookups resolve to
+        let restored = interner.intern("beta");
+        assert!(Arc::ptr_eq(&restored.inner, &interner.intern("beta").inner));
+        assert_eq!(interner.len(), 3);
+        
+        // Objects built after a global import share the imported entries
+        import_table(vec!["table_key".to_string(), "table_value".to_string()]);
+        assert!(export_table().iter().any(|s| s == "table_value"));
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_property("table_key", JSValue::from("table_value"));
+        let value = obj.get_string_property("table_key").unwrap();
+        assert!(Arc::ptr_eq(&value.inner, &InternedString::new("table_value").inner));
+    }
+    
+    #[test]
+    fn test_collect_young_incremental_in_batches() {
+        use crate::gc::GCConfiguration;
+        
+        let gc = GarbageCollector::new();
+        gc.configure(GCConfiguration {
+            young_gen_threshold_kb: usize::MAX / 1024,
+            ..GCConfiguration::default()
+        });
+        
+        // Every tenth object is rooted; the rest are garbage
+        let objects: Vec<_> = (0..1000).map(|_| gc.create_object(JSObjectType::Object)).collect();
+        let rooted: Vec<_> = objects.iter().step_by(10).cloned().collect();
+        for obj in &rooted {
+            gc.add_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+        }
+        drop(objects);
+        
+        let mut calls = 1;
+        while !gc.collect_young_incremental(100) {
+            assert_eq!(gc.statistics().objects_freed, 0);
+            calls += 1;
+        }
+        assert_eq!(calls, 10);
+        
+        let stats = gc.statistics();
+        assert_eq!(stats.objects_freed, 900);
+        assert_eq!(stats.collection_count, 1);
+        for obj in &rooted {
+            assert_eq!(gc.generation_of(obj), Some(Generation::Young));
+            assert!(!obj.ptr.is_marked());
+        }
+    }
+    
+    #[test]
+    fn test_rename_property_shares_shape() {
+        let make = || {
+            let obj = JSObject::new(JSObjectType::Object);
+            obj.set_property("rename_x", JSValue::Number(1.0));
+            obj.set_property("rename_y", JSValue::Number(2.0));
+            obj
+        };
+        let first = make();
+        let second = make();
+        let original = first.inner.read().shape.clone();
+        
+        assert!(first.rename_property("rename_x", "rename_z"));
+        assert!(second.rename_property("rename_x", "rename_z"));
+        
+        // The value moved with the key and the slot layout is unchanged
+        assert!(matches!(first.get_property("rename_x"), JSValue::Undefined));
+        assert_eq!(first.get_number_property("rename_z"), Some(1.0));
+        assert_eq!(first.get_number_property("rename_y"), Some(2.0));
+        
+        let renamed = first.inner.read().shape.clone();
+        assert!(!Arc::ptr_eq(&renamed, &original));
+        assert_eq!(renamed.property_count(), original.property_count());
+        assert!(Arc::ptr_eq(&renamed, &second.inner.read().shape));
+        assert!(Arc::ptr_eq(renamed.parent().unwrap(), original.parent().unwrap()));
+        
+        // Missing sources and taken targets are rejected
+        assert!(!first.rename_property("missing", "other"));
+        assert!(!first.rename_property("rename_y", "rename_z"));
+    }
+    
+    #[test]
+    fn test_weak_callback_fires_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        extern "C" fn count_callback(user: *mut libc::c_void) {
+            let calls = unsafe { &*(user as *const AtomicUsize) };
+            calls.fetch_add(1, Ordering::SeqCst);
+        }
+        
+        let gc = GarbageCollector::new();
+        let calls = AtomicUsize::new(0);
+        let user = &calls as *const AtomicUsize as *mut libc::c_void;
+        
+        let target = gc.create_object(JSObjectType::Object);
+        let kept = gc.create_object(JSObjectType::Object);
+        gc.add_root(Arc::as_ptr(&kept.ptr) as *mut JSObject);
+        gc.register_weak(&target, count_callback, user);
+        gc.register_weak(&kept, count_callback, user);
+        
+        // Nothing fires while the target is alive
+        gc.collect();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        
+        drop(target);
+        gc.collect();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        gc.collect();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        
+        // Callbacks run outside the collector's locks, so they may call back in
+        static GC: std::sync::OnceLock<Arc<GarbageCollector>> = std::sync::OnceLock::new();
+        extern "C" fn collect_again(_: *mut libc::c_void) {
+            GC.get().unwrap().collect();
+        }
+        let gc = GC.get_or_init(GarbageCollector::new);
+        let target = gc.create_object(JSObjectType::Object);
+        gc.register_weak(&target, collect_again, std::ptr::null_mut());
+        drop(target);
+        gc.collect();
+    }
+    
+    #[test]
+    fn test_to_int32_and_uint32() {
+        let n = |value: f64| JSValue::Number(value);
+        
+        assert_eq!(n(4294967296.0).to_uint32(), 0);
+        assert_eq!(n(4294967296.0).to_int32(), 0);
+        assert_eq!(n(-1.0).to_uint32(), 0xFFFF_FFFF);
+        assert_eq!(n(-1.0).to_int32(), -1);
+        assert_eq!(n(f64::NAN).to_int32(), 0);
+        assert_eq!(n(f64::INFINITY).to_uint32(), 0);
+        assert_eq!(n(f64::NEG_INFINITY).to_int32(), 0);
+        
+        // Truncation toward zero, then modular wraparound
+        assert_eq!(n(2147483648.0).to_int32(), i32::MIN);
+        assert_eq!(n(-3.7).to_int32(), -3);
+        assert_eq!(n(4294967297.5).to_uint32(), 1);
+        assert_eq!(n(-4294967297.0).to_int32(), -1);
+        assert_eq!(n(1e21).to_int32(), -559939584);
+        
+        // Non-numbers go through ToNumber first
+        assert_eq!(JSValue::Boolean(true).to_int32(), 1);
+        assert_eq!(JSValue::Undefined.to_int32(), 0);
+        assert_eq!(JSValue::from(" 42 ").to_int32(), 42);
+        assert_eq!(JSValue::from("0x10").to_uint32(), 16);
+        assert_eq!(JSValue::from("0x10000000000000000").to_number(), 18446744073709551616.0);
+        assert_eq!(JSValue::from(format!("0b{}", "1".repeat(65)).as_str()).to_number(), 36893488147419103232.0);
+        assert!(JSValue::from("0x").to_number().is_nan());
+        assert_eq!(JSValue::from("inf").to_int32(), 0);
+        assert!(JSValue::from("nan").to_number().is_nan());
+        assert_eq!(JSValue::from("").to_number(), 0.0);
+    }
+    
+    #[test]
+    fn test_pause_percentiles() {
+        use crate::gc::GCConfiguration;
+        use std::time::Instant;
+        
+        let gc = GarbageCollector::new();
+        assert_eq!(gc.pause_percentiles(), (0, 0, 0));
+        // Only the timed collections below may record pauses, not allocations
+        gc.configure(GCConfiguration { young_gen_threshold_kb: 1 << 20, ..GCConfiguration::default() });
+        
+        // Collections over heaps of different sizes give a spread of pauses
+        let mut longest_call = 0;
+        for round in 0..20 {
+            let kept: Vec<_> = (0..round * 50).map(|_| gc.create_object(JSObjectType::Object)).collect();
+            for obj in &kept {
+                gc.add_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+            }
+            
+            let started = Instant::now();
+            gc.collect();
+            longest_call = longest_call.max(started.elapsed().as_micros() as u64);
+            
+            for obj in &kept {
+                gc.remove_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+            }
+        }
+        
+        let (p50, p95, p99) = gc.pause_percentiles();
+        assert!(p50 <= p95 && p95 <= p99);
+        assert!(p99 <= longest_call);
+        
+        let mut buffer = [0 as libc::c_char; 64];
+        let gc_handle = Arc::as_ptr(&gc) as *mut GarbageCollector;
+        assert_eq!(js_gc_pause_percentiles_json(gc_handle, buffer.as_mut_ptr(), buffer.len()), 1);
+        let json = unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) }.to_str().unwrap();
+        assert_eq!(json, format!("{{\"p50_us\":{},\"p95_us\":{},\"p99_us\":{}}}", p50, p95, p99));
+    }
+    
+    #[test]
+    fn test_shallow_equals_same_shape() {
+        let gc = GarbageCollector::new();
+        let make = |x: f64, label: &str| {
+            let obj = gc.create_object(JSObjectType::Object);
+            obj.ptr.set_property("x", JSValue::Number(x));
+            obj.ptr.set_property("label", JSValue::String(InternedString::new(label)));
+            obj
+        };
+        
+        let a = make(1.0, "point");
+        let b = make(1.0, "point");
+        let c = make(2.0, "point");
+        assert_eq!(a.ptr.shallow_equals_same_shape(&b.ptr), Some(true));
+        assert_eq!(a.ptr.shallow_equals_same_shape(&c.ptr), Some(false));
+        assert_eq!(a.ptr.shallow_equals_same_shape(&a.ptr), Some(true));
+        
+        // Same keys in a different order is a different shape
+        let d = gc.create_object(JSObjectType::Object);
+        d.ptr.set_property("label", JSValue::String(InternedString::new("point")));
+        d.ptr.set_property("x", JSValue::Number(1.0));
+        assert_eq!(a.ptr.shallow_equals_same_shape(&d.ptr), None);
+    }
+    
+    #[test]
+    fn test_tenure_all_young() {
+        let gc = GarbageCollector::new();
+        let objects: Vec<_> = (0..10).map(|_| gc.create_object(JSObjectType::Object)).collect();
+        let old_size = gc.statistics().old_generation_size;
+        
+        assert_eq!(gc.tenure_all_young(), objects.len());
+        for obj in &objects {
+            assert_eq!(gc.generation_of(obj), Some(Generation::Old));
+        }
+        
+        let stats = gc.statistics();
+        assert_eq!(stats.young_generation_size, 0);
+        assert!(stats.old_generation_size > old_size);
+        
+        // Nothing left to promote
+        assert_eq!(gc.tenure_all_young(), 0);
+    }
+    
+    #[test]
+    fn test_array_sort_default_order() {
+        let gc = GarbageCollector::new();
+        let array = gc.create_object(JSObjectType::Array);
+        array.ptr.array_push(JSValue::Number(10.0));
+        array.ptr.array_push(JSValue::Undefined);
+        array.ptr.array_push(JSValue::Number(1.0));
+        array.ptr.array_push(JSValue::Number(2.0));
+        
+        // String order puts "10" before "2", and undefined goes last
+        array.ptr.array_sort();
+        let sorted: Vec<String> = array.ptr.values().iter().map(|v| v.to_js_string()).collect();
+        assert_eq!(sorted, ["1", "10", "2", "undefined"]);
+        
+        assert_eq!(JSValue::Number(0.5).to_js_string(), "0.5");
+        assert_eq!(JSValue::Number(1e21).to_js_string(), "1e+21");
+        assert_eq!(JSValue::Number(1.5e-7).to_js_string(), "1.5e-7");
+        assert_eq!(JSValue::Number(-0.0).to_js_string(), "0");
+        assert_eq!(JSValue::Object(array.clone()).to_js_string(), "1,10,2,");
+    }
+    
+    #[test]
+    fn test_array_sort_keeps_concurrent_pushes() {
+        let gc = GarbageCollector::new();
+        let array = gc.create_object(JSObjectType::Array);
+        for i in 0..200 {
+            array.ptr.array_push(JSValue::Integer(i));
+        }
+        
+        let pusher = {
+            let array = array.clone();
+            std::thread::spawn(move || {
+                for i in 200..400 {
+                    array.ptr.array_push(JSValue::Integer(i));
+                }
+            })
+        };
+        for _ in 0..50 {
+            array.ptr.array_sort();
+        }
+        pusher.join().unwrap();
+        
+        // A push that landed mid-sort is never overwritten by the sorted snapshot
+        assert_eq!(array.ptr.values().len(), 400);
+    }
+    
+    #[test]
+    fn test_configure_from_env() {
+        let gc = GarbageCollector::new();
+        let defaults = gc.config();
+        
+        std::env::set_var("JSGC_YOUNG_KB", "512");
+        std::env::set_var("JSGC_OLD_KB", "not a number");
+        gc.configure_from_env();
+        std::env::remove_var("JSGC_YOUNG_KB");
+        std::env::remove_var("JSGC_OLD_KB");
+        
+        let config = gc.config();
+        assert_eq!(config.young_gen_threshold_kb, 512);
+        // Invalid and unset variables are ignored
+        assert_eq!(config.old_gen_threshold_kb, defaults.old_gen_threshold_kb);
+        assert_eq!(config.verbose, defaults.verbose);
+    }
+    
+    #[test]
+    fn test_property_paths() {
+        let gc = GarbageCollector::new();
+        let root = gc.create_object(JSObjectType::Object);
+        let a = gc.create_object(JSObjectType::Object);
+        let b = gc.create_object(JSObjectType::Object);
+        root.ptr.set_property("a", JSValue::Object(a.clone()));
+        a.ptr.set_property("b", JSValue::Object(b.clone()));
+        b.ptr.set_property("c", JSValue::Number(42.0));
+        
+        assert_eq!(root.ptr.get_path(&["a", "b", "c"]).to_number(), 42.0);
+        assert!(matches!(root.ptr.get_path(&["a", "missing", "c"]), JSValue::Undefined));
+        // A primitive in the middle of the path stops the walk
+        assert!(matches!(root.ptr.get_path(&["a", "b", "c", "d"]), JSValue::Undefined));
+        
+        root.ptr.set_path(&gc, &["x", "y", "z"], JSValue::from("deep"));
+        assert!(root.ptr.get_object_property("x").unwrap().ptr.get_object_property("y").is_some());
+        assert_eq!(root.ptr.get_path(&["x", "y", "z"]).to_js_string(), "deep");
+        
+        // Existing intermediates are reused rather than replaced
+        root.ptr.set_path(&gc, &["a", "b", "d"], JSValue::Boolean(true));
+        assert_eq!(b.ptr.get_bool_property("d"), Some(true));
+        assert_eq!(b.ptr.get_number_property("c"), Some(42.0));
+    }
+    
+    #[test]
+    fn test_ffi_object_handles_are_identity_stable() {
+        let gc = GarbageCollector::new();
+        let parent = gc.create_object(JSObjectType::Object);
+        let child = gc.create_object(JSObjectType::Object);
+        parent.ptr.set_property("child", JSValue::Object(child.clone()));
+        
+        let parent_handle = Arc::as_ptr(&parent.ptr) as *mut JSObject;
+        let key = std::ffi::CString::new("child").unwrap();
+        let mut first = std::ptr::null_mut();
+        let mut second = std::ptr::null_mut();
+        assert_eq!(js_get_property_object(parent_handle, key.as_ptr(), &mut first), 1);
+        assert_eq!(js_get_property_object(parent_handle, key.as_ptr(), &mut second), 1);
+        assert_eq!(first, second);
+        assert_eq!(first as *const JSObject, Arc::as_ptr(&child.ptr));
+        
+        // Each handout holds its own reference until released
+        let baseline = Arc::strong_count(&child.ptr);
+        js_release_object(first);
+        assert_eq!(Arc::strong_count(&child.ptr), baseline - 1);
+        js_release_object(second);
+        assert_eq!(Arc::strong_count(&child.ptr), baseline - 2);
+    }
+    
+    #[test]
+    fn test_oom_handler_frees_space_for_retry() {
+        use crate::gc::GCConfiguration;
+        use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+        
+        // The handler has no user data, so it finds its state through statics
+        static GC: AtomicPtr<GarbageCollector> = AtomicPtr::new(std::ptr::null_mut());
+        static HOG: AtomicPtr<JSObject> = AtomicPtr::new(std::ptr::null_mut());
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        
+        extern "C" fn release_hog() -> libc::c_int {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            let gc = unsafe { &*GC.load(Ordering::SeqCst) };
+            let hog = HOG.swap(std::ptr::null_mut(), Ordering::SeqCst);
+            if hog.is_null() {
+                return 0;
+            }
+            gc.remove_root(hog);
+            1
+        }
+        
+        let gc = GarbageCollector::new();
+        GC.store(Arc::as_ptr(&gc) as *mut GarbageCollector, Ordering::SeqCst);
+        gc.configure(GCConfiguration {
+            max_heap_kb: 1,
+            ..GCConfiguration::default()
+        });
+        gc.set_oom_handler(release_hog);
+        
+        // A rooted array that alone outgrows the 1KB heap
+        let hog = gc.create_object(JSObjectType::Array);
+        for i in 0..256 {
+            hog.ptr.array_push(JSValue::Number(i as f64));
+        }
+        gc.add_root(Arc::as_ptr(&hog.ptr) as *mut JSObject);
+        HOG.store(Arc::as_ptr(&hog.ptr) as *mut JSObject, Ordering::SeqCst);
+        drop(hog);
+        gc.collect();
+        
+        // Collecting alone can't make room, so the handler unroots the hog and the retry succeeds
+        assert!(gc.try_create_object(JSObjectType::Object).is_some());
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+        
+        // Once nothing else can be freed, allocation fails
+        let kept: Vec<_> = std::iter::from_fn(|| gc.try_create_object(JSObjectType::Object))
+            .inspect(|obj| gc.add_root(Arc::as_ptr(&obj.ptr) as *mut JSObject))
+            .take(100)
+            .collect();
+        assert!(kept.len() < 100);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+    }
+    
+    #[test]
+    fn test_property_names_cached_on_shape() {
+        let gc = GarbageCollector::new();
+        let obj = gc.create_object(JSObjectType::Object);
+        obj.ptr.set_property("first", JSValue::Number(1.0));
+        obj.ptr.set_property("second", JSValue::Number(2.0));
+        obj.ptr.set_property("third", JSValue::Number(3.0));
+        
+        let names = obj.ptr.property_names();
+        let keys: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+        assert_eq!(keys, ["first", "second", "third"]);
+        
+        // Later calls hand back the same interned strings rather than new ones
+        for _ in 0..3 {
+            let again = obj.ptr.property_names();
+            assert_eq!(again, names);
+            for (a, b) in again.iter().zip(&names) {
+                assert!(Arc::ptr_eq(&a.inner, &b.inner));
+            }
+        }
+    }
+    
+    #[test]
+    fn test_write_barrier_statistics() {
+        let gc = GarbageCollector::new();
+        let old = gc.create_object(JSObjectType::Object);
+        gc.add_root(Arc::as_ptr(&old.ptr) as *mut JSObject);
+        gc.tenure_all_young();
+        assert!(old.ptr.is_tenured());
+        assert_eq!(gc.remembered_set_size(), 0);
+        
+        // Storing young objects into an old object goes through the barrier
+        assert_eq!(gc.statistics().write_barrier_hits, 0);
+        let young = gc.create_object(JSObjectType::Object);
+        old.ptr.set_property("young", JSValue::Object(young.clone()));
+        old.ptr.set_property("other", JSValue::Object(gc.create_object(JSObjectType::Object)));
+        assert_eq!(gc.statistics().write_barrier_hits, 2);
+        assert_eq!(gc.statistics().write_barrier_hits, 2);
+        
+        // Hits are counted by the collector that owns the old object
+        let other_gc = GarbageCollector::new();
+        let other_old = other_gc.create_object(JSObjectType::Object);
+        other_gc.add_root(Arc::as_ptr(&other_old.ptr) as *mut JSObject);
+        other_gc.tenure_all_young();
+        other_old.ptr.set_property("young", JSValue::Object(other_gc.create_object(JSObjectType::Object)));
+        assert_eq!(other_gc.statistics().write_barrier_hits, 1);
+        assert_eq!(gc.statistics().write_barrier_hits, 2);
+        assert!(old.ptr.is_remembered());
+        assert_eq!(gc.remembered_set_size(), 1);
+        
+        // Young-to-young stores don't count
+        assert!(!young.ptr.is_remembered());
+        young.ptr.set_property("peer", JSValue::Object(gc.create_object(JSObjectType::Object)));
+        assert!(!young.ptr.is_remembered());
+        
+        // Once the young referents are promoted the old object leaves the set
+        gc.collect();
+        assert_eq!(gc.statistics().remembered_set_size, 1);
+        gc.collect();
+        assert!(!old.ptr.is_remembered());
+        assert_eq!(gc.statistics().remembered_set_size, 0);
+    }
+    
+    #[test]
+    fn test_prototype_ffi() {
+        let gc = GarbageCollector::new();
+        let gc_handle = Arc::as_ptr(&gc) as *mut GarbageCollector;
+        let base = js_create_object(gc_handle, 0);
+        let middle = js_create_object(gc_handle, 0);
+        let leaf = js_create_object(gc_handle, 0);
+        
+        let key = std::ffi::CString::new("greeting").unwrap();
+        let value = std::ffi::CString::new("hello").unwrap();
+        js_set_property_string(base, key.as_ptr(), value.as_ptr());
+        
+        let mut proto = std::ptr::null_mut();
+        assert_eq!(js_get_prototype(leaf, &mut proto), 0);
+        assert!(proto.is_null());
+        
+        assert_eq!(js_set_prototype(middle, base), 1);
+        assert_eq!(js_set_prototype(leaf, middle), 1);
+        assert_eq!(js_get_prototype(leaf, &mut proto), 1);
+        assert_eq!(proto, middle);
+        js_release_object(proto);
+        
+        // The property is found two links up the chain, but only by the _with_proto getters
+        let mut buffer = [0 as libc::c_char; 16];
+        assert_eq!(js_get_property_string(leaf, key.as_ptr(), buffer.as_mut_ptr(), buffer.len()), 0);
+        assert_eq!(js_get_property_string_with_proto(leaf, key.as_ptr(), buffer.as_mut_ptr(), buffer.len()), 6);
+        let read = unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) };
+        assert_eq!(read.to_str().unwrap(), "hello");
+        assert_eq!(js_has_own_property(leaf, key.as_ptr()), 0);
+        
+        let number_key = std::ffi::CString::new("count").unwrap();
+        let flag_key = std::ffi::CString::new("flag").unwrap();
+        let object_key = std::ffi::CString::new("child").unwrap();
+        js_set_property_number(base, number_key.as_ptr(), 2.0);
+        js_set_property_boolean(base, flag_key.as_ptr(), 1);
+        js_set_property_object(base, object_key.as_ptr(), middle);
+        let (mut number, mut flag, mut object) = (0.0, 0, std::ptr::null_mut());
+        assert_eq!(js_get_property_number(leaf, number_key.as_ptr(), &mut number), 0);
+        assert_eq!(js_get_property_number_with_proto(leaf, number_key.as_ptr(), &mut number), 1);
+        assert_eq!(number, 2.0);
+        assert_eq!(js_get_property_boolean(leaf, flag_key.as_ptr(), &mut flag), 0);
+        assert_eq!(js_get_property_boolean_with_proto(leaf, flag_key.as_ptr(), &mut flag), 1);
+        assert_eq!(flag, 1);
+        assert_eq!(js_get_property_object(leaf, object_key.as_ptr(), &mut object), 0);
+        assert_eq!(js_get_property_object_with_proto(leaf, object_key.as_ptr(), &mut object), 1);
+        assert_eq!(object, middle);
+        js_release_object(object);
+        
+        // Closing the loop is refused and leaves the chain untouched
+        assert_eq!(js_set_prototype(base, leaf), -1);
+        assert_eq!(js_set_prototype(base, base), -1);
+        assert_eq!(js_get_prototype(base, &mut proto), 0);
+        
+        // A null prototype clears the link
+        assert_eq!(js_set_prototype(leaf, std::ptr::null_mut()), 1);
+        assert_eq!(js_get_property_string_with_proto(leaf, key.as_ptr(), buffer.as_mut_ptr(), buffer.len()), 0);
+        
+        for obj in [base, middle, leaf] {
+            js_release_object(obj);
+        }
+    }
+    
+    #[test]
+    fn test_large_strings_skip_deduplication() {
+        let interner = StringInterner::new();
+        interner.set_large_string_threshold(1024);
+        
+        let huge = "x".repeat(1 << 20);
+        let stored = interner.intern_large(huge.clone());
+        assert_eq!(interner.len(), 0);
+        assert_eq!(interner.large_string_stats(), (1, huge.len()));
+        assert!(interner.is_pooled(&stored));
+        
+        // Still readable like any other string
+        assert_eq!(stored.len(), huge.len());
+        assert!(stored.starts_with("xxx"));
+        assert_eq!(stored.as_str(), huge);
+        
+        // Short strings are interned normally
+        let small = interner.intern_large("small".to_string());
+        assert_eq!(interner.len(), 1);
+        assert!(!interner.is_pooled(&small));
+        
+        drop(stored);
+        assert_eq!(interner.large_string_stats(), (0, 0));
+    }
+    
+    #[test]
+    fn test_assert_collected_unrooted() {
+        let gc = GarbageCollector::new();
+        let obj = gc.create_object(JSObjectType::Object);
+        let weak = obj.downgrade();
+        drop(obj);
+        gc.assert_collected(&weak);
+    }
+    
+    #[test]
+    #[should_panic(expected = "is a root, retained by 1 object(s)")]
+    fn test_assert_collected_reports_retainers() {
+        let gc = GarbageCollector::new();
+        let obj = gc.create_object(JSObjectType::Object);
+        let holder = gc.create_object(JSObjectType::Object);
+        holder.ptr.set_property("held", JSValue::Object(obj.clone()));
+        gc.add_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+        gc.add_root(Arc::as_ptr(&holder.ptr) as *mut JSObject);
+        
+        let weak = obj.downgrade();
+        drop(obj);
+        gc.assert_collected(&weak);
+    }
+    
+    #[test]
+    fn test_intern_utf16() {
+        let text = "utf16 caf\u{e9} \u{1f600}";
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let from_units = InternedString::from_utf16(&units);
+        assert!(Arc::ptr_eq(&from_units.inner, &InternedString::new(text).inner));
+        
+        // A lone surrogate becomes the replacement character
+        let lone = InternedString::from_utf16(&[0x61, 0xD800, 0x62]);
+        assert_eq!(lone.as_str(), "a\u{fffd}b");
+    }
+    
+    #[test]
+    fn test_promotion_callback_reports_ages() {
+        use crate::gc::GCConfiguration;
+        use std::sync::Mutex;
+        
+        static PROMOTED: Mutex<Vec<(usize, u32)>> = Mutex::new(Vec::new());
+        
+        extern "C" fn on_promotion(obj: *const JSObject, age: u32) {
+            PROMOTED.lock().unwrap().push((obj as usize, age));
+        }
+        
+        let gc = GarbageCollector::new();
+        gc.configure(GCConfiguration {
+            promotion_age: 3,
+            ..GCConfiguration::default()
+        });
+        gc.set_promotion_callback(on_promotion);
+        
+        let obj = gc.create_object(JSObjectType::Object);
+        gc.add_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+        let address = Arc::as_ptr(&obj.ptr) as usize;
+        let promotions = || PROMOTED.lock().unwrap().iter().filter(|(ptr, _)| *ptr == address).count();
+        
+        gc.collect();
+        gc.collect();
+        assert_eq!(promotions(), 0);
+        gc.collect();
+        assert_eq!(PROMOTED.lock().unwrap().iter().find(|(ptr, _)| *ptr == address), Some(&(address, 3)));
+        
+        // Tenuring everything reports each object's current age
+        let fresh = gc.create_object(JSObjectType::Object);
+        let fresh_address = Arc::as_ptr(&fresh.ptr) as usize;
+        gc.tenure_all_young();
+        assert!(PROMOTED.lock().unwrap().contains(&(fresh_address, 0)));
+        assert_eq!(promotions(), 1);
+    }
+    
+    #[test]
+    fn test_canonical_values() {
+        let a = JSValue::canonical(JSValue::Number(42.0));
+        let b = JSValue::canonical(JSValue::from(42.0));
+        assert!(a.same_representation(&b));
+        
+        // NaNs with different payloads share one representation once canonical
+        let quiet = JSValue::Number(f64::NAN);
+        let payload = JSValue::Number(f64::from_bits(f64::NAN.to_bits() | 1));
+        assert!(!quiet.same_representation(&payload));
+        assert!(JSValue::canonical(quiet).same_representation(&JSValue::canonical(payload)));
+        
+        // -0 stays distinct from 0, as with Object.is
+        let negative_zero = JSValue::canonical(JSValue::Number(-0.0));
+        assert!(!negative_zero.same_representation(&JSValue::Number(0.0)));
+        assert!(JSValue::canonical(JSValue::Boolean(true)).same_representation(&JSValue::Boolean(true)));
+        assert!(JSValue::canonical(JSValue::from("text")).same_representation(&JSValue::from("text")));
+    }
+    
+    #[test]
+    fn test_reachable_from_limited() {
+        let gc = GarbageCollector::new();
+        let chain: Vec<_> = (0..5).map(|_| gc.create_object(JSObjectType::Object)).collect();
+        for pair in chain.windows(2) {
+            pair[0].ptr.set_property("next", JSValue::Object(pair[1].clone()));
+        }
+        
+        // The root plus a 4-deep chain below it; only the first two levels come back
+        let reachable = gc.reachable_from_limited(&chain[0], 2);
+        let ids: Vec<u64> = reachable.iter().map(|obj| obj.ptr.id()).collect();
+        assert_eq!(ids, [chain[1].ptr.id(), chain[2].ptr.id()]);
+        
+        assert_eq!(gc.reachable_from_limited(&chain[0], 10).len(), 4);
+        assert!(gc.reachable_from_limited(&chain[0], 0).is_empty());
+    }
+    
+    #[test]
+    fn test_shape_change_callback() {
+        use std::sync::Mutex;
+        
+        static CHANGES: Mutex<Vec<(usize, usize, usize)>> = Mutex::new(Vec::new());
+        
+        extern "C" fn on_shape_change(obj: *const JSObject, old_shape_id: usize, new_shape_id: usize) {
+            CHANGES.lock().unwrap().push((obj as usize, old_shape_id, new_shape_id));
+        }
+        
+        let gc = GarbageCollector::new();
+        let obj = gc.create_object(JSObjectType::Object);
+        obj.ptr.set_shape_change_callback(on_shape_change);
+        let address = Arc::as_ptr(&obj.ptr) as usize;
+        let changes = || -> Vec<_> {
+            CHANGES.lock().unwrap().iter().filter(|change| change.0 == address).cloned().collect()
+        };
+        
+        let before = obj.ptr.inner.read().shape.id();
+        obj.ptr.set_property("x", JSValue::Number(1.0));
+        let after = obj.ptr.inner.read().shape.id();
+        assert_ne!(before, after);
+        assert_eq!(changes(), [(address, before, after)]);
+        
+        // Updating an existing property keeps the shape
+        obj.ptr.set_property("x", JSValue::Number(2.0));
+        assert_eq!(changes().len(), 1);
+        
+        assert!(obj.ptr.rename_property("x", "y"));
+        assert_eq!(changes().len(), 2);
+    }
+    
+    #[test]
+    fn test_deferred_free_generations() {
+        use crate::gc::GCConfiguration;
+        
+        let gc = GarbageCollector::new();
+        gc.configure(GCConfiguration {
+            deferred_free_generations: 1,
+            ..GCConfiguration::default()
+        });
+        
+        let garbage = gc.create_object(JSObjectType::Object);
+        garbage.ptr.set_property("label", JSValue::from("dying"));
+        let id = garbage.ptr.id();
+        let weak = garbage.downgrade();
+        drop(garbage);
+        
+        // Still inspectable after one collection
+        gc.collect();
+        let condemned = gc.condemned_objects();
+        assert_eq!(condemned.len(), 1);
+        assert_eq!(condemned[0].ptr.id(), id);
+        assert_eq!(condemned[0].ptr.get_property("label").to_js_string(), "dying");
+        drop(condemned);
+        
+        // Gone after the next one
+        gc.collect();
+        assert!(gc.condemned_objects().is_empty());
+        assert!(weak.upgrade().is_none());
+    }
+    
+    #[test]
+    fn test_delete_property() {
+        let gc = GarbageCollector::new();
+        let make = || {
+            let obj = gc.create_object(JSObjectType::Object);
+            obj.ptr.set_property("a", JSValue::Number(1.0));
+            obj.ptr.set_property("b", JSValue::Number(2.0));
+            obj.ptr.set_property("c", JSValue::Number(3.0));
+            obj
+        };
+        let remaining = |obj: &JSObjectHandle| -> Vec<(String, f64)> {
+            obj.ptr.entries().iter().map(|(key, value)| (key.to_string(), value.to_number())).collect()
+        };
+        
+        let first = make();
+        assert!(first.ptr.delete_property("a"));
+        assert!(matches!(first.ptr.get_property("a"), JSValue::Undefined));
+        assert_eq!(remaining(&first), [("b".to_string(), 2.0), ("c".to_string(), 3.0)]);
+        
+        let middle = make();
+        assert!(middle.ptr.delete_property("b"));
+        assert_eq!(middle.ptr.get_number_property("a"), Some(1.0));
+        assert_eq!(middle.ptr.get_number_property("c"), Some(3.0));
+        assert!(!middle.ptr.has_own_property("b"));
+        assert_eq!(middle.ptr.property_count(), 2);
+        
+        let last = make();
+        assert!(last.ptr.delete_property("c"));
+        assert_eq!(remaining(&last), [("a".to_string(), 1.0), ("b".to_string(), 2.0)]);
+        assert!(!last.ptr.delete_property("c"));
+        
+        // Objects left with the same keys share a shape again
+        let fresh = gc.create_object(JSObjectType::Object);
+        fresh.ptr.set_property("a", JSValue::Number(0.0));
+        fresh.ptr.set_property("c", JSValue::Number(0.0));
+        assert!(Arc::ptr_eq(&middle.ptr.inner.read().shape, &fresh.ptr.inner.read().shape));
+        
+        // New properties still land in the right slots afterwards
+        middle.ptr.set_property("d", JSValue::Number(4.0));
+        assert_eq!(middle.ptr.get_number_property("c"), Some(3.0));
+        assert_eq!(middle.ptr.get_number_property("d"), Some(4.0));
+    }
+    
+    #[test]
+    fn test_freeze_shape() {
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_property("x", JSValue::Number(1.0));
+        obj.set_property("y", JSValue::Number(2.0));
+        obj.freeze_shape();
+        assert!(obj.is_shape_frozen());
+        let shape_id = obj.inner.read().shape.id();
+        
+        assert!(obj.set_property("x", JSValue::Number(10.0)));
+        assert_eq!(obj.get_number_property("x"), Some(10.0));
+        
+        assert!(!obj.set_property("z", JSValue::Number(3.0)));
+        assert!(!obj.delete_property("y"));
+        assert!(!obj.rename_property("x", "w"));
+        obj.reserve_properties(&["z"]);
+        
+        assert_eq!(obj.inner.read().shape.id(), shape_id);
+        assert!(!obj.has_own_property("z"));
+        assert_eq!(obj.get_number_property("y"), Some(2.0));
+        
+        let handle = Arc::as_ptr(&obj) as *mut JSObject;
+        let key = std::ffi::CString::new("z").unwrap();
+        assert_eq!(js_set_property_number(handle, key.as_ptr(), 3.0), 0);
+        assert_eq!(obj.inner.read().shape.id(), shape_id);
+    }
+    
+    #[test]
+    fn test_begin_shutdown_abandons_incremental_cycle() {
+        use crate::gc::CollectProgress;
+        
+        let gc = GarbageCollector::new();
+        let root = gc.create_object(JSObjectType::Object);
+        gc.add_root(Arc::as_ptr(&root.ptr) as *mut JSObject);
+        let mut garbage = Vec::new();
+        for i in 0..50 {
+            let obj = gc.create_object(JSObjectType::Object);
+            if i % 2 == 0 {
+                root.ptr.set_property(&format!("k{}", i), JSValue::Object(obj.clone()));
+            }
+            garbage.push(obj.downgrade());
+        }
+        
+        // Start a cycle and take a few steps without finishing it
+        assert_eq!(gc.try_collect(0), CollectProgress::InProgress);
+        gc.begin_shutdown();
+        assert!(gc.is_shutting_down());
+        
+        // The next step abandons the cycle and no new one starts
+        let mut steps = 0;
+        while gc.try_collect(0) == CollectProgress::InProgress {
+            steps += 1;
+            assert!(steps < 2, "collection kept running after shutdown");
+        }
+        assert!(gc.collect_young_incremental(1));
+        gc.collect();
+        
+        // Objects not yet swept stay alive, unmarked, and the heap is still consistent
+        assert!(garbage.iter().all(|weak| weak.upgrade().is_some_and(|obj| !obj.ptr.is_marked())));
+        assert!(!root.ptr.is_marked());
+        assert_eq!(gc.heap_snapshot_summary().get(JSObjectType::Object).count, 51);
+        assert!(gc.verify_refcounts().is_ok());
+    }
+    
+    #[test]
+    fn test_young_objects_held_by_old_objects_survive_minor_collection() {
+        use crate::gc::Generation;
+        
+        let gc = GarbageCollector::new();
+        let rooted = gc.create_object(JSObjectType::Object);
+        gc.add_root(Arc::as_ptr(&rooted.ptr) as *mut JSObject);
+        let unrooted = gc.create_object(JSObjectType::Object);
+        gc.tenure_all_young();
+        
+        // Fresh young objects stored into old ones, one reachable from a root
+        // and one only reachable through an old object a minor cycle won't sweep
+        let young = gc.create_object(JSObjectType::Object);
+        let grandchild = gc.create_object(JSObjectType::Object);
+        young.ptr.set_property("child", JSValue::Object(grandchild.clone()));
+        rooted.ptr.set_property("young", JSValue::Object(young.clone()));
+        let orphan = gc.create_object(JSObjectType::Object);
+        unrooted.ptr.set_property("young", JSValue::Object(orphan.clone()));
+        
+        gc.collect_young();
+        assert_eq!(gc.generation_of(&young), Some(Generation::Young));
+        assert_eq!(gc.generation_of(&grandchild), Some(Generation::Young));
+        assert_eq!(gc.generation_of(&orphan), Some(Generation::Young));
+        
+        // The incremental minor cycle traces the remembered set too
+        while !gc.collect_young_incremental(1) {}
+        assert!(gc.generation_of(&young).is_some());
+        assert!(gc.generation_of(&orphan).is_some());
+    }
+    
+    #[cfg(feature = "shape-type-profile")]
+    #[test]
+    fn test_slot_type_profile() {
+        let objects: Vec<_> = (0..100).map(|i| {
+            let obj = JSObject::new(JSObjectType::Object);
+            obj.reserve_properties(&["profiled_count", "profiled_label"]);
+            obj.set_property("profiled_count", JSValue::Number(i as f64));
+            let label = if i % 4 == 0 { JSValue::Null } else { JSValue::from("label") };
+            obj.set_property("profiled_label", label);
+            obj
+        }).collect();
+        
+        let shape = objects[0].inner.read().shape.clone();
+        let count_slot = shape.get_property_index("profiled_count").unwrap();
+        let label_slot = shape.get_property_index("profiled_label").unwrap();
+        assert_eq!(shape.slot_type_profile(), vec![
+            (count_slot, std::collections::HashMap::from([("number", 100)])),
+            (label_slot, std::collections::HashMap::from([("null", 25), ("string", 75)])),
+        ]);
+    }
+    
+    #[test]
+    fn test_collect_frees_reference_cycles() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        static FINALIZED: AtomicUsize = AtomicUsize::new(0);
+        extern "C" fn count_finalized(_obj: *mut JSObject) {
+            FINALIZED.fetch_add(1, Ordering::SeqCst);
+        }
+        
+        let gc = GarbageCollector::new();
+        let make_cycle = || {
+            let a = gc.create_object(JSObjectType::Object);
+            let b = gc.create_object(JSObjectType::Object);
+            a.ptr.set_property("b", JSValue::Object(b.clone()));
+            b.ptr.set_property("a", JSValue::Object(a.clone()));
+            a.ptr.set_finalizer(count_finalized);
+            b.ptr.set_finalizer(count_finalized);
+            (a, b)
+        };
+        
+        let (a, b) = make_cycle();
+        gc.add_root(Arc::as_ptr(&a.ptr) as *mut JSObject);
+        gc.add_root(Arc::as_ptr(&b.ptr) as *mut JSObject);
+        gc.remove_root(Arc::as_ptr(&a.ptr) as *mut JSObject);
+        gc.remove_root(Arc::as_ptr(&b.ptr) as *mut JSObject);
+        let (weak_a, weak_b) = (a.downgrade(), b.downgrade());
+        drop((a, b));
+        
+        // A host handle keeps this one's references intact
+        let (held, other) = make_cycle();
+        drop(other);
+        
+        gc.collect();
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 2);
+        assert!(weak_a.upgrade().is_none());
+        assert!(weak_b.upgrade().is_none());
+        let other = held.ptr.get_object_property("b").unwrap();
+        assert!(other.ptr.get_object_property("a").is_some_and(|a| Arc::ptr_eq(&a.ptr, &held.ptr)));
+    }
+    
+    #[test]
+    fn test_import_objects_into_old_generation() {
+        use crate::gc::Generation;
+        
+        let gc = GarbageCollector::new();
+        let objects: Vec<_> = (0..10).map(|i| {
+            let obj = JSObject::new(JSObjectType::Object);
+            obj.set_property("index", JSValue::Number(i as f64));
+            obj
+        }).collect();
+        let first = JSObjectHandle { ptr: objects[0].clone() };
+        
+        gc.import_objects(objects, Generation::Old);
+        let stats = gc.statistics();
+        let summary = gc.heap_snapshot_summary();
+        assert_eq!(summary.get(JSObjectType::Object).count, 10);
+        assert_eq!(stats.old_generation_size, summary.get(JSObjectType::Object).bytes as usize);
+        assert_eq!(stats.young_generation_size, 0);
+        assert_eq!(gc.generation_of(&first), Some(Generation::Old));
+        assert!(first.ptr.is_tenured());
+        assert!(gc.object_by_id(first.ptr.id()).is_some());
+    }
+    
+    #[test]
+    fn test_array_elements() {
+        let gc = GarbageCollector::new();
+        let array = gc.create_object(JSObjectType::Array);
+        gc.add_root(Arc::as_ptr(&array.ptr) as *mut JSObject);
+        
+        // Appending at the current length grows by one
+        for i in 0..3 {
+            array.ptr.set_element(array.ptr.array_length(), JSValue::Number(i as f64));
+        }
+        assert_eq!(array.ptr.array_length(), 3);
+        assert_eq!(array.ptr.get_element(2).to_number(), 2.0);
+        assert_eq!(array.ptr.get_property("1").to_number(), 1.0);
+        
+        // Sparse writes fill the gap with undefined
+        array.ptr.set_element(6, JSValue::from("end"));
+        assert_eq!(array.ptr.array_length(), 7);
+        assert!(matches!(array.ptr.get_element(4), JSValue::Undefined));
+        assert!(matches!(array.ptr.get_element(100), JSValue::Undefined));
+        assert!(array.ptr.property_names().is_empty());
+        
+        // Objects held only by elements survive collection
+        let element = gc.create_object(JSObjectType::Object);
+        let weak = element.downgrade();
+        array.ptr.set_element(4, JSValue::Object(element));
+        gc.collect();
+        let element = weak.upgrade().expect("element was freed");
+        assert!(gc.generation_of(&element).is_some());
+        
+        array.ptr.set_element(4, JSValue::Undefined);
+        drop(element);
+        gc.collect();
+        assert!(weak.upgrade().is_none());
+    }
+    
+    #[test]
+    fn test_has_property_leaves_refcounts_alone() {
+        let obj = JSObject::new(JSObjectType::Object);
+        let child = JSObject::new(JSObjectType::Object);
+        let text = InternedString::new("has_property_value");
+        obj.set_property("child", JSValue::Object(JSObjectHandle { ptr: child.clone() }));
+        obj.set_property("text", JSValue::String(text.clone()));
+        let child_count = Arc::strong_count(&child);
+        let text_count = Arc::strong_count(&text.inner);
+        
+        let handle = Arc::as_ptr(&obj) as *mut JSObject;
+        for key in ["child", "text"] {
+            assert!(obj.has_property(key));
+            let key = std::ffi::CString::new(key).unwrap();
+            assert_eq!(js_has_property(handle, key.as_ptr()), 1);
+        }
+        assert!(!obj.has_property("missing"));
+        let missing = std::ffi::CString::new("missing").unwrap();
+        assert_eq!(js_has_property(handle, missing.as_ptr()), 0);
+        
+        assert_eq!(Arc::strong_count(&child), child_count);
+        assert_eq!(Arc::strong_count(&text.inner), text_count);
+    }
+    
+    #[test]
+    fn test_slot_access_guarded_by_shape_id() {
+        let point = JSObject::new(JSObjectType::Object);
+        point.set_property("x", JSValue::Number(1.0));
+        point.set_property("y", JSValue::Number(2.0));
+        
+        // What an inline cache would remember after a keyed lookup
+        let cached_shape = point.shape_id();
+        let cached_slot = point.inner.read().shape.get_property_index("y").unwrap();
+        
+        point.set_slot(cached_slot, JSValue::Number(5.0));
+        assert_eq!(point.get_slot(cached_slot).to_number(), 5.0);
+        assert_eq!(point.get_number_property("y"), Some(5.0));
+        assert_eq!(point.shape_id(), cached_shape);
+        
+        // A differently shaped object fails the guard, so the cached slot isn't used
+        let other = JSObject::new(JSObjectType::Object);
+        other.set_property("y", JSValue::Number(7.0));
+        other.set_property("x", JSValue::Number(8.0));
+        let read_y = |obj: &JSObject| if obj.shape_id() == cached_shape {
+            obj.get_slot(cached_slot)
+        } else {
+            obj.get_property("y")
+        };
+        assert_ne!(other.shape_id(), cached_shape);
+        assert_eq!(read_y(&other).to_number(), 7.0);
+        assert_eq!(read_y(&point).to_number(), 5.0);
+    }
+    
+    #[test]
+    fn test_interner_hit_miss_stats() {
+        let interner = StringInterner::new();
+        for _ in 0..100 {
+            interner.intern("repeated");
+        }
+        interner.intern_owned("other".to_string());
+        assert_eq!(interner.hit_miss_stats(), (99, 2));
+        
+        // Hold a handle so a concurrent sweep can't drop the string mid-loop
+        let _held = InternedString::new("hit_miss_repeated");
+        let (hits_before, _) = hit_miss_stats();
+        for _ in 0..100 {
+            InternedString::new("hit_miss_repeated");
+        }
+        let (hits_after, _) = hit_miss_stats();
+        assert!(hits_after - hits_before >= 99);
+    }
+    
+    #[test]
+    fn test_property_lookup_does_not_intern_key() {
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_property("present", JSValue::Number(1.0));
+        
+        for _ in 0..1000 {
+            assert!(matches!(obj.get_property("lookup_only_key_never_stored"), JSValue::Undefined));
+            assert_eq!(obj.get_number_property("present"), Some(1.0));
+        }
+        assert!(!obj.has_property("lookup_only_key_never_stored"));
+        
+        // Other tests intern concurrently, so check for the key rather than the count
+        assert!(!export_table().iter().any(|s| s == "lookup_only_key_never_stored"));
+    }
+    
+    #[test]
+    fn test_trace_callback_keeps_host_values_alive() {
+        use crate::gc::GCConfiguration;
+        
+        let gc = GarbageCollector::new();
+        // Keep everything young so every collection can free it
+        gc.configure(GCConfiguration { promotion_age: u8::MAX, ..GCConfiguration::default() });
+        let host_values: Arc<parking_lot::Mutex<Vec<JSValue>>> = Arc::default();
+        let traced = host_values.clone();
+        gc.add_trace_callback(move |tracer| {
+            for value in traced.lock().iter() {
+                tracer.trace_value(value);
+            }
+        });
+        
+        let kept = gc.create_object(JSObjectType::Object);
+        let child = gc.create_object(JSObjectType::Object);
+        kept.ptr.set_property("child", JSValue::Object(child.clone()));
+        let dropped = gc.create_object(JSObjectType::Object);
+        host_values.lock().extend([JSValue::Object(kept.clone()), JSValue::Number(1.0)]);
+        let (kept_weak, child_weak, dropped_weak) = (kept.downgrade(), child.downgrade(), dropped.downgrade());
+        drop((kept, child, dropped));
+        
+        gc.collect();
+        assert!(kept_weak.upgrade().is_some_and(|obj| gc.generation_of(&obj).is_some()));
+        assert!(child_weak.upgrade().is_some_and(|obj| gc.generation_of(&obj).is_some()));
+        assert!(dropped_weak.upgrade().is_none());
+        
+        // Values stored while an incremental cycle is marking are picked up too
+        let late = gc.create_object(JSObjectType::Object);
+        let late_weak = late.downgrade();
+        while gc.try_collect(0) == CollectProgress::InProgress {
+            if let Some(late) = late_weak.upgrade() {
+                host_values.lock().push(JSValue::Object(late));
+            }
+        }
+        drop(late);
+        assert!(late_weak.upgrade().is_some_and(|obj| gc.generation_of(&obj).is_some()));
+        
+        host_values.lock().clear();
+        gc.collect();
+        assert!(kept_weak.upgrade().is_none());
+    }
+    
+    #[test]
+    fn test_weak_ref_does_not_keep_object_alive() {
+        let gc = GarbageCollector::new();
+        let obj = gc.create_object(JSObjectType::Object);
+        let weak = JSObject::downgrade(&obj.ptr);
+        let ffi_weak = js_create_weak_ref(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+        
+        // While the object is alive both kinds of weak reference resolve to it
+        assert!(weak.upgrade().is_some_and(|handle| Arc::ptr_eq(&handle.ptr, &obj.ptr)));
+        let strong = js_weak_ref_deref(ffi_weak);
+        assert_eq!(strong as *const JSObject, Arc::as_ptr(&obj.ptr));
+        js_release_object(strong);
+        
+        drop(obj);
+        gc.collect();
+        assert!(weak.upgrade().is_none());
+        assert!(js_weak_ref_deref(ffi_weak).is_null());
+        js_release_weak_ref(ffi_weak);
+    }
+    
+    #[test]
+    fn test_try_restore_shape() {
+        use crate::gc::GCConfiguration;
+        
+        let gc = GarbageCollector::new();
+        let same_shape = |a: &JSObject, b: &JSObject| Arc::ptr_eq(&a.inner.read().shape, &b.inner.read().shape);
+        let reference = JSObject::new(JSObjectType::Object);
+        reference.set_property("restore_a", JSValue::Number(1.0));
+        reference.set_property("restore_c", JSValue::Number(2.0));
+        
+        // Renaming leaves the object on a shape of its own
+        let renamed = JSObject::new(JSObjectType::Object);
+        renamed.set_property("restore_a", JSValue::Number(1.0));
+        renamed.set_property("restore_b", JSValue::Number(2.0));
+        assert!(renamed.rename_property("restore_b", "restore_c"));
+        assert!(!same_shape(&renamed, &reference));
+        
+        assert!(renamed.try_restore_shape(&gc));
+        assert!(same_shape(&renamed, &reference));
+        assert_eq!(renamed.get_number_property("restore_c"), Some(2.0));
+        assert!(!renamed.try_restore_shape(&gc));
+        
+        // Major collections restore surviving objects on their own
+        gc.configure(GCConfiguration { old_gen_threshold_kb: 0, ..GCConfiguration::default() });
+        let survivor = gc.create_object(JSObjectType::Object);
+        gc.add_root(Arc::as_ptr(&survivor.ptr) as *mut JSObject);
+        gc.tenure_all_young();
+        survivor.ptr.set_property("restore_a", JSValue::Number(1.0));
+        survivor.ptr.set_property("restore_x", JSValue::Number(2.0));
+        survivor.ptr.rename_property("restore_x", "restore_c");
+        assert!(!same_shape(&survivor.ptr, &reference));
+        gc.collect();
+        assert!(same_shape(&survivor.ptr, &reference));
+    }
+    
+    #[test]
+    fn test_prototype_chain_lookup() {
+        let gc = GarbageCollector::new();
+        let grandparent = gc.create_object(JSObjectType::Object);
+        let parent = gc.create_object(JSObjectType::Object);
+        let child = gc.create_object(JSObjectType::Object);
+        grandparent.ptr.set_property("greeting", JSValue::from("hello"));
+        grandparent.ptr.set_property("name", JSValue::from("grandparent"));
+        parent.ptr.set_property("name", JSValue::from("parent"));
+        parent.ptr.set_prototype(Some(grandparent.clone()));
+        child.ptr.set_prototype(Some(parent.clone()));
+        
+        // Inherited two levels up, and shadowed by the nearest own property
+        assert_eq!(child.ptr.get_property_with_proto("greeting").to_js_string(), "hello");
+        assert_eq!(child.ptr.get_property_with_proto("name").to_js_string(), "parent");
+        child.ptr.set_property("name", JSValue::from("child"));
+        assert_eq!(child.ptr.get_property_with_proto("name").to_js_string(), "child");
+        assert!(matches!(child.ptr.get_property_with_proto("missing"), JSValue::Undefined));
+        
+        // Prototypes are traced, so the chain outlives its other handles
+        gc.add_root(Arc::as_ptr(&child.ptr) as *mut JSObject);
+        let grandparent_weak = grandparent.downgrade();
+        drop((grandparent, parent));
+        gc.collect();
+        let grandparent = grandparent_weak.upgrade().unwrap();
+        assert!(gc.generation_of(&grandparent).is_some());
+        
+        // A cyclic chain ends the walk instead of looping forever
+        grandparent.ptr.set_prototype(Some(child.clone()));
+        assert!(matches!(child.ptr.get_property_with_proto("missing"), JSValue::Undefined));
+        assert!(!child.ptr.has_property("missing"));
+        assert_eq!(child.ptr.get_property_with_proto("greeting").to_js_string(), "hello");
+        grandparent.ptr.set_prototype(None);
+    }
+    
+    #[test]
+    fn test_concurrent_prototype_links_never_form_a_cycle() {
+        for _ in 0..200 {
+            let a = JSObjectHandle { ptr: JSObject::new(JSObjectType::Object) };
+            let b = JSObjectHandle { ptr: JSObject::new(JSObjectType::Object) };
+            let barrier = Arc::new(std::sync::Barrier::new(2));
+            
+            // Each link is fine alone, but together they would close a loop
+            let link = |from: &JSObjectHandle, to: &JSObjectHandle| {
+                let (from, to, barrier) = (from.clone(), to.clone(), barrier.clone());
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    from.ptr.try_set_prototype(Some(to))
+                })
+            };
+            let first = link(&a, &b);
+            let second = link(&b, &a);
+            let linked = [first.join().unwrap(), second.join().unwrap()];
+            assert_eq!(linked.iter().filter(|&&ok| ok).count(), 1);
+            
+            a.ptr.set_prototype(None);
+            b.ptr.set_prototype(None);
+        }
+    }
+    
+    #[test]
+    fn test_weak_map_pruned_by_major_collection() {
+        use crate::gc::GCConfiguration;
+        
+        let gc = GarbageCollector::new();
+        gc.configure(GCConfiguration { old_gen_threshold_kb: 0, ..GCConfiguration::default() });
+        let map = gc.create_weak_map();
+        let kept = gc.create_object(JSObjectType::Object);
+        gc.add_root(Arc::as_ptr(&kept.ptr) as *mut JSObject);
+        map.set(&kept, JSValue::Number(0.0));
+        
+        let dropped: Vec<_> = (0..3).map(|_| gc.create_object(JSObjectType::Object)).collect();
+        for (i, key) in dropped.iter().enumerate() {
+            map.set(key, JSValue::Number(i as f64 + 1.0));
+        }
+        assert_eq!(map.len(), 4);
+        assert!(map.has(&dropped[1]));
+        drop(dropped);
+        
+        gc.collect();
+        assert_eq!(map.len(), 1);
+        assert_eq!(gc.statistics().weak_entries_pruned, 3);
+        assert_eq!(map.get(&kept).map(|value| value.to_number()), Some(0.0));
+        assert!(map.delete(&kept));
+        assert!(map.is_empty());
+    }
+    
+    #[test]
+    fn test_integer_values() {
+        let big = (1i64 << 53) + 1;
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_property("count", JSValue::from(big));
+        assert!(matches!(obj.get_property("count"), JSValue::Integer(i) if i == big));
+        assert_eq!(obj.get_property("count").to_js_string(), "9007199254740993");
+        assert_eq!(format!("{:?}", JSValue::from(big)), "9007199254740993");
+        assert_eq!(format!("{:?}", JSValue::Integer(-42)), "-42");
+        assert_eq!(JSValue::Integer(i64::MIN).to_js_string(), i64::MIN.to_string());
+        assert_eq!(JSValue::Integer((1 << 32) + 5).to_uint32(), 5);
+        
+        // Integers and doubles are the same JavaScript type
+        assert_eq!(JSValue::Integer(7).tag(), JSValueTag::Number);
+        assert!(JSValue::Integer(7).strict_equals(&JSValue::Number(7.0)));
+        assert!(JSValue::Number(7.0).strict_equals(&JSValue::Integer(7)));
+        assert!(!JSValue::Integer(7).strict_equals(&JSValue::Number(7.5)));
+        assert!(!JSValue::Integer(big).strict_equals(&JSValue::Number(big as f64)));
+        assert!(!JSValue::Integer(i64::MAX).strict_equals(&JSValue::Number(i64::MAX as f64)));
+        assert!(JSValue::Integer(0).strict_equals(&JSValue::Number(-0.0)));
+        assert!(!JSValue::Integer(0).same_representation(&JSValue::Number(-0.0)));
+        assert!(JSValue::Integer(0).same_representation(&JSValue::Number(0.0)));
+        
+        // Number getters accept either variant
+        obj.set_property("small", JSValue::Integer(12));
+        assert_eq!(obj.get_number_property("small"), Some(12.0));
+        let key = std::ffi::CString::new("small").unwrap();
+        let mut out = 0.0;
+        let found = js_get_property_number(Arc::as_ptr(&obj) as RustObjectHandle, key.as_ptr(), &mut out);
+        assert_eq!((found, out), (1, 12.0));
+    }
+    
+    #[test]
+    fn test_own_keys_spec_order() {
+        let obj = JSObject::new(JSObjectType::Object);
+        for key in ["z", "2", "1", "x", "10"] {
+            obj.set_property(key, JSValue::from(key));
+        }
+        let keys: Vec<String> = obj.own_keys_spec_order().iter().map(|key| key.to_string()).collect();
+        assert_eq!(keys, ["1", "2", "10", "z", "x"]);
+        
+        // Enumeration follows the same order, and deletes keep it stable
+        let entry_keys: Vec<String> = obj.entries().iter().map(|(key, _)| key.to_string()).collect();
+        assert_eq!(entry_keys, keys);
+        assert!(obj.delete_property("2"));
+        obj.set_property("2", JSValue::Null);
+        obj.set_property("a", JSValue::Null);
+        let keys: Vec<String> = obj.own_keys_spec_order().iter().map(|key| key.to_string()).collect();
+        assert_eq!(keys, ["1", "2", "10", "z", "x", "a"]);
+        
+        // Array elements lead, ahead of named keys
+        let array = JSObject::new(JSObjectType::Array);
+        array.set_property("name", JSValue::Null);
+        array.set_property("1", JSValue::Null);
+        array.set_property("0", JSValue::Null);
+        let keys: Vec<String> = array.own_keys_spec_order().iter().map(|key| key.to_string()).collect();
+        assert_eq!(keys, ["0", "1", "name"]);
+        assert_eq!(array.own_keys_spec_order()[0], PropertyKey::Index(0));
+    }
+    
+    #[test]
+    fn test_property_names_insertion_order() {
+        for _ in 0..5 {
+            let obj = JSObject::new(JSObjectType::Object);
+            obj.set_property("z", JSValue::Number(1.0));
+            obj.set_property("a", JSValue::Number(2.0));
+            obj.set_property("m", JSValue::Number(3.0));
+            let names: Vec<String> = obj.property_names().iter().map(|name| name.as_str().to_string()).collect();
+            assert_eq!(names, ["z", "a", "m"]);
+            
+            // A renamed property keeps its position
+            obj.rename_property("a", "b");
+            let names: Vec<String> = obj.property_names().iter().map(|name| name.as_str().to_string()).collect();
+            assert_eq!(names, ["z", "b", "m"]);
+        }
+    }
+    
+    #[test]
+    fn test_set_object_type() {
+        let gc = GarbageCollector::new();
+        let obj = gc.create_object(JSObjectType::Object);
+        obj.ptr.set_property("name", JSValue::from("f"));
+        let handle = Arc::as_ptr(&obj.ptr) as RustObjectHandle;
+        assert_eq!(js_get_object_type(handle), 0);
+        
+        assert_eq!(js_set_object_type(handle, 2), 1);
+        assert_eq!(js_get_object_type(handle), 2);
+        assert_eq!(obj.ptr.get_property("name").to_js_string(), "f");
+        assert_eq!(js_set_object_type(handle, 42), 0);
+        assert_eq!(js_get_object_type(handle), 2);
+        
+        // Arrays must be emptied before becoming something else
+        let array = JSObject::new(JSObjectType::Array);
+        array.array_push(JSValue::Number(1.0));
+        assert!(!array.set_object_type(JSObjectType::Object));
+        array.array_pop();
+        assert!(array.set_object_type(JSObjectType::Object));
+        
+        // Integer-named properties would be shadowed by elements
+        let indexed = JSObject::new(JSObjectType::Object);
+        indexed.set_property("0", JSValue::Null);
+        assert!(!indexed.set_object_type(JSObjectType::Array));
+        assert!(indexed.delete_property("0"));
+        assert!(indexed.set_object_type(JSObjectType::Array));
+        indexed.set_property("0", JSValue::Number(5.0));
+        assert_eq!(indexed.array_length(), 1);
+    }
+    
+    #[test]
+    fn test_property_enumeration_ffi() {
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_property("zeta", JSValue::Number(1.0));
+        obj.set_property("alpha", JSValue::Number(2.0));
+        obj.set_property("7", JSValue::Number(3.0));
+        let handle = Arc::as_ptr(&obj) as RustObjectHandle;
+        
+        let count = js_get_property_count(handle);
+        assert_eq!(count, 3);
+        let mut buffer = [0 as std::ffi::c_char; 16];
+        let names: Vec<String> = (0..count).map(|i| {
+            assert_eq!(js_get_property_name_at(handle, i, buffer.as_mut_ptr(), buffer.len()), 1);
+            unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) }.to_str().unwrap().to_string()
+        }).collect();
+        assert_eq!(names, ["7", "zeta", "alpha"]);
+        assert_eq!(js_get_property_name_at(handle, count, buffer.as_mut_ptr(), buffer.len()), 0);
+        
+        // Names are truncated to fit, still null-terminated
+        let mut small = [0 as std::ffi::c_char; 3];
+        assert_eq!(js_get_property_name_at(handle, 2, small.as_mut_ptr(), small.len()), 1);
+        assert_eq!(unsafe { std::ffi::CStr::from_ptr(small.as_ptr()) }.to_str().unwrap(), "al");
+        
+        // Array elements are listed by index ahead of named properties
+        let array = JSObject::new(JSObjectType::Array);
+        array.set_property("name", JSValue::Null);
+        array.array_push(JSValue::Number(1.0));
+        array.array_push(JSValue::Number(2.0));
+        let array_handle = Arc::as_ptr(&array) as RustObjectHandle;
+        let names: Vec<String> = (0..js_get_property_count(array_handle)).map(|i| {
+            assert_eq!(js_get_property_name_at(array_handle, i, buffer.as_mut_ptr(), buffer.len()), 1);
+            unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) }.to_str().unwrap().to_string()
+        }).collect();
+        assert_eq!(names, ["0", "1", "name"]);
+        assert_eq!(array.own_key_at(1), Some(PropertyKey::Index(1)));
+        assert_eq!(js_get_property_name_at(std::ptr::null_mut(), 0, buffer.as_mut_ptr(), buffer.len()), 0);
+    }
+    
+    #[test]
+    fn test_log_levels() {
+        use crate::gc::GCConfiguration;
+        use parking_lot::Mutex;
+        
+        // The same workload at each level: some garbage, some promotions
+        let run = |configure: &dyn Fn(&GarbageCollector)| {
+            let gc = GarbageCollector::new();
+            let messages = Arc::new(Mutex::new(Vec::new()));
+            let sink = messages.clone();
+            gc.set_log_callback(Arc::new(move |message: &str| sink.lock().push(message.to_string())));
+            gc.configure(GCConfiguration { incremental: false, promotion_age: 1, ..GCConfiguration::default() });
+            configure(&gc);
+            
+            let kept = gc.create_object(JSObjectType::Object);
+            gc.add_root(Arc::as_ptr(&kept.ptr) as *mut JSObject);
+            for _ in 0..3 {
+                gc.create_object(JSObjectType::Object);
+            }
+            gc.collect();
+            let count = messages.lock().len();
+            count
+        };
+        
+        let off = run(&|_| {});
+        let summary = run(&|gc| gc.set_log_level(GCConfiguration::LOG_SUMMARY));
+        let detailed = run(&|gc| gc.set_log_level(GCConfiguration::LOG_DETAILED));
+        let trace = run(&|gc| gc.set_log_level(GCConfiguration::LOG_TRACE));
+        assert_eq!(off, 0);
+        assert!(0 < summary && summary < detailed && detailed < trace, "{} {} {}", summary, detailed, trace);
+        
+        // verbose is the summary level
+        let verbose = run(&|gc| {
+            gc.configure(GCConfiguration { verbose: true, ..gc.config() });
+        });
+        assert_eq!(verbose, summary);
+    }
+    
+    #[test]
+    fn test_get_property_string_truncation() {
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_property("text", JSValue::from("añ€"));
+        let handle = Arc::as_ptr(&obj) as RustObjectHandle;
+        let key = std::ffi::CString::new("text").unwrap();
+        let read = |buffer: &[libc::c_char]| {
+            unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) }.to_str().unwrap().to_string()
+        };
+        
+        // "añ€" is 1 + 2 + 3 bytes, so a terminator makes 7
+        let mut buffer = [0 as libc::c_char; 7];
+        assert_eq!(js_get_property_string(handle, key.as_ptr(), buffer.as_mut_ptr(), buffer.len()), 7);
+        assert_eq!(read(&buffer), "añ€");
+        
+        // Room for 5 bytes would split the euro sign, so it's dropped whole
+        let mut small = [0 as libc::c_char; 6];
+        assert_eq!(js_get_property_string(handle, key.as_ptr(), small.as_mut_ptr(), small.len()), -7);
+        assert_eq!(read(&small), "añ");
+        let mut tiny = [0 as libc::c_char; 3];
+        assert_eq!(js_get_property_string(handle, key.as_ptr(), tiny.as_mut_ptr(), tiny.len()), -7);
+        assert_eq!(read(&tiny), "a");
+    }
+    
+    #[test]
+    fn test_shape_defaults() {
+        let shape = PropertyShape::with_defaults(&[
+            ("width", JSValue::Number(100.0)),
+            ("height", JSValue::Number(50.0)),
+            ("label", JSValue::from("box")),
+        ]).unwrap();
+        let first = JSObject::new(JSObjectType::Object);
+        let second = JSObject::new(JSObjectType::Object);
+        assert!(first.adopt_shape(shape.clone()));
+        assert!(second.adopt_shape(shape.clone()));
+        
+        // Both read the defaults without storing them
+        assert_eq!(first.get_number_property("width"), Some(100.0));
+        assert_eq!(second.get_property("label").to_js_string(), "box");
+        assert!(first.inner.read().values.is_empty());
+        assert_eq!(first.shallow_equals_same_shape(&second), Some(true));
+        
+        // Writing a slot overrides it for that instance only
+        first.set_property("height", JSValue::Number(75.0));
+        assert_eq!(first.get_number_property("height"), Some(75.0));
+        assert_eq!(second.get_number_property("height"), Some(50.0));
+        assert_eq!(first.get_number_property("width"), Some(100.0));
+        second.set_property("label", JSValue::Undefined);
+        assert!(matches!(second.get_property("label"), JSValue::Undefined));
+        assert!(second.has_own_property("label"));
+        
+        // New properties and deletes keep the remaining values
+        first.set_property("depth", JSValue::Number(3.0));
+        assert_eq!(first.get_number_property("width"), Some(100.0));
+        assert!(first.delete_property("label"));
+        assert!(!first.inner.read().shape.has_defaults());
+        assert_eq!(first.get_number_property("width"), Some(100.0));
+        assert_eq!(first.get_number_property("height"), Some(75.0));
+        assert_eq!(first.get_number_property("depth"), Some(3.0));
+        
+        // Defaults can't hold objects, and only empty objects adopt a shape
+        let child = JSObjectHandle { ptr: JSObject::new(JSObjectType::Object) };
+        assert!(PropertyShape::with_defaults(&[("child", JSValue::Object(child))]).is_none());
+        assert!(PropertyShape::with_defaults(&[("x", JSValue::Null), ("x", JSValue::Null)]).is_none());
+        assert!(!first.adopt_shape(shape));
+    }
+    
+    #[test]
+    fn test_same_value_zero() {
+        let nan = JSValue::Number(f64::NAN);
+        assert!(nan.same_value_zero(&JSValue::Number(-f64::NAN)));
+        assert!(!nan.strict_equals(&nan));
+        assert!(!nan.same_value_zero(&JSValue::Undefined));
+        assert!(JSValue::Number(0.0).same_value_zero(&JSValue::Number(-0.0)));
+        assert!(JSValue::Integer(0).same_value_zero(&JSValue::Number(-0.0)));
+        assert!(JSValue::Number(2.0).same_value_zero(&JSValue::Integer(2)));
+        assert!(!JSValue::Number(2.0).same_value_zero(&JSValue::from("2")));
+        
+        // Strings compare by content, interned or not
+        let owned = JSValue::from(String::from("same text"));
+        assert!(owned.same_value_zero(&JSValue::from("same text")));
+        assert!(!owned.same_value_zero(&JSValue::from("other text")));
+        
+        // Objects compare by identity
+        let obj = JSObject::new(JSObjectType::Object);
+        let twin = JSObject::new(JSObjectType::Object);
+        let value = JSValue::Object(JSObjectHandle { ptr: obj.clone() });
+        assert!(value.same_value_zero(&JSValue::Object(JSObjectHandle { ptr: obj.clone() })));
+        assert!(!value.same_value_zero(&JSValue::Object(JSObjectHandle { ptr: twin.clone() })));
+        let (a, b) = (Arc::as_ptr(&obj) as RustObjectHandle, Arc::as_ptr(&twin) as RustObjectHandle);
+        assert_eq!(js_value_equals(a, a), 1);
+        assert_eq!(js_value_equals(a, b), 0);
+        assert_eq!(js_value_equals(std::ptr::null_mut(), std::ptr::null_mut()), 0);
+    }
+    
+    #[test]
+    fn test_packed_double_elements() {
+        let numbers = JSObject::new(JSObjectType::Array);
+        let boxed = JSObject::new(JSObjectType::Array);
+        for i in 0..100 {
+            numbers.set_element(i, JSValue::Number(i as f64 * 0.5));
+            boxed.set_element(i, JSValue::Number(i as f64 * 0.5));
+        }
+        boxed.set_element(100, JSValue::Null);
+        boxed.array_pop();
+        
+        // Same contents, but the numeric array stores bare doubles
+        assert!(numbers.inner.read().elements.is_packed());
+        assert!(!boxed.inner.read().elements.is_packed());
+        assert_eq!(numbers.inner.read().elements.size_bytes(), 100 * std::mem::size_of::<f64>());
+        assert!(numbers.inner.read().elements.size_bytes() < boxed.inner.read().elements.size_bytes());
+        assert_eq!(numbers.get_element(7).to_number(), 3.5);
+        assert!(numbers.shallow_equals_same_shape(&boxed).unwrap());
+        numbers.array_unshift(JSValue::Number(-1.0));
+        assert_eq!(numbers.array_shift().to_number(), -1.0);
+        assert!(numbers.inner.read().elements.is_packed());
+        
+        // A string switches to boxed storage and keeps every element
+        numbers.set_property("3", JSValue::from("three"));
+        assert!(!numbers.inner.read().elements.is_packed());
+        assert_eq!(numbers.get_element(3).to_js_string(), "three");
+        assert_eq!(numbers.get_element(99).to_number(), 49.5);
+        assert_eq!(numbers.array_length(), 100);
+        
+        // A gap would need Undefined, so it switches too
+        let gapped = JSObject::new(JSObjectType::Array);
+        gapped.set_element(2, JSValue::Number(1.0));
+        assert!(!gapped.inner.read().elements.is_packed());
+        assert!(matches!(gapped.get_element(0), JSValue::Undefined));
+        
+        // Sorting repacks when every element is a double
+        let sorted = JSObject::new(JSObjectType::Array);
+        sorted.array_push(JSValue::from("x"));
+        sorted.array_push(JSValue::Number(2.0));
+        sorted.array_push(JSValue::Number(1.0));
+        sorted.array_shift();
+        sorted.array_sort();
+        assert!(sorted.inner.read().elements.is_packed());
+        assert_eq!(sorted.get_element(0).to_number(), 1.0);
+    }
+    
+    #[test]
+    fn test_incremental_collect_honors_pause_budget() {
+        use crate::gc::{CollectProgress, CollectionPhase, GCConfiguration};
+        
+        // A zero budget makes every slice do exactly one object's work
+        let gc = GarbageCollector::new();
+        gc.configure(GCConfiguration {
+            incremental: true,
+            max_pause_ms: 0,
+            young_gen_threshold_kb: 1 << 20,
+            ..GCConfiguration::default()
+        });
+        
+        // A rooted chain of 101 objects to mark, plus 50 garbage objects to sweep
+        let head = gc.create_object(JSObjectType::Object);
+        gc.add_root(Arc::as_ptr(&head.ptr) as *mut JSObject);
+        let mut tail = head.clone();
+        for _ in 0..100 {
+            let next = gc.create_object(JSObjectType::Object);
+            tail.ptr.set_property("next", JSValue::Object(next.clone()));
+            tail = next;
+        }
+        drop(tail);
+        for _ in 0..50 {
+            gc.create_object(JSObjectType::Object);
+        }
+        
+        // collect() starts the cycle with one slice and later calls resume it
+        gc.collect();
+        assert_eq!(gc.collection_phase(), CollectionPhase::Marking);
+        assert_eq!(gc.statistics().collection_count, 0);
+        assert_eq!(gc.statistics().objects_freed, 0);
+        
+        let (mut marking_steps, mut sweeping_steps) = (1, 0);
+        loop {
+            let phase = gc.collection_phase();
+            if phase == CollectionPhase::Sweeping {
+                sweeping_steps += 1;
+            } else {
+                // Marking never resumes once sweeping has started
+                assert_eq!(sweeping_steps, 0);
+                marking_steps += 1;
+            }
+            if gc.gc_step() == CollectProgress::Complete {
+                break;
+            }
+        }
+        
+        // One slice per reachable object, then one per object swept
+        assert_eq!(marking_steps, 101);
+        assert_eq!(sweeping_steps, 151);
+        assert_eq!(gc.collection_phase(), CollectionPhase::Idle);
+        assert_eq!(gc.statistics().collection_count, 1);
+        assert_eq!(gc.statistics().objects_freed, 50);
+        assert!(head.ptr.get_object_property("next").is_some());
+    }
+    
+    #[test]
+    fn test_run_full_maintenance() {
+        let gc = GarbageCollector::new();
+        let map = gc.create_weak_map();
+        
+        // A survivor with spare storage left from growing
+        let kept = gc.create_object(JSObjectType::Array);
+        gc.add_root(Arc::as_ptr(&kept.ptr) as *mut JSObject);
+        for i in 0..5 {
+            kept.ptr.set_property(&format!("field{}", i), JSValue::Number(i as f64));
+            kept.ptr.array_push(JSValue::Number(i as f64));
+        }
+        let spare = |obj: &JSObject| {
+            let inner = obj.inner.read();
+            inner.values.capacity() - inner.values.len()
+        };
+        assert!(spare(&kept.ptr) > 0);
+        
+        // Garbage holding the only reference to a string, and keying a weak map entry
+        let text = format!("maintenance-only-{:p}", &*kept.ptr);
+        let garbage = gc.create_object(JSObjectType::Object);
+        garbage.ptr.set_property("text", JSValue::from(text.clone()));
+        map.set(&garbage, JSValue::Null);
+        drop(garbage);
+        assert!(export_table().contains(&text));
+        
+        let live_before = gc.heap_snapshot_summary().get(JSObjectType::Object).count;
+        let report = gc.run_full_maintenance();
+        assert_eq!(report.objects_freed, 1);
+        assert_eq!(report.weak_entries_pruned, 1);
+        assert!(report.strings_swept >= 1);
+        assert!(report.bytes_compacted > 0);
+        
+        assert_eq!(gc.heap_snapshot_summary().get(JSObjectType::Object).count, live_before - 1);
+        assert!(map.is_empty());
+        assert!(!export_table().contains(&text));
+        assert_eq!(spare(&kept.ptr), 0);
+        assert_eq!(kept.ptr.get_number_property("field4"), Some(4.0));
+        assert_eq!(kept.ptr.get_element(4).to_number(), 4.0);
+    }
+
+    #[test]
+    fn test_dictionary_mode_threshold() {
+        let obj = JSObject::new(JSObjectType::Object);
+        let threshold = dictionary_mode_threshold();
+        for i in 0..threshold {
+            obj.set_property(&format!("dict_key_{}", i), JSValue::Number(i as f64));
+        }
+        assert!(!obj.is_dictionary_mode());
+        let shape_id = obj.shape_id();
+        
+        obj.set_property("dict_key_last", JSValue::from("last"));
+        assert!(obj.is_dictionary_mode());
+        assert_ne!(obj.shape_id(), shape_id);
+        assert_eq!(obj.property_count(), threshold + 1);
+        
+        // Everything set before the switch is still there, in insertion order
+        for i in 0..threshold {
+            assert_eq!(obj.get_property(&format!("dict_key_{}", i)).to_number(), i as f64);
+        }
+        assert_eq!(obj.get_property("dict_key_last").to_js_string(), "last");
+        let names = obj.property_names();
+        assert_eq!(names.first().unwrap().as_str(), "dict_key_0");
+        assert_eq!(names.last().unwrap().as_str(), "dict_key_last");
+    }
+    
+    #[test]
+    fn test_dictionary_mode_updates() {
+        let gc = GarbageCollector::new();
+        let obj = gc.create_object(JSObjectType::Object);
+        for i in 0..=dictionary_mode_threshold() {
+            obj.ptr.set_property(&format!("update_key_{}", i), JSValue::Undefined);
+        }
+        assert!(obj.ptr.is_dictionary_mode());
+        let shape_id = obj.ptr.shape_id();
+        assert_eq!(obj.ptr.own_key_at(0).unwrap().to_string(), "update_key_0");
+        
+        // Overwrite, add, delete and rename without going back to shapes
+        obj.ptr.set_property("update_key_0", JSValue::Number(1.0));
+        obj.ptr.set_property("2", JSValue::Number(2.0));
+        assert!(obj.ptr.delete_property("update_key_1"));
+        assert!(!obj.ptr.delete_property("update_key_1"));
+        assert!(obj.ptr.rename_property("update_key_2", "renamed"));
+        assert_eq!(obj.ptr.shape_id(), shape_id);
+        
+        assert_eq!(obj.ptr.get_property("update_key_0").to_number(), 1.0);
+        assert!(matches!(obj.ptr.get_property("update_key_1"), JSValue::Undefined));
+        assert!(!obj.ptr.has_own_property("update_key_2"));
+        assert!(obj.ptr.has_own_property("renamed"));
+        let names = obj.ptr.property_names();
+        assert_eq!(names[0].as_str(), "2");
+        assert_eq!(names[2].as_str(), "renamed");
+        // The cached enumeration order follows the changes
+        let keys = obj.ptr.own_keys_spec_order();
+        assert!(keys.iter().enumerate().all(|(i, key)| obj.ptr.own_key_at(i).as_ref() == Some(key)));
+        assert!(obj.ptr.own_key_at(keys.len()).is_none());
+        
+        // Objects held only by dictionary properties stay reachable
+        let child = gc.create_object(JSObjectType::Object);
+        obj.ptr.set_property("child", JSValue::Object(child.clone()));
+        gc.add_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+        gc.collect();
+        assert!(gc.generation_of(&child).is_some());
+    }
+
+    #[test]
+    fn test_object_size_excludes_interned_bytes() {
+        let gc = GarbageCollector::new();
+        let text = "shared interned text ".repeat(200);
+        
+        let first = gc.create_object(JSObjectType::Object);
+        first.ptr.set_property("shared_interned_text", JSValue::from(text.as_str()));
+        let one = gc.heap_snapshot_summary().get(JSObjectType::Object);
+        assert_eq!(one.count, 1);
+        assert!((one.bytes as usize) < text.len());
+        
+        // The second object holds a handle to the same interned string
+        let second = gc.create_object(JSObjectType::Object);
+        second.ptr.set_property("shared_interned_text", JSValue::from(text.as_str()));
+        let two = gc.heap_snapshot_summary().get(JSObjectType::Object);
+        assert_eq!(two.count, 2);
+        assert!((two.bytes as usize) < text.len());
+        
+        // The shape is shared too, so adding an object costs less than the first
+        assert!(two.bytes - one.bytes <= one.bytes);
+    }
+
+    #[test]
+    fn test_finalizer_runs_once_in_sweep() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        static FINALIZED: AtomicUsize = AtomicUsize::new(0);
+        extern "C" fn count_finalized(_: *mut JSObject) {
+            FINALIZED.fetch_add(1, Ordering::SeqCst);
+        }
+        
+        let gc = GarbageCollector::new();
+        let dead = gc.create_object(JSObjectType::Object);
+        dead.ptr.set_finalizer(count_finalized);
+        drop(dead);
+        
+        // Still held by the host, so it isn't finalized until the host lets go
+        let held = gc.create_object(JSObjectType::Object);
+        held.ptr.set_finalizer(count_finalized);
+        
+        gc.collect();
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 1);
+        assert!(!held.ptr.is_finalized());
+        
+        drop(held);
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 2);
+        gc.collect();
+        drop(gc);
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_value_type_of() {
+        use std::ffi::{CStr, CString};
+        
+        let function = JSObject::new(JSObjectType::Function);
+        let array = JSObject::new(JSObjectType::Array);
+        assert_eq!(JSValue::Undefined.type_of(), "undefined");
+        assert_eq!(JSValue::Null.type_of(), "object");
+        assert_eq!(JSValue::Boolean(false).type_of(), "boolean");
+        assert_eq!(JSValue::Number(f64::NAN).type_of(), "number");
+        assert_eq!(JSValue::Integer(7).type_of(), "number");
+        assert_eq!(JSValue::from("text").type_of(), "string");
+        assert_eq!(JSValue::Object(JSObjectHandle { ptr: array.clone() }).type_of(), "object");
+        assert_eq!(JSValue::Object(JSObjectHandle { ptr: function.clone() }).type_of(), "function");
+        
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_property("callback", JSValue::Object(JSObjectHandle { ptr: function }));
+        obj.set_property("nothing", JSValue::Null);
+        let handle = Arc::as_ptr(&obj) as *mut JSObject;
+        let type_of = |key: &str| {
+            let key = CString::new(key).unwrap();
+            let name = js_value_type_of(handle, key.as_ptr());
+            unsafe { CStr::from_ptr(name) }.to_str().unwrap()
+        };
+        assert_eq!(type_of("callback"), "function");
+        assert_eq!(type_of("nothing"), "object");
+        assert_eq!(type_of("missing"), "undefined");
+        let key = CString::new("x").unwrap();
+        assert!(js_value_type_of(std::ptr::null_mut(), key.as_ptr()).is_null());
+    }
+
+    #[test]
+    fn test_interner_sweep_evicts_unused() {
+        let interner = StringInterner::new();
+        let kept = interner.intern("sweep_kept");
+        let transient = interner.intern("sweep_transient");
+        let copy = transient.clone();
+        assert_eq!(interner.len(), 2);
+        
+        // Nothing is evicted while a handle is alive
+        drop(transient);
+        assert_eq!(interner.sweep_unused(), 0);
+        drop(copy);
+        assert_eq!(interner.sweep_unused(), 1);
+        assert_eq!(interner.len(), 1);
+        assert_eq!(kept.as_str(), "sweep_kept");
+        
+        // An evicted string is interned afresh and deduplicated again
+        let again = interner.intern("sweep_transient");
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.intern("sweep_transient"), again);
+        
+        // The global interner evicts through FFI too
+        drop(InternedString::new("js_intern_sweep_transient"));
+        js_intern_sweep();
+        assert!(!export_table().iter().any(|s| s == "js_intern_sweep_transient"));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let gc = GarbageCollector::new();
+        let json: serde_json::Value = serde_json::from_str(
+            r#"{"name":"widget","count":3,"ratio":0.5,"enabled":true,"missing":null,
+                "tags":["a",2,false,null,{"deep":"x"}],"nested":{"z":1,"a":[]}}"#,
+        ).unwrap();
+        
+        let obj = gc.from_json(&json).unwrap();
+        gc.add_root(Arc::as_ptr(&obj.ptr) as *mut JSObject);
+        assert_eq!(obj.ptr.get_property("name").to_js_string(), "widget");
+        assert_eq!(obj.ptr.get_property("ratio").to_number(), 0.5);
+        assert!(matches!(obj.ptr.get_property("missing"), JSValue::Null));
+        let tags = obj.ptr.get_object_property("tags").unwrap();
+        assert_eq!(tags.ptr.inner.read().obj_type, JSObjectType::Array);
+        assert_eq!(tags.ptr.array_length(), 5);
+        assert_eq!(gc.heap_snapshot_summary().get(JSObjectType::Object).count, 3);
+        
+        // Survives a collection and converts back with keys in insertion order
+        gc.collect();
+        let back = obj.ptr.to_json().unwrap();
+        assert_eq!(back, json);
+        assert_eq!(serde_json::to_string(&back).unwrap(), serde_json::to_string(&json).unwrap());
+        
+        assert_eq!(gc.from_json(&serde_json::json!(1)).unwrap_err(), JsonError::NotAnObject);
+    }
+    
+    #[test]
+    fn test_json_rejects_cycles() {
+        let a = JSObject::new(JSObjectType::Object);
+        let b = JSObject::new(JSObjectType::Array);
+        let function = JSObject::new(JSObjectType::Function);
+        a.set_property("skipped", JSValue::Undefined);
+        a.set_property("callback", JSValue::Object(JSObjectHandle { ptr: function.clone() }));
+        a.set_property("nan", JSValue::Number(f64::NAN));
+        b.array_push(JSValue::Undefined);
+        
+        // Shared references are fine; undefined and functions follow JSON.stringify
+        a.set_property("first", JSValue::Object(JSObjectHandle { ptr: b.clone() }));
+        a.set_property("second", JSValue::Object(JSObjectHandle { ptr: b.clone() }));
+        assert_eq!(
+            a.to_json().unwrap(),
+            serde_json::json!({"nan": null, "first": [null], "second": [null]}),
+        );
+        
+        b.array_push(JSValue::Object(JSObjectHandle { ptr: a.clone() }));
+        assert_eq!(a.to_json().unwrap_err(), JsonError::Cycle);
+        assert_eq!(b.to_json().unwrap_err(), JsonError::Cycle);
+        
+        // Break the cycle so both can be dropped
+        b.array_pop();
+    }
+    
+    #[test]
+    fn test_json_rejects_deep_nesting() {
+        fn nested(depth: usize) -> Arc<JSObject> {
+            let outer = JSObject::new(JSObjectType::Array);
+            let mut current = outer.clone();
+            for _ in 1..depth {
+                let inner = JSObject::new(JSObjectType::Array);
+                current.array_push(JSValue::Object(JSObjectHandle { ptr: inner.clone() }));
+                current = inner;
+            }
+            outer
+        }
+        
+        assert!(nested(MAX_JSON_DEPTH).to_json().is_ok());
+        assert_eq!(nested(MAX_JSON_DEPTH + 1).to_json().unwrap_err(), JsonError::TooDeep);
+    }
+
+    #[test]
+    fn test_object_display() {
+        let obj = JSObject::new(JSObjectType::Object);
+        obj.set_property("name", JSValue::from("John"));
+        obj.set_property("value", JSValue::Number(42.0));
+        assert_eq!(obj.to_string(), r#"{ name: "John", value: 42 }"#);
+        
+        let list = JSObject::new(JSObjectType::Array);
+        list.array_push(JSValue::Number(1.0));
+        list.array_push(JSValue::Null);
+        obj.set_property("list", JSValue::Object(JSObjectHandle { ptr: list }));
+        obj.set_property("two words", JSValue::Object(JSObjectHandle { ptr: JSObject::new(JSObjectType::Object) }));
+        assert_eq!(obj.to_string(), r#"{ name: "John", value: 42, list: [ 1, null ], "two words": {} }"#);
+        
+        // A self-reference terminates
+        obj.set_property("me", JSValue::Object(JSObjectHandle { ptr: obj.clone() }));
+        assert!(obj.to_string().ends_with("me: [Circular] }"));
+        obj.delete_property("me");
+        
+        // Deep graphs are summarized
+        let root = JSObject::new(JSObjectType::Object);
+        let mut current = root.clone();
+        for _ in 0..10 {
+            let next = JSObject::new(JSObjectType::Object);
+            current.set_property("next", JSValue::Object(JSObjectHandle { ptr: next.clone() }));
+            current = next;
+        }
+        current.set_property("end", JSValue::Boolean(true));
+        assert_eq!(root.to_string(), "{ next: { next: { next: { next: [Object] } } } }");
+    }
+
+    #[test]
+    fn test_set_properties_batch() {
+        use std::ffi::CString;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        static SHAPE_CHANGES: AtomicUsize = AtomicUsize::new(0);
+        extern "C" fn count_shape_change(_: *const JSObject, _: usize, _: usize) {
+            SHAPE_CHANGES.fetch_add(1, Ordering::SeqCst);
+        }
+        
+        let obj = JSObject::new(JSObjectType::Object);
+        let child = JSObject::new(JSObjectType::Object);
+        obj.set_shape_change_callback(count_shape_change);
+        let old_shape = obj.shape_id();
+        
+        let names: Vec<CString> = (0..50).map(|i| CString::new(format!("batch_{}", i)).unwrap()).collect();
+        let text = CString::new("hello").unwrap();
+        let mut keys: Vec<*const std::ffi::c_char> = names.iter().map(|name| name.as_ptr()).collect();
+        let mut values: Vec<JSValueFFI> = (0..50)
+            .map(|i| JSValueFFI { tag: JSValueTag::Number as i32, payload: JSValuePayload { number: i as f64 } })
+            .collect();
+        values[1] = JSValueFFI { tag: JSValueTag::String as i32, payload: JSValuePayload { string: text.as_ptr() } };
+        values[2] = JSValueFFI { tag: JSValueTag::Boolean as i32, payload: JSValuePayload { boolean: 1 } };
+        values[3] = JSValueFFI { tag: JSValueTag::Null as i32, payload: JSValuePayload { number: 0.0 } };
+        values[4] = JSValueFFI {
+            tag: JSValueTag::Object as i32,
+            payload: JSValuePayload { object: Arc::as_ptr(&child) as *mut JSObject },
+        };
+        // Skipped: an unsupported tag and a null key
+        values[5] = JSValueFFI { tag: JSValueTag::Symbol as i32, payload: JSValuePayload { number: 0.0 } };
+        keys[6] = std::ptr::null();
+        
+        let handle = Arc::as_ptr(&obj) as *mut JSObject;
+        let mut written = 0;
+        assert_eq!(
+            js_set_properties_batch(handle, keys.as_ptr(), values.as_ptr(), keys.len(), &mut written),
+            JSSetStatus::Ok
+        );
+        assert_eq!(written, 48);
+        
+        // One shape change for the whole batch, since the lock was taken once
+        assert_eq!(SHAPE_CHANGES.load(Ordering::SeqCst), 1);
+        assert_ne!(obj.shape_id(), old_shape);
+        assert_eq!(obj.property_count(), 48);
+        assert_eq!(obj.get_property("batch_0").to_number(), 0.0);
+        assert_eq!(obj.get_property("batch_49").to_number(), 49.0);
+        assert_eq!(obj.get_property("batch_1").to_js_string(), "hello");
+        assert!(matches!(obj.get_property("batch_2"), JSValue::Boolean(true)));
+        assert!(matches!(obj.get_property("batch_3"), JSValue::Null));
+        assert!(matches!(obj.get_property("batch_4"), JSValue::Object(ref o) if Arc::ptr_eq(&o.ptr, &child)));
+        assert!(!obj.has_own_property("batch_5"));
+        assert!(!obj.has_own_property("batch_6"));
+        
+        assert_eq!(
+            js_set_properties_batch(std::ptr::null_mut(), keys.as_ptr(), values.as_ptr(), 1, &mut written),
+            JSSetStatus::InvalidHandle
+        );
+        assert_eq!(written, 0);
+    }
+
+    #[test]
+    fn test_pause_timing_statistics() {
+        use crate::gc::GCConfiguration;
+        
+        let gc = GarbageCollector::new();
+        gc.configure(GCConfiguration {
+            young_gen_threshold_kb: 1 << 20,
+            ..GCConfiguration::default()
+        });
+        assert_eq!(gc.statistics().total_pause_ns, 0);
+        assert_eq!(gc.statistics().max_pause_ns, 0);
+        
+        let mut previous_total = 0;
+        let mut observed = Vec::new();
+        for round in 0..5 {
+            for _ in 0..(round + 1) * 200 {
+                gc.create_object(JSObjectType::Object);
+            }
+            gc.collect();
+            let stats = gc.statistics();
+            assert!(stats.total_pause_ns >= previous_total);
+            assert!(stats.last_collection_ns > 0);
+            previous_total = stats.total_pause_ns;
+            observed.push(stats.last_collection_ns);
+        }
+        
+        let stats = gc.statistics();
+        assert!(stats.max_pause_ns >= *observed.iter().max().unwrap());
+        assert!(stats.max_pause_ns <= stats.total_pause_ns);
+        assert!(stats.total_pause_ns >= observed.iter().sum::<u64>());
+        
+        // The fields cross FFI with the rest of the statistics
+        let gc_handle = Arc::as_ptr(&gc) as *mut GarbageCollector;
+        assert_eq!(js_gc_get_stats(gc_handle).max_pause_ns, stats.max_pause_ns);
+        assert_eq!(js_gc_get_stats(std::ptr::null_mut()).total_pause_ns, 0);
+    }
+
+    #[test]
+    fn test_shape_stats() {
+        let before = get_shape_stats();
+        let first = JSObject::new(JSObjectType::Object);
+        first.set_property("shape_stats_a", JSValue::Number(1.0));
+        first.set_property("shape_stats_b", JSValue::Number(2.0));
+        let after_first = get_shape_stats();
+        assert!(after_first.transition_misses >= before.transition_misses + 2);
+        assert!(after_first.live_shapes > 0);
+        
+        // The same order reuses the cached transitions
+        let same = JSObject::new(JSObjectType::Object);
+        same.set_property("shape_stats_a", JSValue::Number(3.0));
+        same.set_property("shape_stats_b", JSValue::Number(4.0));
+        let after_same = get_shape_stats();
+        assert!(after_same.transition_hits >= after_first.transition_hits + 2);
+        assert_eq!(same.shape_id(), first.shape_id());
+        
+        // A different order builds shapes of its own
+        let reversed = JSObject::new(JSObjectType::Object);
+        reversed.set_property("shape_stats_b", JSValue::Number(5.0));
+        reversed.set_property("shape_stats_a", JSValue::Number(6.0));
+        let after_reversed = get_shape_stats();
+        assert!(after_reversed.transition_misses >= after_same.transition_misses + 2);
+        assert_ne!(reversed.shape_id(), first.shape_id());
+        
+        assert!(js_get_shape_stats().transition_hits >= after_reversed.transition_hits);
+    }
+    
+    #[test]
+    fn test_shallow_clone_shares_shape() {
+        let gc = GarbageCollector::new();
+        let handle = gc.create_object(JSObjectType::Object);
+        let original = &handle.ptr;
+        original.set_property("x", JSValue::Number(1.0));
+        original.set_property("y", JSValue::from("two"));
+        let allocations = gc.statistics().allocation_count;
+        
+        let clone_handle = gc.clone_object(&handle);
+        let clone = &clone_handle.ptr;
+        assert!(gc.statistics().allocation_count > allocations);
+        assert!(gc.object_by_id(clone.id()).is_some());
+        assert!(Arc::ptr_eq(&original.inner.read().shape, &clone.inner.read().shape));
+        assert!(original.inner.read().shape.reference_count() >= 2);
+        assert!(matches!(clone.get_property("y"), JSValue::String(s) if s == "two"));
+        
+        // Writing an existing slot keeps the shape and leaves the other copy alone
+        clone.set_property("x", JSValue::Number(10.0));
+        assert!(matches!(original.get_property("x"), JSValue::Number(n) if n == 1.0));
+        assert_eq!(clone.shape_id(), original.shape_id());
+        
+        original.set_property("z", JSValue::Boolean(true));
+        assert!(matches!(clone.get_property("z"), JSValue::Undefined));
+        assert_ne!(clone.shape_id(), original.shape_id());
+    }
+    
+    #[test]
+    fn test_clone_during_marking_traces_copied_references() {
+        let gc = GarbageCollector::new();
+        let root = gc.create_object(JSObjectType::Object);
+        gc.add_root(Arc::as_ptr(&root.ptr) as *mut JSObject);
+        let source = gc.create_object(JSObjectType::Object);
+        let target = gc.create_object(JSObjectType::Object);
+        root.ptr.set_property("source", JSValue::Object(source.clone()));
+        source.ptr.set_property("target", JSValue::Object(target.clone()));
+        
+        // Clone while the source hasn't been traced yet, then cut its reference
+        assert_eq!(gc.try_collect(0), CollectProgress::InProgress);
+        let clone = gc.clone_object(&source);
+        root.ptr.set_property("clone", JSValue::Object(clone.clone()));
+        source.ptr.set_property("target", JSValue::Null);
+        drop(clone);
+        
+        // The target is only reachable through the clone now
+        while gc.try_collect(u64::MAX) != CollectProgress::Complete {}
+        assert!(gc.generation_of(&target).is_some());
+    }
+    
+    #[test]
+    fn test_freeze_object() {
+        let obj = JSObject::new(JSObjectType::Object);
+        assert!(obj.set_property("x", JSValue::Number(1.0)));
+        assert!(obj.set_property("y", JSValue::Number(2.0)));
+        assert!(obj.delete_property("y"));
+        
+        obj.freeze();
+        assert!(obj.is_frozen());
+        assert!(obj.is_shape_frozen());
+        
+        // Existing keys are as locked as new ones, and nothing is deleted
+        assert!(!obj.set_property("x", JSValue::Number(10.0)));
+        assert!(!obj.set_property("z", JSValue::Number(3.0)));
+        assert_eq!(obj.set_properties([("x", JSValue::Number(11.0))]), 0);
+        assert!(!obj.delete_property("x"));
+        assert!(!obj.try_set_prototype(Some(JSObjectHandle { ptr: JSObject::new(JSObjectType::Object) })));
+        
+        assert_eq!(obj.get_number_property("x"), Some(1.0));
+        assert!(!obj.has_own_property("z"));
+        assert!(obj.get_prototype().is_none());
+        
+        let array = JSObject::new(JSObjectType::Array);
+        assert!(array.set_element(0, JSValue::Number(1.0)));
+        array.array_push(JSValue::Number(2.0));
+        array.freeze();
+        assert!(!array.set_element(0, JSValue::Number(5.0)));
+        assert!(!array.set_property("1", JSValue::Number(6.0)));
+        assert_eq!(array.array_push(JSValue::Number(3.0)), 2);
+        assert!(matches!(array.array_pop(), JSValue::Undefined));
+        assert!(!array.delete_property("0"));
+        assert_eq!(array.array_length(), 2);
+        assert!(matches!(array.get_element(1), JSValue::Number(n) if n == 2.0));
+        
+        let handle = Arc::as_ptr(&array) as *mut JSObject;
+        let key = std::ffi::CString::new("0").unwrap();
+        assert_eq!(js_set_property_number(handle, key.as_ptr(), 7.0), 0);
+        assert!(matches!(array.get_element(0), JSValue::Number(n) if n == 1.0));
+        assert_eq!(js_delete_property(handle, key.as_ptr()), 0);
+        assert_eq!(js_array_push(handle, 7.0), 0);
+        assert_eq!(js_is_frozen(handle), 1);
+        
+        let other = JSObject::new(JSObjectType::Object);
+        let other_handle = Arc::as_ptr(&other) as *mut JSObject;
+        assert_eq!(js_set_property_number(other_handle, key.as_ptr(), 8.0), 1);
+        assert_eq!(js_freeze_object(other_handle), 1);
+        assert_eq!(js_set_property_number(other_handle, key.as_ptr(), 7.0), 0);
+        assert!(other.is_frozen());
+        assert_eq!(other.get_number_property("0"), Some(8.0));
+    }
+    
+    #[test]
+    fn test_freeze_materializes_lazy_properties_added_while_freezing() {
+        #[allow(improper_ctypes_definitions)]
+        extern "C" fn second(_obj: *mut JSObject) -> JSValue {
+            JSValue::Number(2.0)
+        }
+        #[allow(improper_ctypes_definitions)]
+        extern "C" fn first(obj: *mut JSObject) -> JSValue {
+            // Registers another lazy property after freeze took its snapshot
+            let obj = unsafe { &*obj };
+            assert!(obj.set_lazy_property("second", second));
+            JSValue::Number(1.0)
+        }
+        
+        let obj = JSObject::new(JSObjectType::Object);
+        assert!(obj.set_lazy_property("first", first));
+        obj.freeze();
+        
+        // Nothing is left pending on the frozen object, so both values are stored
+        assert!(obj.inner.read().lazy_properties.is_empty());
+        assert_eq!(obj.get_number_property("first"), Some(1.0));
+        assert_eq!(obj.get_number_property("second"), Some(2.0));
+    }
+    
+    #[test]
+    fn test_collect_compacts_generations() {
+        use crate::gc::GCConfiguration;
+        
+        fn young_capacity_after_collect(compact_threshold_ratio: f64) -> usize {
+            let gc = GarbageCollector::new();
+            gc.configure(GCConfiguration {
+                young_gen_threshold_kb: 1 << 20,
+                compact_threshold_ratio,
+                ..GCConfiguration::default()
+            });
+            
+            let handles: Vec<JSObjectHandle> = (0..4000)
+                .map(|_| gc.create_object(JSObjectType::Object))
+                .collect();
+            for handle in handles.iter().take(10) {
+                gc.add_root(Arc::as_ptr(&handle.ptr) as *mut JSObject);
+            }
+            assert!(gc.generation_capacities().0 >= 4000);
+            
+            // Minor collections keep the young generation's allocation
+            gc.collect_young();
+            assert!(gc.generation_capacities().0 >= 4000);
+            
+            gc.collect();
+            gc.generation_capacities().0
+        }
+        
+        assert!(young_capacity_after_collect(4.0) < 4000);
+        assert!(young_capacity_after_collect(0.0) >= 4000);
+    }
+    
+    #[test]
+    fn test_other_collectors_marks_do_not_hide_objects() {
+        let gc = GarbageCollector::new();
+        let other = GarbageCollector::new();
+        
+        let holder = gc.create_object(JSObjectType::Object);
+        let child = gc.create_object(JSObjectType::Object);
+        holder.ptr.set_property("child", JSValue::Object(child.clone()));
+        gc.add_root(Arc::as_ptr(&holder.ptr) as *mut JSObject);
+        
+        // The other collector's trace reaches `holder` and marks it, then pauses
+        let other_root = other.create_object(JSObjectType::Object);
+        other_root.ptr.set_property("foreign", JSValue::Object(holder.clone()));
+        other.add_root(Arc::as_ptr(&other_root.ptr) as *mut JSObject);
+        assert_eq!(other.try_collect(0), CollectProgress::InProgress);
+        assert!(holder.ptr.is_marked());
+        
+        // That mark must not stop this collector from tracing into `holder`
+        gc.collect_young();
+        assert!(gc.generation_of(&child).is_some());
+        
+        while other.try_collect(u64::MAX) == CollectProgress::InProgress {}
+        gc.remove_root(Arc::as_ptr(&holder.ptr) as *mut JSObject);
+        other.remove_root(Arc::as_ptr(&other_root.ptr) as *mut JSObject);
+    }
+    
+    #[test]
+    fn test_huge_array_index_is_a_named_property() {
+        let array = JSObject::new(JSObjectType::Array);
+        let handle = Arc::as_ptr(&array) as *mut JSObject;
+        let key = std::ffi::CString::new("4000000000").unwrap();
+        let value = std::ffi::CString::new("far").unwrap();
+        assert_eq!(js_set_property_string(handle, key.as_ptr(), value.as_ptr()), 1);
+        
+        // Nothing was allocated for the holes
+        assert_eq!(array.array_length(), 0);
+        assert!(array.has_own_property("4000000000"));
+        assert!(matches!(array.get_property("4000000000"), JSValue::String(s) if s == "far"));
+        assert!(matches!(array.get_element(4_000_000_000), JSValue::String(s) if s == "far"));
+        
+        // Small gaps past the end still go to the elements
+        array.set_property("3", JSValue::Number(3.0));
+        assert_eq!(array.array_length(), 4);
+        assert!(array.set_element(1 << 40, JSValue::Boolean(true)));
+        assert_eq!(array.array_length(), 4);
+        assert!(matches!(array.get_element(1 << 40), JSValue::Boolean(true)));
+        
+        assert!(array.delete_property("4000000000"));
+        assert!(!array.has_own_property("4000000000"));
+    }
+    
+    #[test]
+    fn test_ffi_set_on_frozen_object_reports_status() {
+        let obj = JSObject::new(JSObjectType::Object);
+        let handle = Arc::as_ptr(&obj) as *mut JSObject;
+        let key = std::ffi::CString::new("name").unwrap();
+        let before = std::ffi::CString::new("before").unwrap();
+        let after = std::ffi::CString::new("after").unwrap();
+        let number = JSValueFFI { tag: JSValueTag::Number as i32, payload: JSValuePayload { number: 1.0 } };
+        assert_eq!(js_set_property_string(handle, key.as_ptr(), before.as_ptr()), 1);
+        
+        // The typed setters keep returning 0 on refusal; the status entry point says why
+        obj.freeze();
+        assert_eq!(js_set_property_string(handle, key.as_ptr(), after.as_ptr()), 0);
+        assert_eq!(js_set_property_number(handle, key.as_ptr(), 1.0), 0);
+        assert_eq!(js_set_property_boolean(handle, key.as_ptr(), 1), 0);
+        assert_eq!(js_set_property_object(handle, key.as_ptr(), handle), 0);
+        assert_eq!(js_set_property_value(handle, key.as_ptr(), &number), JSSetStatus::Frozen);
+        assert!(matches!(obj.get_property("name"), JSValue::String(s) if s == "before"));
+        
+        let open = JSObject::new(JSObjectType::Object);
+        let open_handle = Arc::as_ptr(&open) as *mut JSObject;
+        assert_eq!(js_set_property_value(open_handle, key.as_ptr(), &number), JSSetStatus::Ok);
+        assert_eq!(open.get_number_property("name"), Some(1.0));
+        open.freeze_shape();
+        let other_key = std::ffi::CString::new("other").unwrap();
+        assert_eq!(js_set_property_value(open_handle, other_key.as_ptr(), &number), JSSetStatus::NotExtensible);
+        
+        assert_eq!(js_set_property_value(std::ptr::null_mut(), key.as_ptr(), &number), JSSetStatus::InvalidHandle);
+        assert_eq!(js_set_property_value(handle, std::ptr::null(), &number), JSSetStatus::InvalidArgument);
+        assert_eq!(js_set_property_value(handle, key.as_ptr(), std::ptr::null()), JSSetStatus::InvalidArgument);
+    }
+    
+    #[test]
+    fn test_equal_large_strings_are_strictly_equal() {
+        use std::collections::HashSet;
+        
+        let interner = StringInterner::new();
+        interner.set_large_string_threshold(1024);
+        let huge = "y".repeat(4096);
+        let first = interner.intern_large(huge.clone());
+        let second = interner.intern_large(huge.clone());
+        assert!(!Arc::ptr_eq(&first.inner, &second.inner));
+        
+        // Separate allocations, but the same JS string
+        assert_eq!(first, second);
+        assert!(JSValue::String(first.clone()).strict_equals(&JSValue::String(second.clone())));
+        let other = interner.intern_large("z".repeat(4096));
+        assert!(!JSValue::String(first.clone()).strict_equals(&JSValue::String(other)));
+        
+        // Hash and Eq agree with lookups by content
+        let set: HashSet<InternedString> = [first, second].into_iter().collect();
+        assert_eq!(set.len(), 1);
+        assert!(set.contains(huge.as_str()));
+    }
+    
+    #[test]
+    fn test_restore_shape_leaves_dictionary_mode() {
+        let gc = GarbageCollector::new();
+        let obj = JSObject::new(JSObjectType::Object);
+        let threshold = dictionary_mode_threshold();
+        for i in 0..=threshold {
+            obj.set_property(&format!("undict_key_{}", i), JSValue::Number(i as f64));
+        }
+        assert!(obj.is_dictionary_mode());
+        assert!(!obj.try_restore_shape(&gc));
+        
+        // Once it drops below the threshold it goes back onto a shared shape
+        assert!(obj.delete_property("undict_key_0"));
+        assert!(obj.delete_property("undict_key_1"));
+        assert!(obj.try_restore_shape(&gc));
+        assert!(!obj.is_dictionary_mode());
+        
+        let reference = JSObject::new(JSObjectType::Object);
+        for i in 2..=threshold {
+            reference.set_property(&format!("undict_key_{}", i), JSValue::Number(i as f64));
+        }
+        assert_eq!(obj.shape_id(), reference.shape_id());
+        for i in 2..=threshold {
+            assert_eq!(obj.get_property(&format!("undict_key_{}", i)).to_number(), i as f64);
+        }
+        assert_eq!(obj.property_names().first().unwrap().as_str(), "undict_key_2");
+    }
+}
@@ -1,249 +1,2603 @@
-use libc::{c_char, c_double, c_int, c_void};
-use parking_lot::RwLock;
-use std::collections::HashMap;
-use std::ffi::{CStr, CString};
-use std::fmt;
-use std::sync::{Arc, Weak};
-use std::sync::atomic::{AtomicBool, Ordering};
-use crate::shape::PropertyShape;
-use crate::string_interner::InternedString;
-
-/// Type of JavaScript object
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum JSObjectType {
-    Object,
-    Array,
-    Function,
-    String,
-    Number,
-    Boolean,
-    Null,
-    Undefined,
-}
-
-/// JavaScript value type
-#[derive(Clone)]
-pub enum JSValue {
-    Undefined,
-    Null,
-    Boolean(bool),
-    Number(f64),
-    // Use InternedString instead of String to deduplicate string values
-    String(InternedString),
-    Object(JSObjectHandle),
-}
-
-impl fmt::Debug for JSValue {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            JSValue::Undefined => write!(f, "undefined"),
-            JSValue::Null => write!(f, "null"),
-            JSValue::Boolean(b) => write!(f, "{}", b),
-            JSValue::Number(n) => write!(f, "{}", n),
-            JSValue::String(s) => write!(f, "\"{}\"", s),
-            JSValue::Object(_) => write!(f, "[object]"),
-        }
-    }
-}
-
-impl Default for JSValue {
-    fn default() -> Self {
-        JSValue::Undefined
-    }
-}
-
-// Helper conversion implementations for JSValue
-impl From<&str> for JSValue {
-    fn from(s: &str) -> Self {
-        JSValue::String(InternedString::new(s))
-    }
-}
-
-impl From<String> for JSValue {
-    fn from(s: String) -> Self {
-        JSValue::String(InternedString::new(&s))
-    }
-}
-
-impl From<f64> for JSValue {
-    fn from(n: f64) -> Self {
-        JSValue::Number(n)
-    }
-}
-
-impl From<bool> for JSValue {
-    fn from(b: bool) -> Self {
-        JSValue::Boolean(b)
-    }
-}
-
-/// Internal structure of a JavaScript object
-pub struct JSObjectInner {
-    pub obj_type: JSObjectType,
-    // Using shape-based optimization
-    pub shape: Arc<PropertyShape>,
-    pub values: Vec<JSValue>,
-    pub marked: bool,
-    pub finalizer: Option<extern "C" fn(*mut JSObject)>,
-}
-
-impl JSObjectInner {
-    /// Create a new JS object inner state
-    pub fn new(obj_type: JSObjectType) -> Self {
-        Self {
-            obj_type,
-            shape: PropertyShape::new_empty(),
-            values: Vec::new(),
-            marked: false,
-            finalizer: None,
-        }
-    }
-}
-
-/// JavaScript object - thread-safe wrapper around properties
-pub struct JSObject {
-    pub inner: RwLock<JSObjectInner>,
-}
-
-impl JSObject {
-    /// Create a new JavaScript object of the specified type
-    pub fn new(obj_type: JSObjectType) -> Arc<Self> {
-        Arc::new(Self {
-            inner: RwLock::new(JSObjectInner::new(obj_type)),
-        })
-    }
-    
-    /// Set a property on this object
-    pub fn set_property(&self, key: &str, value: JSValue) {
-        let mut inner = self.inner.write();
-        
-        // Check if property already exists in the current shape
-        if let Some(index) = inner.shape.get_property_index(key) {
-            // Property exists, just update the value
-            if index < inner.values.len() {
-                inner.values[index] = value;
-            } else {
-                // This shouldn't happen if the shape is consistent, but handle it anyway
-                inner.values.resize_with(index + 1, || JSValue::Undefined);
-                inner.values[index] = value;
-            }
-        } else {
-            // Property doesn't exist, transition to a new shape
-            let old_shape = inner.shape.clone();
-            let new_shape = old_shape.transition_to(key);
-            
-            // Update reference counts
-            old_shape.remove_reference();
-            new_shape.add_reference();
-            
-            // Get the index for the new property
-            let index = new_shape.get_property_index(key).unwrap();
-            
-            // Ensure values vector has enough capacity
-            if index >= inner.values.len() {
-                inner.values.resize_with(index + 1, || JSValue::Undefined);
-            }
-            
-            // Set the value and update the shape
-            inner.values[index] = value;
-            inner.shape = new_shape;
-        }
-    }
-    
-    /// Get a property from this object
-    pub fn get_property(&self, key: &str) -> JSValue {
-        let inner = self.inner.read();
-        
-        // Check if property exists in the current shape
-        if let Some(index) = inner.shape.get_property_index(key) {
-            if index < inner.values.len() {
-                // Return the value if it exists
-                inner.values[index].clone()
-            } else {
-                // Index out of bounds (shouldn't happen with well-formed shapes)
-                JSValue::Undefined
-            }
-        } else {
-            // Property not found
-            JSValue::Undefined
-        }
-    }
-    
-    /// Mark object for garbage collection
-    pub fn mark(&self) {
-        let mut inner = self.inner.write();
-        inner.marked = true;
-        
-        // Mark any object properties recursively
-        for value in inner.values.iter() {
-            if let JSValue::Object(obj) = value {
-                obj.ptr.mark();
-            }
-        }
-    }
-    
-    /// Unmark object after garbage collection
-    pub fn unmark(&self) {
-        let mut inner = self.inner.write();
-        inner.marked = false;
-    }
-    
-    /// Check if object is marked
-    pub fn is_marked(&self) -> bool {
-        let inner = self.inner.read();
-        inner.marked
-    }
-    
-    /// Set a finalizer to be called when object is collected
-    pub fn set_finalizer(&self, finalizer: extern "C" fn(*mut JSObject)) {
-        let mut inner = self.inner.write();
-        inner.finalizer = Some(finalizer);
-    }
-    
-    /// Get all property names in this object
-    pub fn property_names(&self) -> Vec<String> {
-        let inner = self.inner.read();
-        inner.shape.property_names()
-    }
-}
-
-impl Drop for JSObject {
-    fn drop(&mut self) {
-        // Call the finalizer if set
-        if let Some(finalizer) = self.inner.read().finalizer {
-            // Safety: We're passing a raw pointer to the finalizer
-            finalizer(self as *mut JSObject);
-        }
-    }
-}
-
-/// Safe handle to a JavaScript object
-#[derive(Clone)]
-pub struct JSObjectHandle {
-    pub ptr: Arc<JSObject>,
-}
-
-impl JSObjectHandle {
-    /// Create a handle from a raw pointer
-    pub fn from_raw(raw: *mut JSObject) -> Option<Self> {
-        if raw.is_null() {
-            None
-        } else {
-            // Safety: Convert raw pointer back to Arc
-            unsafe {
-                let arc = Arc::from_raw(raw);
-                let ptr = arc.clone();
-                // Don't drop the original Arc when this function returns
-                std::mem::forget(arc);
-                Some(Self { ptr })
-            }
-        }
-    }
-}
-
-impl fmt::Debug for JSObjectHandle {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let inner = self.ptr.inner.read();
-        write!(f, "JSObject({:?})", inner.obj_type)
-    }
+use parking_lot::{RwLock, RwLockWriteGuard};
+use once_cell::sync::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use crate::gc::{GCConfiguration, GarbageCollector};
+use crate::shape::PropertyShape;
+use crate::string_interner::InternedString;
+
+/// Type of JavaScript object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JSObjectType {
+    Object,
+    Array,
+    Function,
+    String,
+    Number,
+    Boolean,
+    Null,
+    Undefined,
+}
+
+/// JavaScript value type
+#[derive(Clone, Default)]
+pub enum JSValue {
+    #[default]
+    Undefined,
+    Null,
+    Boolean(bool),
+    Number(f64),
+    /// A number known to be an integer, kept exact beyond 2^53
+    ///
+    /// This is the same JavaScript type as `Number` and compares equal to it.
+    Integer(i64),
+    // Use InternedString instead of String to deduplicate string values
+    String(InternedString),
+    Object(JSObjectHandle),
+}
+
+/// Stable type tag of a JavaScript value, shared with C++ over FFI
+///
+/// The discriminants are part of the FFI contract and must not change.
+/// `BigInt` and `Symbol` are reserved for value kinds not stored yet.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JSValueTag {
+    Undefined = 0,
+    Null = 1,
+    Boolean = 2,
+    Number = 3,
+    String = 4,
+    Object = 5,
+    BigInt = 6,
+    Symbol = 7,
+}
+
+impl JSValueTag {
+    /// Get the lowercase name of this tag, e.g. `"number"`
+    pub fn name(self) -> &'static str {
+        match self {
+            JSValueTag::Undefined => "undefined",
+            JSValueTag::Null => "null",
+            JSValueTag::Boolean => "boolean",
+            JSValueTag::Number => "number",
+            JSValueTag::String => "string",
+            JSValueTag::Object => "object",
+            JSValueTag::BigInt => "bigint",
+            JSValueTag::Symbol => "symbol",
+        }
+    }
+}
+
+/// An own property key as enumeration reports it
+///
+/// Array indices stay numbers, so enumerating a large array doesn't intern a
+/// string per element. `Display` gives the key's text either way.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PropertyKey {
+    Index(usize),
+    Name(InternedString),
+}
+
+impl fmt::Display for PropertyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyKey::Index(index) => fmt::Display::fmt(index, f),
+            PropertyKey::Name(name) => fmt::Display::fmt(name, f),
+        }
+    }
+}
+
+impl JSValue {
+    /// Convert this value to a number following the spec's ToNumber
+    ///
+    /// Objects convert to NaN since there is no ToPrimitive hook here.
+    pub fn to_number(&self) -> f64 {
+        match self {
+            JSValue::Undefined => f64::NAN,
+            JSValue::Null => 0.0,
+            JSValue::Boolean(b) => if *b { 1.0 } else { 0.0 },
+            JSValue::Number(n) => *n,
+            JSValue::Integer(i) => *i as f64,
+            JSValue::String(s) => string_to_number(s),
+            JSValue::Object(_) => f64::NAN,
+        }
+    }
+    
+    /// Convert this value to a signed 32-bit integer following the spec's ToInt32
+    ///
+    /// NaN and infinities become 0; everything else is truncated and wrapped
+    /// modulo 2^32, as the bitwise operators require.
+    pub fn to_int32(&self) -> i32 {
+        self.to_uint32() as i32
+    }
+    
+    /// Convert this value to an unsigned 32-bit integer following the spec's ToUint32
+    pub fn to_uint32(&self) -> u32 {
+        if let JSValue::Integer(i) = self {
+            // Truncating wraps modulo 2^32 without going through f64
+            return *i as u32;
+        }
+        let n = self.to_number();
+        if !n.is_finite() {
+            return 0;
+        }
+        n.trunc().rem_euclid(4294967296.0) as u32
+    }
+    
+    /// Convert this value to a string following the spec's ToString
+    ///
+    /// Arrays join their elements with commas like `Array.prototype.toString`,
+    /// with nested references back to an enclosing array rendering as empty.
+    /// Other objects become `[object Object]` since there is no ToPrimitive hook.
+    pub fn to_js_string(&self) -> String {
+        let mut out = String::new();
+        self.write_js_string(&mut out, &mut Vec::new());
+        out
+    }
+    
+    fn write_js_string(&self, out: &mut String, ancestors: &mut Vec<*const JSObject>) {
+        match self {
+            JSValue::Undefined => out.push_str("undefined"),
+            JSValue::Null => out.push_str("null"),
+            JSValue::Boolean(b) => out.push_str(if *b { "true" } else { "false" }),
+            JSValue::Number(n) => out.push_str(&number_to_string(*n)),
+            JSValue::Integer(i) => out.push_str(&i.to_string()),
+            JSValue::String(s) => out.push_str(s.as_str()),
+            JSValue::Object(handle) => {
+                let ptr = Arc::as_ptr(&handle.ptr);
+                let inner = handle.ptr.inner.read();
+                match inner.obj_type {
+                    JSObjectType::Array if !ancestors.contains(&ptr) => {
+                        // Snapshot the elements so no lock is held while recursing
+                        let elements = inner.elements.to_vec();
+                        drop(inner);
+                        
+                        ancestors.push(ptr);
+                        for (i, element) in elements.iter().enumerate() {
+                            if i > 0 {
+                                out.push(',');
+                            }
+                            // join() renders undefined and null as empty
+                            if !matches!(element, JSValue::Undefined | JSValue::Null) {
+                                element.write_js_string(out, ancestors);
+                            }
+                        }
+                        ancestors.pop();
+                    }
+                    JSObjectType::Array => {}
+                    JSObjectType::Function => out.push_str("function () { [native code] }"),
+                    _ => out.push_str("[object Object]"),
+                }
+            }
+        }
+    }
+    
+    /// Compare two values the way `Array.prototype.sort` does without a comparator
+    ///
+    /// Values are ordered by the UTF-16 code units of their string form, so `10`
+    /// sorts before `2`. `undefined` sorts after everything else.
+    pub fn default_sort_cmp(&self, other: &JSValue) -> std::cmp::Ordering {
+        use std::cmp::Ordering;
+        match (self, other) {
+            (JSValue::Undefined, JSValue::Undefined) => Ordering::Equal,
+            (JSValue::Undefined, _) => Ordering::Greater,
+            (_, JSValue::Undefined) => Ordering::Less,
+            _ => {
+                let (a, b) = (self.to_js_string(), other.to_js_string());
+                a.encode_utf16().cmp(b.encode_utf16())
+            }
+        }
+    }
+    
+    /// Get the type tag of this value
+    pub fn tag(&self) -> JSValueTag {
+        match self {
+            JSValue::Undefined => JSValueTag::Undefined,
+            JSValue::Null => JSValueTag::Null,
+            JSValue::Boolean(_) => JSValueTag::Boolean,
+            JSValue::Number(_) | JSValue::Integer(_) => JSValueTag::Number,
+            JSValue::String(_) => JSValueTag::String,
+            JSValue::Object(_) => JSValueTag::Object,
+        }
+    }
+    
+    /// Get the result of JavaScript's `typeof` for this value
+    ///
+    /// Unlike `tag().name()`, this follows the language's quirks: `typeof null`
+    /// is `"object"`, and objects of type `Function` report `"function"`.
+    pub fn type_of(&self) -> &'static str {
+        match self {
+            JSValue::Undefined => "undefined",
+            JSValue::Null => "object",
+            JSValue::Boolean(_) => "boolean",
+            JSValue::Number(_) | JSValue::Integer(_) => "number",
+            JSValue::String(_) => "string",
+            JSValue::Object(handle) if handle.ptr.inner.read().obj_type == JSObjectType::Function => "function",
+            JSValue::Object(_) => "object",
+        }
+    }
+    
+    /// Get the canonical representation of a value
+    ///
+    /// Booleans and numbers are stored inline in `JSValue`, so there is nothing to
+    /// share for them and small integers are already as cheap as they get; strings
+    /// are interned and objects are references. What can differ is the bit pattern
+    /// of NaN, which is normalized here so canonical values compare equal with
+    /// `same_representation` exactly when they hold the same value. `-0` keeps
+    /// its sign, since `Object.is` tells it apart from `0`.
+    pub fn canonical(value: JSValue) -> JSValue {
+        match value {
+            JSValue::Number(n) if n.is_nan() => JSValue::Number(f64::NAN),
+            other => other,
+        }
+    }
+    
+    /// Whether two values have identical representations
+    ///
+    /// Numbers compare by bit pattern, strings and objects by pointer. For
+    /// canonical values this is `Object.is` (SameValue), so an `Integer` zero
+    /// differs from `-0`.
+    pub fn same_representation(&self, other: &JSValue) -> bool {
+        match (self, other) {
+            (JSValue::Number(a), JSValue::Number(b)) => a.to_bits() == b.to_bits(),
+            (JSValue::Number(n), JSValue::Integer(_)) | (JSValue::Integer(_), JSValue::Number(n))
+                if n.to_bits() == (-0.0f64).to_bits() => false,
+            _ => self.strict_equals(other),
+        }
+    }
+    
+    /// Compare two values with the spec's SameValueZero, as `includes` and `Map` keys do
+    ///
+    /// Like `strict_equals` except that NaN equals NaN. Zeros of either sign are
+    /// equal. Strings compare by content, after a pointer check.
+    pub fn same_value_zero(&self, other: &JSValue) -> bool {
+        match (self, other) {
+            (JSValue::String(a), JSValue::String(b)) => a == b || a.as_str() == b.as_str(),
+            _ => self.strict_equals(other) || (self.is_nan() && other.is_nan()),
+        }
+    }
+    
+    /// Whether this is a NaN number
+    fn is_nan(&self) -> bool {
+        matches!(self, JSValue::Number(n) if n.is_nan())
+    }
+    
+    /// Compare two values with the spec's `===`
+    ///
+    /// Strings compare by interned identity and objects by reference, so this
+    /// never hashes or walks anything.
+    pub fn strict_equals(&self, other: &JSValue) -> bool {
+        match (self, other) {
+            (JSValue::Undefined, JSValue::Undefined) | (JSValue::Null, JSValue::Null) => true,
+            (JSValue::Boolean(a), JSValue::Boolean(b)) => a == b,
+            (JSValue::Number(a), JSValue::Number(b)) => a == b,
+            (JSValue::Integer(a), JSValue::Integer(b)) => a == b,
+            (JSValue::Integer(i), JSValue::Number(n)) | (JSValue::Number(n), JSValue::Integer(i)) => {
+                integer_equals_number(*i, *n)
+            }
+            (JSValue::String(a), JSValue::String(b)) => a == b,
+            (JSValue::Object(a), JSValue::Object(b)) => Arc::ptr_eq(&a.ptr, &b.ptr),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Debug for JSValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JSValue::Undefined => write!(f, "undefined"),
+            JSValue::Null => write!(f, "null"),
+            JSValue::Boolean(b) => write!(f, "{}", b),
+            JSValue::Number(n) => write!(f, "{}", n),
+            JSValue::Integer(i) => write!(f, "{}", i),
+            JSValue::String(s) => write!(f, "\"{}\"", s),
+            JSValue::Object(_) => write!(f, "[object]"),
+        }
+    }
+}
+
+impl fmt::Display for JSValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pretty(0))
+    }
+}
+
+impl JSValue {
+    /// Render this value for humans, recursing into objects and arrays
+    ///
+    /// With a non-zero `indent`, nested entries go on their own lines indented by
+    /// that many spaces per level, like `JSON.stringify(value, null, indent)`; with
+    /// zero everything stays on one line. Values JSON can't represent are rendered
+    /// anyway, and a reference back to an enclosing object prints `[Circular]`.
+    pub fn pretty(&self, indent: usize) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, indent, 0, &mut Vec::new());
+        out
+    }
+    
+    fn write_pretty(&self, out: &mut String, indent: usize, depth: usize, ancestors: &mut Vec<*const JSObject>) {
+        let handle = match self {
+            JSValue::Object(handle) => handle,
+            JSValue::String(s) => return out.push_str(&format!("{:?}", s.as_str())),
+            JSValue::Number(n) if n.is_infinite() => {
+                return out.push_str(if *n > 0.0 { "Infinity" } else { "-Infinity" });
+            }
+            other => return out.push_str(&format!("{:?}", other)),
+        };
+        
+        let ptr = Arc::as_ptr(&handle.ptr);
+        if ancestors.contains(&ptr) {
+            return out.push_str("[Circular]");
+        }
+        
+        // Snapshot the contents so no lock is held while recursing
+        let obj_type = handle.ptr.inner.read().obj_type;
+        let (open, close, entries) = match obj_type {
+            JSObjectType::Function => return out.push_str("[Function]"),
+            JSObjectType::Array => {
+                let elements = handle.ptr.inner.read().elements.to_vec();
+                ('[', ']', elements.into_iter().map(|value| (None, value)).collect::<Vec<_>>())
+            }
+            _ => {
+                let entries = handle.ptr.entries();
+                ('{', '}', entries.into_iter().map(|(key, value)| (Some(key), value)).collect())
+            }
+        };
+        
+        out.push(open);
+        if entries.is_empty() {
+            out.push(close);
+            return;
+        }
+        
+        ancestors.push(ptr);
+        for (i, (key, value)) in entries.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            if indent > 0 {
+                out.push('\n');
+                out.push_str(&" ".repeat(indent * (depth + 1)));
+            } else if i > 0 {
+                out.push(' ');
+            }
+            if let Some(key) = key {
+                out.push_str(&format!("{:?}: ", key.to_string()));
+            }
+            value.write_pretty(out, indent, depth + 1, ancestors);
+        }
+        ancestors.pop();
+        
+        if indent > 0 {
+            out.push('\n');
+            out.push_str(&" ".repeat(indent * depth));
+        }
+        out.push(close);
+    }
+}
+
+// Helper conversion implementations for JSValue
+impl From<&str> for JSValue {
+    fn from(s: &str) -> Self {
+        JSValue::String(InternedString::new(s))
+    }
+}
+
+impl From<String> for JSValue {
+    fn from(s: String) -> Self {
+        JSValue::String(InternedString::from_owned(s))
+    }
+}
+
+impl From<f64> for JSValue {
+    fn from(n: f64) -> Self {
+        JSValue::Number(n)
+    }
+}
+
+impl From<i64> for JSValue {
+    fn from(i: i64) -> Self {
+        JSValue::Integer(i)
+    }
+}
+
+impl From<bool> for JSValue {
+    fn from(b: bool) -> Self {
+        JSValue::Boolean(b)
+    }
+}
+
+/// Whether an integer and a double hold exactly the same value
+///
+/// Converting the integer to f64 would round above 2^53, so the double is
+/// converted instead when it is integral and in range.
+fn integer_equals_number(i: i64, n: f64) -> bool {
+    n.fract() == 0.0 && (-9223372036854775808.0..9223372036854775808.0).contains(&n) && n as i64 == i
+}
+
+/// Parse a string as a number the way the spec's ToNumber does
+fn string_to_number(s: &str) -> f64 {
+    let s = s.trim();
+    if s.is_empty() {
+        return 0.0;
+    }
+    
+    match s {
+        "Infinity" | "+Infinity" => return f64::INFINITY,
+        "-Infinity" => return f64::NEG_INFINITY,
+        _ => {}
+    }
+    
+    let radix = match s.get(..2) {
+        Some("0x") | Some("0X") => Some(16),
+        Some("0o") | Some("0O") => Some(8),
+        Some("0b") | Some("0B") => Some(2),
+        _ => None,
+    };
+    if let Some(radix) = radix {
+        return radix_integer_to_number(&s[2..], radix);
+    }
+    
+    // Rust also accepts spellings like "inf" and "nan" that JavaScript doesn't
+    if s.bytes().any(|b| b.is_ascii_alphabetic() && b != b'e' && b != b'E') {
+        return f64::NAN;
+    }
+    s.parse().unwrap_or(f64::NAN)
+}
+
+/// Parse the digits after a `0x`, `0o` or `0b` prefix
+///
+/// Digits accumulate in a u64 while they fit and in an f64 after that, so
+/// literals above `u64::MAX` still parse, rounded like any large number.
+fn radix_integer_to_number(digits: &str, radix: u32) -> f64 {
+    if digits.is_empty() {
+        return f64::NAN;
+    }
+    
+    let mut exact: u64 = 0;
+    let mut overflowed: Option<f64> = None;
+    for c in digits.chars() {
+        let Some(digit) = c.to_digit(radix) else {
+            return f64::NAN;
+        };
+        overflowed = match overflowed {
+            Some(n) => Some(n * radix as f64 + digit as f64),
+            None => match exact.checked_mul(radix as u64).and_then(|n| n.checked_add(digit as u64)) {
+                Some(n) => {
+                    exact = n;
+                    None
+                }
+                None => Some(exact as f64 * radix as f64 + digit as f64),
+            },
+        };
+    }
+    overflowed.unwrap_or(exact as f64)
+}
+
+/// Format a number the way the spec's Number::toString does
+fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        return "NaN".to_string();
+    }
+    if n == 0.0 {
+        // Covers -0 too
+        return "0".to_string();
+    }
+    if n.is_infinite() {
+        return if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    
+    // Rust's exponent form gives the shortest round-tripping digits
+    let sci = format!("{:e}", n.abs());
+    let (mantissa, exponent) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    // Position of the decimal point relative to the digits
+    let point = exponent.parse::<i32>().unwrap_or(0) + 1;
+    
+    let body = if k <= point && point <= 21 {
+        format!("{}{}", digits, "0".repeat((point - k) as usize))
+    } else if 0 < point && point <= 21 {
+        format!("{}.{}", &digits[..point as usize], &digits[point as usize..])
+    } else if -6 < point && point <= 0 {
+        format!("0.{}{}", "0".repeat((-point) as usize), digits)
+    } else {
+        let sign = if point > 0 { '+' } else { '-' };
+        let fraction = if k > 1 { format!(".{}", &digits[1..]) } else { String::new() };
+        format!("{}{}e{}{}", &digits[..1], fraction, sign, (point - 1).abs())
+    };
+    
+    if n < 0.0 { format!("-{}", body) } else { body }
+}
+
+// Number of incremental marking phases currently in progress; while non-zero,
+// storing an object into an already-marked object marks the stored object too
+static ACTIVE_MARKING: AtomicUsize = AtomicUsize::new(0);
+
+/// Enable the incremental marking write barrier
+pub(crate) fn begin_incremental_marking() {
+    ACTIVE_MARKING.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Disable the incremental marking write barrier
+pub(crate) fn end_incremental_marking() {
+    ACTIVE_MARKING.fetch_sub(1, Ordering::SeqCst);
+}
+
+/// Parse a property key as an array index (a canonical uint32 below 2^32 - 1)
+fn parse_array_index(key: &str) -> Option<u32> {
+    let bytes = key.as_bytes();
+    if bytes.is_empty() || (bytes.len() > 1 && bytes[0] == b'0') {
+        return None;
+    }
+    if !bytes.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    key.parse::<u32>().ok().filter(|&index| index != u32::MAX)
+}
+
+// Most `Undefined` slots an array index write may add past the end of the
+// elements; indices further out are stored as named properties instead
+const MAX_ELEMENT_GAP: usize = 1024;
+
+/// Get the array index a property key refers to, if any
+///
+/// Keys that don't start with a digit are rejected before any parsing, so
+/// ordinary named properties pay a single byte comparison.
+pub fn array_index(key: &str) -> Option<u32> {
+    if !key.as_bytes().first().is_some_and(u8::is_ascii_digit) {
+        return None;
+    }
+    parse_array_index(key)
+}
+
+/// Computes a lazily materialized property the first time it is read
+///
+/// `JSValue` has no C layout, so initializers are meant to be written in Rust.
+#[allow(improper_ctypes_definitions)]
+pub type LazyInitializer = extern "C" fn(obj: *mut JSObject) -> JSValue;
+
+/// A lazily materialized property that hasn't been stored yet
+pub struct LazyProperty {
+    pub initializer: LazyInitializer,
+    /// Filled in by the first reader; readers arriving meanwhile wait on it
+    /// rather than run the initializer again
+    pub value: Arc<OnceCell<JSValue>>,
+}
+
+impl LazyProperty {
+    pub fn new(initializer: LazyInitializer) -> Self {
+        LazyProperty { initializer, value: Arc::new(OnceCell::new()) }
+    }
+}
+
+/// Notified after an object moves from one shape to another
+pub type ShapeChangeCallback = extern "C" fn(obj: *const JSObject, old_shape_id: usize, new_shape_id: usize);
+
+// Named property count above which objects switch to dictionary mode; 0 never switches
+static DICTIONARY_MODE_THRESHOLD: AtomicUsize = AtomicUsize::new(128);
+
+/// Get the number of named properties an object can hold before it leaves shape-based storage
+pub fn dictionary_mode_threshold() -> usize {
+    DICTIONARY_MODE_THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Set the number of named properties an object can hold before it switches to dictionary mode
+///
+/// Applies to every object in the process the next time it gains a property;
+/// objects already in dictionary mode stay there. 0 keeps objects on shapes.
+pub fn set_dictionary_mode_threshold(count: usize) {
+    DICTIONARY_MODE_THRESHOLD.store(count, Ordering::Relaxed);
+}
+
+// Ids handed out to objects, starting at 1 so that 0 can mean "no object"
+static NEXT_OBJECT_ID: AtomicU64 = AtomicU64::new(1);
+
+// Held while a prototype link is checked and stored, so two changes can't each
+// pass the cycle check and then close a loop together
+static PROTOTYPE_LOCK: parking_lot::Mutex<()> = parking_lot::const_mutex(());
+
+// Addresses of live objects, consulted before FFI dereferences a handle
+#[cfg(feature = "handle-validation")]
+static LIVE_OBJECTS: once_cell::sync::Lazy<parking_lot::Mutex<std::collections::HashSet<usize>>> =
+    once_cell::sync::Lazy::new(|| parking_lot::Mutex::new(std::collections::HashSet::new()));
+
+/// Check whether a raw handle points at a live object
+///
+/// Only tracked with the `handle-validation` feature. Without it, every handle
+/// is assumed valid so release builds keep the direct-dereference fast path.
+pub fn is_live_handle(ptr: *const JSObject) -> bool {
+    #[cfg(feature = "handle-validation")]
+    {
+        LIVE_OBJECTS.lock().contains(&(ptr as usize))
+    }
+    #[cfg(not(feature = "handle-validation"))]
+    {
+        !ptr.is_null()
+    }
+}
+
+/// Internal structure of a JavaScript object
+pub struct JSObjectInner {
+    pub obj_type: JSObjectType,
+    // Using shape-based optimization
+    pub shape: Arc<PropertyShape>,
+    pub values: Vec<JSValue>,
+    // Indexed element storage used by arrays
+    pub elements: Elements,
+    pub marked: bool,
+    // Young collections survived; a byte is plenty since promotion ages are small
+    pub age: u8,
+    pub finalizer: Option<extern "C" fn(*mut JSObject)>,
+    // Properties computed on first read; usually empty, so a Vec beats a map
+    pub lazy_properties: Vec<(InternedString, LazyProperty)>,
+    // Object that inherited properties are looked up on
+    pub prototype: Option<JSObjectHandle>,
+    // Observer for shape transitions, e.g. to deoptimize specialized code
+    pub shape_change_callback: Option<ShapeChangeCallback>,
+    // Set by freeze_shape; the shape can no longer change, only slot values
+    pub shape_frozen: bool,
+    // Set by freeze; nothing can change, not even slot values or elements
+    pub frozen: bool,
+    // Bit per slot written while on a shape with defaults; unset slots read the default
+    pub overridden: Vec<u64>,
+    // Named properties once the object has outgrown shapes; the shape is then the root
+    pub dictionary: Option<PropertyDictionary>,
+}
+
+impl JSObjectInner {
+    /// Create a new JS object inner state
+    pub fn new(obj_type: JSObjectType) -> Self {
+        Self {
+            obj_type,
+            shape: PropertyShape::root(),
+            values: Vec::new(),
+            elements: Elements::default(),
+            marked: false,
+            age: 0,
+            finalizer: None,
+            lazy_properties: Vec::new(),
+            prototype: None,
+            shape_change_callback: None,
+            shape_frozen: false,
+            frozen: false,
+            overridden: Vec::new(),
+            dictionary: None,
+        }
+    }
+    
+    /// Read a named property slot, falling back to the shape's default
+    fn slot_value(&self, slot: usize) -> JSValue {
+        match self.shape.default_value(slot) {
+            Some(default) if !self.is_overridden(slot) => default.clone(),
+            _ => self.values.get(slot).cloned().unwrap_or_default(),
+        }
+    }
+    
+    fn is_overridden(&self, slot: usize) -> bool {
+        self.overridden.get(slot / 64).is_some_and(|bits| bits & (1 << (slot % 64)) != 0)
+    }
+    
+    /// Record that `slot` now holds its own value rather than the default
+    fn mark_overridden(&mut self, slot: usize) {
+        if self.shape.default_value(slot).is_none() {
+            return;
+        }
+        if slot / 64 >= self.overridden.len() {
+            self.overridden.resize(slot / 64 + 1, 0);
+        }
+        self.overridden[slot / 64] |= 1 << (slot % 64);
+    }
+    
+    /// Copy the defaults of unwritten slots into the object before leaving the shape
+    fn materialize_defaults(&mut self) {
+        let slots = self.shape.property_count();
+        if !self.shape.has_defaults() {
+            return;
+        }
+        if self.values.len() < slots {
+            self.values.resize_with(slots, || JSValue::Undefined);
+        }
+        for slot in 0..slots {
+            if let Some(default) = self.shape.default_value(slot) {
+                if !self.is_overridden(slot) {
+                    self.values[slot] = default.clone();
+                }
+            }
+        }
+        self.overridden.clear();
+    }
+    
+    /// Get the element slot `key` names, if it is an array index kept in the elements
+    ///
+    /// Indices written too far past the end are stored as named properties,
+    /// see `fits_elements`, so a key already held that way isn't an element.
+    fn element_index(&self, key: &str) -> Option<usize> {
+        if self.obj_type != JSObjectType::Array {
+            return None;
+        }
+        let index = array_index(key)? as usize;
+        (!self.has_named_property(key)).then_some(index)
+    }
+    
+    /// Whether element `index` can be written without leaving a large run of holes
+    fn fits_elements(&self, index: usize) -> bool {
+        index <= self.elements.len() + MAX_ELEMENT_GAP
+    }
+    
+    /// Whether `key` is an own named property, in either storage mode
+    fn has_named_property(&self, key: &str) -> bool {
+        match &self.dictionary {
+            Some(dictionary) => dictionary.contains_key(key),
+            None => self.shape.get_property_index(key).is_some(),
+        }
+    }
+    
+    /// Get the named property names in enumeration order, in either storage mode
+    fn named_properties_ordered(&self) -> Vec<InternedString> {
+        match &self.dictionary {
+            Some(dictionary) => dictionary.names_ordered(),
+            None => self.shape.property_names_ordered(),
+        }
+    }
+    
+    /// Switch to dictionary mode if the shape now holds more properties than allowed
+    fn check_dictionary_threshold(&mut self) {
+        let threshold = dictionary_mode_threshold();
+        if threshold > 0 && self.dictionary.is_none() && self.shape.property_count() > threshold {
+            self.enter_dictionary_mode();
+        }
+    }
+    
+    /// Move the named properties out of the shape into a dictionary
+    fn enter_dictionary_mode(&mut self) {
+        self.materialize_defaults();
+        let mut dictionary = PropertyDictionary::default();
+        for (slot, name) in self.shape.property_names().iter().enumerate() {
+            let value = self.values.get_mut(slot).map(mem::take).unwrap_or_default();
+            dictionary.insert(name.clone(), value);
+        }
+        
+        let old_shape = mem::replace(&mut self.shape, PropertyShape::root());
+        old_shape.remove_reference();
+        self.shape.add_reference();
+        self.values = Vec::new();
+        self.overridden = Vec::new();
+        self.dictionary = Some(dictionary);
+    }
+}
+
+/// Named properties of an object in dictionary mode
+///
+/// Objects with many properties would otherwise walk a long chain of shapes,
+/// each holding a copy of the property map. Keys are also kept in insertion
+/// order so enumeration order doesn't change when an object switches.
+#[derive(Clone, Default)]
+pub struct PropertyDictionary {
+    values: HashMap<InternedString, JSValue>,
+    order: Vec<InternedString>,
+    // Names in enumeration order, built on first request and dropped when the
+    // names change
+    ordered: OnceCell<Vec<InternedString>>,
+}
+
+impl PropertyDictionary {
+    /// Get the number of properties
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+    
+    /// Whether there are no properties
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+    
+    /// Whether `key` is one of the properties
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+    
+    /// Get the value of `key`
+    pub fn get(&self, key: &str) -> Option<&JSValue> {
+        self.values.get(key)
+    }
+    
+    /// Set `key`, appending it to the enumeration order if it is new
+    pub fn insert(&mut self, key: InternedString, value: JSValue) {
+        if let Some(existing) = self.values.get_mut(&key) {
+            *existing = value;
+            return;
+        }
+        self.order.push(key.clone());
+        self.values.insert(key, value);
+        self.ordered.take();
+    }
+    
+    /// Remove `key`, returning its value
+    pub fn remove(&mut self, key: &str) -> Option<JSValue> {
+        let value = self.values.remove(key)?;
+        self.order.retain(|name| name.as_str() != key);
+        self.ordered.take();
+        Some(value)
+    }
+    
+    /// Rename `from` to `to` in place, keeping its value and position
+    ///
+    /// Returns false if `from` is missing or `to` already exists.
+    pub fn rename(&mut self, from: &str, to: &str) -> bool {
+        if self.values.contains_key(to) {
+            return false;
+        }
+        let Some(value) = self.values.remove(from) else {
+            return false;
+        };
+        let to = InternedString::new(to);
+        if let Some(name) = self.order.iter_mut().find(|name| name.as_str() == from) {
+            *name = to.clone();
+        }
+        self.values.insert(to, value);
+        self.ordered.take();
+        true
+    }
+    
+    /// Iterate over the properties in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = (&InternedString, &JSValue)> {
+        self.order.iter().map(|name| (name, &self.values[name]))
+    }
+    
+    /// Get the names in enumeration order: integer keys ascending, then insertion order
+    pub fn names_ordered(&self) -> Vec<InternedString> {
+        self.names_in_enumeration_order().to_vec()
+    }
+    
+    /// Get the name at `index` in `names_ordered`, without copying the list
+    pub fn name_ordered_at(&self, index: usize) -> Option<&InternedString> {
+        self.names_in_enumeration_order().get(index)
+    }
+    
+    fn names_in_enumeration_order(&self) -> &[InternedString] {
+        self.ordered.get_or_init(|| {
+            let mut names = self.order.clone();
+            names.sort_by_key(|name| match array_index(name) {
+                Some(index) => (0, index),
+                None => (1, 0),
+            });
+            names
+        })
+    }
+    
+    /// Take every object-valued property, leaving `undefined` in its place
+    fn take_objects(&mut self, released: &mut Vec<JSValue>) {
+        for value in self.values.values_mut() {
+            if matches!(value, JSValue::Object(_)) {
+                released.push(mem::take(value));
+            }
+        }
+    }
+    
+    /// Estimate the bytes held by the map and the order list, names counted as handles
+    pub fn size_bytes(&self) -> usize {
+        self.order.capacity() * mem::size_of::<InternedString>()
+            + self.values.capacity() * mem::size_of::<(InternedString, JSValue)>()
+    }
+    
+    /// Release spare capacity, returning the bytes freed
+    pub fn shrink_to_fit(&mut self) -> usize {
+        let spare_order = self.order.capacity() - self.order.len();
+        let spare_values = self.values.capacity() - self.values.len();
+        self.order.shrink_to_fit();
+        self.values.shrink_to_fit();
+        spare_order * mem::size_of::<InternedString>()
+            + spare_values * mem::size_of::<(InternedString, JSValue)>()
+    }
+}
+
+/// Indexed element storage of an array
+///
+/// Elements start out as unboxed doubles while every element is a
+/// `JSValue::Number`. Storing anything else, including the `Undefined` that
+/// fills a gap, switches to boxed values for good.
+#[derive(Clone)]
+pub enum Elements {
+    /// Every element is a `JSValue::Number`, stored as its `f64`
+    PackedDoubles(Vec<f64>),
+    /// Elements of any type
+    Boxed(Vec<JSValue>),
+}
+
+impl Default for Elements {
+    fn default() -> Self {
+        Elements::PackedDoubles(Vec::new())
+    }
+}
+
+impl Elements {
+    /// Store `values`, packed if they are all doubles
+    pub fn from_values(values: Vec<JSValue>) -> Self {
+        let doubles: Option<Vec<f64>> = values.iter()
+            .map(|value| match value {
+                JSValue::Number(n) => Some(*n),
+                _ => None,
+            })
+            .collect();
+        match doubles {
+            Some(doubles) => Elements::PackedDoubles(doubles),
+            None => Elements::Boxed(values),
+        }
+    }
+    
+    /// Get the number of elements
+    pub fn len(&self) -> usize {
+        match self {
+            Elements::PackedDoubles(doubles) => doubles.len(),
+            Elements::Boxed(values) => values.len(),
+        }
+    }
+    
+    /// Whether there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Whether the elements are stored as unboxed doubles
+    pub fn is_packed(&self) -> bool {
+        matches!(self, Elements::PackedDoubles(_))
+    }
+    
+    /// Get the element at `index`, boxing it if needed
+    pub fn get(&self, index: usize) -> Option<JSValue> {
+        match self {
+            Elements::PackedDoubles(doubles) => doubles.get(index).map(|&n| JSValue::Number(n)),
+            Elements::Boxed(values) => values.get(index).cloned(),
+        }
+    }
+    
+    /// Copy the elements out as values
+    pub fn to_vec(&self) -> Vec<JSValue> {
+        match self {
+            Elements::PackedDoubles(doubles) => doubles.iter().map(|&n| JSValue::Number(n)).collect(),
+            Elements::Boxed(values) => values.clone(),
+        }
+    }
+    
+    /// Get the boxed elements, or nothing when packed
+    ///
+    /// Packed doubles can't reference objects, so this is all tracing needs.
+    pub fn boxed_values(&self) -> &[JSValue] {
+        match self {
+            Elements::PackedDoubles(_) => &[],
+            Elements::Boxed(values) => values,
+        }
+    }
+    
+    /// Switch to boxed storage if needed and get the values
+    fn boxed_mut(&mut self) -> &mut Vec<JSValue> {
+        if let Elements::PackedDoubles(doubles) = self {
+            *self = Elements::Boxed(doubles.iter().map(|&n| JSValue::Number(n)).collect());
+        }
+        match self {
+            Elements::Boxed(values) => values,
+            Elements::PackedDoubles(_) => unreachable!(),
+        }
+    }
+    
+    /// Set the element at `index`, filling any gap before it with `Undefined`
+    pub fn set(&mut self, index: usize, value: JSValue) {
+        if let (Elements::PackedDoubles(doubles), JSValue::Number(n)) = (&mut *self, &value) {
+            if index < doubles.len() {
+                doubles[index] = *n;
+                return;
+            }
+            if index == doubles.len() {
+                doubles.push(*n);
+                return;
+            }
+        }
+        
+        let values = self.boxed_mut();
+        if index >= values.len() {
+            values.resize_with(index + 1, || JSValue::Undefined);
+        }
+        values[index] = value;
+    }
+    
+    /// Append an element
+    pub fn push(&mut self, value: JSValue) {
+        self.set(self.len(), value);
+    }
+    
+    /// Remove and return the last element
+    pub fn pop(&mut self) -> Option<JSValue> {
+        match self {
+            Elements::PackedDoubles(doubles) => doubles.pop().map(JSValue::Number),
+            Elements::Boxed(values) => values.pop(),
+        }
+    }
+    
+    /// Remove and return the element at `index`, shifting the rest down
+    ///
+    /// Panics if `index` is out of bounds, like `Vec::remove`.
+    pub fn remove(&mut self, index: usize) -> JSValue {
+        match self {
+            Elements::PackedDoubles(doubles) => JSValue::Number(doubles.remove(index)),
+            Elements::Boxed(values) => values.remove(index),
+        }
+    }
+    
+    /// Insert an element at `index`, shifting the rest up
+    ///
+    /// Panics if `index` is past the end, like `Vec::insert`.
+    pub fn insert(&mut self, index: usize, value: JSValue) {
+        if let (Elements::PackedDoubles(doubles), JSValue::Number(n)) = (&mut *self, &value) {
+            doubles.insert(index, *n);
+            return;
+        }
+        self.boxed_mut().insert(index, value);
+    }
+    
+    /// Take out every object element, leaving `Undefined` in its place
+    fn take_objects(&mut self, released: &mut Vec<JSValue>) {
+        if let Elements::Boxed(values) = self {
+            for value in values.iter_mut() {
+                if matches!(value, JSValue::Object(_)) {
+                    released.push(mem::take(value));
+                }
+            }
+        }
+    }
+    
+    /// Release spare capacity, returning the bytes freed
+    pub fn shrink_to_fit(&mut self) -> usize {
+        match self {
+            Elements::PackedDoubles(doubles) => {
+                let spare = doubles.capacity() - doubles.len();
+                doubles.shrink_to_fit();
+                spare * mem::size_of::<f64>()
+            }
+            Elements::Boxed(values) => {
+                let spare = values.capacity() - values.len();
+                values.shrink_to_fit();
+                spare * mem::size_of::<JSValue>()
+            }
+        }
+    }
+    
+    /// Get the bytes the elements occupy
+    pub fn size_bytes(&self) -> usize {
+        match self {
+            Elements::PackedDoubles(doubles) => doubles.len() * mem::size_of::<f64>(),
+            Elements::Boxed(values) => values.len() * mem::size_of::<JSValue>(),
+        }
+    }
+}
+
+/// JavaScript object - thread-safe wrapper around properties
+pub struct JSObject {
+    pub inner: RwLock<JSObjectInner>,
+    // Unique id that never changes, unlike the object's address
+    id: u64,
+    // Whether the object lives in the old generation; outside the lock so the
+    // write barrier can check a stored object while holding its own lock
+    tenured: AtomicBool,
+    // Whether this old object may reference young objects (remembered set membership)
+    remembered: AtomicBool,
+    // Young objects stored into this old object since its collector last counted
+    barrier_hits: AtomicUsize,
+    // Whether the finalizer step has run, by a sweep or by Drop; it never runs twice
+    finalized: AtomicBool,
+}
+
+impl JSObject {
+    /// Create a new JavaScript object of the specified type
+    pub fn new(obj_type: JSObjectType) -> Arc<Self> {
+        let obj = Arc::new(Self {
+            inner: RwLock::new(JSObjectInner::new(obj_type)),
+            id: NEXT_OBJECT_ID.fetch_add(1, Ordering::Relaxed),
+            tenured: AtomicBool::new(false),
+            remembered: AtomicBool::new(false),
+            barrier_hits: AtomicUsize::new(0),
+            finalized: AtomicBool::new(false),
+        });
+        
+        #[cfg(feature = "handle-validation")]
+        LIVE_OBJECTS.lock().insert(Arc::as_ptr(&obj) as usize);
+        
+        obj
+    }
+    
+    /// Create a weak reference to `this` that doesn't keep it alive
+    pub fn downgrade(this: &Arc<JSObject>) -> JSWeakRef {
+        WeakJSObjectHandle { ptr: Arc::downgrade(this) }
+    }
+    
+    /// Get this object's unique id, safe to log and compare across calls
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+    
+    /// Get the id of this object's current shape
+    ///
+    /// Slot indices are only meaningful for the shape they were read from, so
+    /// callers caching an index must compare this first.
+    pub fn shape_id(&self) -> usize {
+        self.inner.read().shape.id()
+    }
+    
+    /// Whether the object keeps its named properties in a dictionary instead of a shape
+    ///
+    /// Objects switch once they gain more than `dictionary_mode_threshold`
+    /// named properties, and switch back once `try_restore_shape` finds them
+    /// below it again. Their shape is the root shape, which has no slots, so
+    /// `get_slot` and `set_slot` don't apply to them.
+    pub fn is_dictionary_mode(&self) -> bool {
+        self.inner.read().dictionary.is_some()
+    }
+    
+    /// Read a named property by slot index, skipping the key lookup
+    ///
+    /// For JIT code that has confirmed `shape_id` against an inline cache. The
+    /// index is only bounds-checked in debug builds; in release builds an
+    /// out-of-range index reads `Undefined`, and an index taken from a different
+    /// shape silently reads whatever property lives in that slot.
+    pub fn get_slot(&self, index: usize) -> JSValue {
+        let inner = self.inner.read();
+        debug_assert!(index < inner.shape.property_count(), "slot {} out of range for shape {}", index, inner.shape.id());
+        inner.slot_value(index)
+    }
+    
+    /// Write a named property by slot index, skipping the key lookup
+    ///
+    /// The same assumptions as `get_slot` apply: the caller must have checked
+    /// `shape_id`, and the index is only bounds-checked in debug builds. Release
+    /// builds drop out-of-range writes. The shape never changes, so this works on
+    /// objects with a frozen shape and doesn't notify the shape change callback.
+    /// Writes to frozen objects are dropped.
+    pub fn set_slot(&self, index: usize, value: JSValue) {
+        let mut inner = self.inner.write();
+        debug_assert!(index < inner.shape.property_count(), "slot {} out of range for shape {}", index, inner.shape.id());
+        if index >= inner.shape.property_count() || inner.frozen {
+            return;
+        }
+        
+        let barrier = self.write_barrier(&inner, &value);
+        #[cfg(feature = "shape-type-profile")]
+        inner.shape.record_slot_type(index, value.tag());
+        if index >= inner.values.len() {
+            inner.values.resize_with(index + 1, || JSValue::Undefined);
+        }
+        inner.values[index] = value;
+        inner.mark_overridden(index);
+        
+        drop(inner);
+        if let Some(target) = barrier {
+            target.mark();
+        }
+    }
+    
+    /// Set a property on this object
+    ///
+    /// Returns false if the object is frozen, or if the key is new and the
+    /// object's shape is frozen.
+    pub fn set_property(&self, key: &str, value: JSValue) -> bool {
+        let mut inner = self.inner.write();
+        let barrier = self.write_barrier(&inner, &value);
+        let old_shape_id = inner.shape.id();
+        
+        let written = Self::write_property(&mut inner, key, value);
+        let shape_change = Self::shape_change(&inner, old_shape_id);
+        
+        // Mark outside the lock so self-references can't deadlock
+        drop(inner);
+        if let Some(target) = barrier {
+            target.mark();
+        }
+        self.notify_shape_change(shape_change);
+        written
+    }
+    
+    /// Set several properties while holding the object's lock once
+    ///
+    /// Equivalent to calling `set_property` for each pair in order, except that
+    /// the shape change callback runs at most once, after every write. Returns
+    /// how many properties were written; new keys are refused once the shape
+    /// is frozen, and every key once the object is.
+    pub fn set_properties<'a>(&self, properties: impl IntoIterator<Item = (&'a str, JSValue)>) -> usize {
+        let mut inner = self.inner.write();
+        let old_shape_id = inner.shape.id();
+        let mut barriers = Vec::new();
+        let mut written = 0;
+        for (key, value) in properties {
+            barriers.extend(self.write_barrier(&inner, &value));
+            if Self::write_property(&mut inner, key, value) {
+                written += 1;
+            }
+        }
+        let shape_change = Self::shape_change(&inner, old_shape_id);
+        
+        drop(inner);
+        for target in barriers {
+            target.mark();
+        }
+        self.notify_shape_change(shape_change);
+        written
+    }
+    
+    /// Remove an own property, returning whether it existed
+    ///
+    /// Shapes only ever grow, so the object moves to the shape reached by adding
+    /// the remaining keys to the root shape in their original order, and the
+    /// remaining values are compacted to match. Objects left with the same keys
+    /// therefore share a shape again. Objects in dictionary mode just drop the
+    /// key. Deleting an array element leaves `undefined` in its place, since
+    /// elements are stored without holes. Frozen objects refuse every delete.
+    pub fn delete_property(&self, key: &str) -> bool {
+        let mut inner = self.inner.write();
+        let element = inner.element_index(key);
+        if inner.frozen || inner.shape_frozen && element.is_none() {
+            return false;
+        }
+        
+        let had_lazy = inner.lazy_properties.iter().any(|(lazy_key, _)| lazy_key.as_str() == key);
+        if had_lazy {
+            inner.lazy_properties.retain(|(lazy_key, _)| lazy_key.as_str() != key);
+        }
+        
+        if let Some(index) = element {
+            if index >= inner.elements.len() {
+                return had_lazy;
+            }
+            inner.elements.set(index, JSValue::Undefined);
+            return true;
+        }
+        
+        if let Some(dictionary) = inner.dictionary.as_mut() {
+            return dictionary.remove(key).is_some() || had_lazy;
+        }
+        
+        let old_shape = inner.shape.clone();
+        let Some(removed) = old_shape.get_property_index(key) else {
+            return had_lazy;
+        };
+        // The rebuilt shape has no defaults
+        inner.materialize_defaults();
+        
+        let mut new_shape = PropertyShape::root();
+        let mut values = Vec::with_capacity(inner.values.len().saturating_sub(1));
+        for (slot, name) in old_shape.property_names().iter().enumerate() {
+            if slot != removed {
+                new_shape = new_shape.transition_to(name);
+                values.push(inner.values.get_mut(slot).map(mem::take).unwrap_or_default());
+            }
+        }
+        
+        old_shape.remove_reference();
+        new_shape.add_reference();
+        inner.shape = new_shape;
+        inner.values = values;
+        
+        let shape_change = Self::shape_change(&inner, old_shape.id());
+        drop(inner);
+        self.notify_shape_change(shape_change);
+        true
+    }
+    
+    /// Call `callback` whenever this object moves to a different shape
+    ///
+    /// Adding a property (including via `reserve_properties`), deleting one or
+    /// renaming one changes the shape; overwriting an existing property doesn't. The callback
+    /// runs after the object's lock is released, so it may read the object.
+    pub fn set_shape_change_callback(&self, callback: ShapeChangeCallback) {
+        self.inner.write().shape_change_callback = Some(callback);
+    }
+    
+    /// Get the pending shape change notification, if the shape moved from `old_shape_id`
+    fn shape_change(inner: &JSObjectInner, old_shape_id: usize) -> Option<(ShapeChangeCallback, usize, usize)> {
+        let new_shape_id = inner.shape.id();
+        if new_shape_id == old_shape_id {
+            return None;
+        }
+        inner.shape_change_callback.map(|callback| (callback, old_shape_id, new_shape_id))
+    }
+    
+    /// Deliver a notification from `shape_change`; call without holding the lock
+    fn notify_shape_change(&self, shape_change: Option<(ShapeChangeCallback, usize, usize)>) {
+        if let Some((callback, old_shape_id, new_shape_id)) = shape_change {
+            callback(self, old_shape_id, new_shape_id);
+        }
+    }
+    
+    /// Get the object that must be marked after storing `value`, if any
+    ///
+    /// Write barrier: an object stored into an already-traced object during
+    /// incremental marking must not be missed. Callers mark the returned object
+    /// after releasing their lock. Storing a young object into an old one also
+    /// adds this object to the remembered set.
+    fn write_barrier(&self, inner: &JSObjectInner, value: &JSValue) -> Option<Arc<JSObject>> {
+        if let JSValue::Object(handle) = value {
+            if self.is_tenured() && !handle.ptr.is_tenured() {
+                self.barrier_hits.fetch_add(1, Ordering::Relaxed);
+                self.remembered.store(true, Ordering::Relaxed);
+            }
+        }
+        
+        match value {
+            JSValue::Object(handle) if inner.marked && ACTIVE_MARKING.load(Ordering::SeqCst) > 0 => {
+                Some(handle.ptr.clone())
+            }
+            _ => None,
+        }
+    }
+    
+    /// Store a property value into the locked object state
+    fn write_property(inner: &mut JSObjectInner, key: &str, value: JSValue) -> bool {
+        if inner.frozen {
+            return false;
+        }
+        
+        // An explicit write replaces any pending lazy initializer
+        if !inner.lazy_properties.is_empty() {
+            inner.lazy_properties.retain(|(lazy_key, _)| lazy_key.as_str() != key);
+        }
+        
+        // Array index keys go to the element storage instead of the shape,
+        // unless they are so far out that the gap would be mostly holes
+        if let Some(index) = inner.element_index(key) {
+            if inner.fits_elements(index) {
+                inner.elements.set(index, value);
+                return true;
+            }
+        }
+        
+        if let Some(dictionary) = inner.dictionary.as_mut() {
+            if !inner.shape_frozen || dictionary.contains_key(key) {
+                dictionary.insert(InternedString::new(key), value);
+                return true;
+            }
+            return false;
+        }
+        
+        // Check if property already exists in the current shape
+        if let Some(index) = inner.shape.get_property_index(key) {
+            #[cfg(feature = "shape-type-profile")]
+            inner.shape.record_slot_type(index, value.tag());
+            
+            // Property exists, just update the value; with shape defaults the
+            // object may not store the slot yet
+            if index >= inner.values.len() {
+                inner.values.resize_with(index + 1, || JSValue::Undefined);
+            }
+            inner.values[index] = value;
+            inner.mark_overridden(index);
+        } else if inner.shape_frozen {
+            return false;
+        } else {
+            // Property doesn't exist, transition to a new shape
+            let old_shape = inner.shape.clone();
+            let new_shape = old_shape.transition_to(key);
+            
+            // Update reference counts
+            old_shape.remove_reference();
+            new_shape.add_reference();
+            
+            // Get the index for the new property
+            let index = new_shape.get_property_index(key).unwrap();
+            #[cfg(feature = "shape-type-profile")]
+            new_shape.record_slot_type(index, value.tag());
+            
+            // Ensure values vector has enough capacity
+            if index >= inner.values.len() {
+                inner.values.resize_with(index + 1, || JSValue::Undefined);
+            }
+            
+            // Set the value and update the shape
+            inner.values[index] = value;
+            inner.shape = new_shape;
+            inner.check_dictionary_threshold();
+        }
+        true
+    }
+    
+    /// Reserve slots for properties the object is known to gain
+    ///
+    /// The object moves straight to the shape holding all of `keys`, with the new
+    /// slots set to `Undefined`, so setting those keys afterwards only writes values.
+    /// Keys already present, and array index keys on arrays, are left untouched.
+    /// Does nothing once the shape is frozen.
+    pub fn reserve_properties(&self, keys: &[&str]) {
+        let mut inner = self.inner.write();
+        if inner.shape_frozen {
+            return;
+        }
+        let shape_change = Self::reserve_slots(&mut inner, keys);
+        drop(inner);
+        self.notify_shape_change(shape_change);
+    }
+    
+    /// Move to the shape holding all of `keys`, under a write lock the caller holds
+    fn reserve_slots(inner: &mut JSObjectInner, keys: &[&str]) -> Option<(ShapeChangeCallback, usize, usize)> {
+        let is_array = inner.obj_type == JSObjectType::Array;
+        
+        if let Some(dictionary) = inner.dictionary.as_mut() {
+            for key in keys {
+                if !(dictionary.contains_key(key) || is_array && array_index(key).is_some()) {
+                    dictionary.insert(InternedString::new(key), JSValue::Undefined);
+                }
+            }
+            return None;
+        }
+        
+        let old_shape = inner.shape.clone();
+        let mut new_shape = old_shape.clone();
+        for key in keys {
+            if is_array && array_index(key).is_some() {
+                continue;
+            }
+            if new_shape.get_property_index(key).is_none() {
+                new_shape = new_shape.transition_to(key);
+            }
+        }
+        
+        if Arc::ptr_eq(&old_shape, &new_shape) {
+            return None;
+        }
+        
+        old_shape.remove_reference();
+        new_shape.add_reference();
+        
+        let slots = new_shape.property_count();
+        if slots > inner.values.len() {
+            inner.values.resize_with(slots, || JSValue::Undefined);
+        }
+        inner.shape = new_shape;
+        inner.check_dictionary_threshold();
+        
+        Self::shape_change(inner, old_shape.id())
+    }
+
+    /// Reclassify the object, returning whether the type changed or already matched
+    ///
+    /// Named properties carry over unchanged. Elements are the only storage
+    /// specific to a type, so an array must be emptied before it can become
+    /// another type, and an object with integer-named properties can't become an
+    /// array, since elements would then shadow those properties.
+    pub fn set_object_type(&self, obj_type: JSObjectType) -> bool {
+        let mut inner = self.inner.write();
+        if inner.obj_type == obj_type {
+            return true;
+        }
+        if inner.frozen {
+            return false;
+        }
+        
+        if inner.obj_type == JSObjectType::Array && !inner.elements.is_empty() {
+            return false;
+        }
+        if obj_type == JSObjectType::Array
+            && inner.named_properties_ordered().iter().any(|name| array_index(name).is_some())
+        {
+            return false;
+        }
+        
+        inner.obj_type = obj_type;
+        true
+    }
+    
+    /// Lock the object's layout while still allowing writes to existing properties
+    ///
+    /// Afterwards adding, deleting or renaming a named property fails and the shape
+    /// id never changes, so code specialized on the shape stays valid. Pending lazy
+    /// properties get their slots reserved first so materializing them still works.
+    /// Array elements aren't part of the shape and remain writable.
+    pub fn freeze_shape(&self) {
+        // Reserve and freeze under one lock, so no lazy property can slip in between
+        let mut inner = self.inner.write();
+        let mut shape_change = None;
+        if !inner.shape_frozen && !inner.lazy_properties.is_empty() {
+            let pending: Vec<InternedString> = inner.lazy_properties.iter()
+                .map(|(key, _)| key.clone())
+                .collect();
+            let keys: Vec<&str> = pending.iter().map(|key| key.as_str()).collect();
+            shape_change = Self::reserve_slots(&mut inner, &keys);
+        }
+        inner.shape_frozen = true;
+        drop(inner);
+        self.notify_shape_change(shape_change);
+    }
+    
+    /// Whether `freeze_shape` has been called on this object
+    pub fn is_shape_frozen(&self) -> bool {
+        self.inner.read().shape_frozen
+    }
+    
+    /// Make the object immutable, like `Object.freeze`
+    ///
+    /// Freezes the shape and then refuses every write: the property setters,
+    /// `delete_property`, the element and array methods and prototype changes
+    /// leave the object untouched and report failure where they return a
+    /// status. Reads are unaffected. Pending lazy properties are materialized
+    /// first so their values are fixed too. Like `Object.freeze`, this is
+    /// shallow and can't be undone.
+    pub fn freeze(&self) {
+        loop {
+            let pending: Vec<InternedString> = self.inner.read().lazy_properties.iter()
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in &pending {
+                self.get_property(key);
+            }
+            
+            // Flip both flags under the lock that saw nothing pending, so a lazy
+            // property added meanwhile is materialized on the next pass
+            let mut inner = self.inner.write();
+            if inner.lazy_properties.is_empty() {
+                inner.shape_frozen = true;
+                inner.frozen = true;
+                return;
+            }
+        }
+    }
+    
+    /// Whether `freeze` has been called on this object
+    pub fn is_frozen(&self) -> bool {
+        self.inner.read().frozen
+    }
+    
+    /// Rename an own property, keeping its value and slot
+    ///
+    /// The object moves to a sibling shape with the key replaced rather than
+    /// losing its layout, so it stays monomorphic. Returns false if `from` isn't
+    /// an own named property or `to` already is.
+    pub fn rename_property(&self, from: &str, to: &str) -> bool {
+        let mut inner = self.inner.write();
+        if inner.shape_frozen {
+            return false;
+        }
+        if inner.obj_type == JSObjectType::Array
+            && (array_index(from).is_some() || array_index(to).is_some()) {
+            return false;
+        }
+        if let Some(dictionary) = inner.dictionary.as_mut() {
+            return dictionary.rename(from, to);
+        }
+        
+        let old_shape = inner.shape.clone();
+        let Some(new_shape) = old_shape.rename_property(from, to) else {
+            return false;
+        };
+        
+        old_shape.remove_reference();
+        new_shape.add_reference();
+        inner.shape = new_shape;
+        
+        let shape_change = Self::shape_change(&inner, old_shape.id());
+        drop(inner);
+        self.notify_shape_change(shape_change);
+        true
+    }
+    
+    /// Release spare capacity in the property and element storage
+    ///
+    /// Storage grows in steps, so objects that were filled and then left alone
+    /// keep unused room. Returns the bytes freed.
+    pub fn compact(&self) -> usize {
+        let mut inner = self.inner.write();
+        let spare_values = inner.values.capacity() - inner.values.len();
+        inner.values.shrink_to_fit();
+        let spare_overridden = inner.overridden.capacity() - inner.overridden.len();
+        inner.overridden.shrink_to_fit();
+        let spare_dictionary = inner.dictionary.as_mut().map_or(0, PropertyDictionary::shrink_to_fit);
+        spare_values * mem::size_of::<JSValue>()
+            + spare_overridden * mem::size_of::<u64>()
+            + spare_dictionary
+            + inner.elements.shrink_to_fit()
+    }
+    
+    /// Copy this object's own properties and elements into a new object on the same shape
+    ///
+    /// The copy shares the shape rather than replaying each property's
+    /// transition, so it stays on the shape's inline caches; only the slot
+    /// values are copied. The prototype, lazy properties and dictionary-mode
+    /// entries carry over too. The finalizer, shape change callback and any
+    /// freezing belong to the original and are left behind, and the copy starts
+    /// unmarked and young. Object values are copied as references, not deeply.
+    pub fn shallow_clone(&self) -> Arc<JSObject> {
+        let inner = self.inner.read();
+        let clone = JSObject::new(inner.obj_type);
+        {
+            let mut clone_inner = clone.inner.write();
+            let root = mem::replace(&mut clone_inner.shape, inner.shape.clone());
+            root.remove_reference();
+            clone_inner.shape.add_reference();
+            clone_inner.values = inner.values.clone();
+            clone_inner.overridden = inner.overridden.clone();
+            clone_inner.elements = inner.elements.clone();
+            // Each copy computes its own lazy values
+            clone_inner.lazy_properties = inner.lazy_properties.iter()
+                .map(|(key, lazy)| (key.clone(), LazyProperty::new(lazy.initializer)))
+                .collect();
+            clone_inner.prototype = inner.prototype.clone();
+            clone_inner.dictionary = inner.dictionary.clone();
+        }
+        clone
+    }
+    
+    /// Start an empty object on `shape`, typically one from `PropertyShape::with_defaults`
+    ///
+    /// Returns false if the object already has named properties, is in
+    /// dictionary mode or has a frozen shape.
+    pub fn adopt_shape(&self, shape: Arc<PropertyShape>) -> bool {
+        let mut inner = self.inner.write();
+        if inner.shape_frozen || inner.shape.property_count() > 0 || inner.dictionary.is_some() {
+            return false;
+        }
+        
+        let old_shape = mem::replace(&mut inner.shape, shape);
+        old_shape.remove_reference();
+        inner.shape.add_reference();
+        inner.overridden.clear();
+        
+        let shape_change = Self::shape_change(&inner, old_shape.id());
+        drop(inner);
+        self.notify_shape_change(shape_change);
+        true
+    }
+    
+    /// Move the object back onto the shared shape for its keys, if it has left it
+    ///
+    /// Renaming gives an object a shape of its own, so it no longer shares a
+    /// shape with objects that have the same keys. This switches it to
+    /// `PropertyShape::for_properties` of its keys in slot order, which keeps
+    /// every slot in place. Objects in dictionary mode that have shrunk below
+    /// `dictionary_mode_threshold` go back to the shared shape for their keys
+    /// in `PropertyDictionary::names_ordered`. Returns whether the shape
+    /// changed; frozen shapes and shapes with defaults are left alone. Major
+    /// collections call this for surviving objects, and `gc` logs each restore
+    /// at `LOG_DETAILED`.
+    pub fn try_restore_shape(&self, gc: &GarbageCollector) -> bool {
+        let restored = self.restore_shape();
+        if restored {
+            gc.log(GCConfiguration::LOG_DETAILED, || {
+                format!("Restored object {} to shared shape {}", self.id(), self.shape_id())
+            });
+        }
+        restored
+    }
+    
+    /// Switch to the shared shape for the object's keys, see `try_restore_shape`
+    fn restore_shape(&self) -> bool {
+        let mut inner = self.inner.write();
+        if inner.shape_frozen {
+            return false;
+        }
+        if inner.dictionary.is_some() {
+            return self.leave_dictionary_mode(inner);
+        }
+        if inner.shape.is_canonical() || inner.shape.has_defaults() {
+            return false;
+        }
+        
+        let old_shape = inner.shape.clone();
+        let names: Vec<&str> = old_shape.property_names().iter().map(|name| name.as_str()).collect();
+        let new_shape = PropertyShape::for_properties(&names);
+        if Arc::ptr_eq(&old_shape, &new_shape) {
+            return false;
+        }
+        
+        old_shape.remove_reference();
+        new_shape.add_reference();
+        inner.shape = new_shape;
+        
+        let shape_change = Self::shape_change(&inner, old_shape.id());
+        drop(inner);
+        self.notify_shape_change(shape_change);
+        true
+    }
+    
+    /// Move a dictionary-mode object's properties back into slots on a shared shape
+    fn leave_dictionary_mode(&self, mut inner: RwLockWriteGuard<'_, JSObjectInner>) -> bool {
+        let threshold = dictionary_mode_threshold();
+        let Some(dictionary) = inner.dictionary.as_mut() else {
+            return false;
+        };
+        if threshold > 0 && dictionary.len() >= threshold {
+            return false;
+        }
+        
+        let names = dictionary.names_ordered();
+        let values: Vec<JSValue> = names.iter()
+            .map(|name| dictionary.values.remove(name).unwrap_or_default())
+            .collect();
+        let keys: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+        let new_shape = PropertyShape::for_properties(&keys);
+        
+        let old_shape = mem::replace(&mut inner.shape, new_shape);
+        old_shape.remove_reference();
+        inner.shape.add_reference();
+        inner.values = values;
+        inner.overridden = Vec::new();
+        inner.dictionary = None;
+        
+        let shape_change = Self::shape_change(&inner, old_shape.id());
+        drop(inner);
+        self.notify_shape_change(shape_change);
+        true
+    }
+    
+    /// Set the object inherited properties are looked up on
+    ///
+    /// Does nothing if the object is frozen.
+    pub fn set_prototype(&self, prototype: Option<JSObjectHandle>) {
+        let _guard = PROTOTYPE_LOCK.lock();
+        self.store_prototype(prototype);
+    }
+    
+    /// Set the prototype unless doing so would make the chain cyclic
+    ///
+    /// Mirrors `Object.setPrototypeOf`, which rejects a prototype whose own chain
+    /// already leads back to this object. Returns whether the prototype was set,
+    /// which it never is on a frozen object.
+    pub fn try_set_prototype(&self, prototype: Option<JSObjectHandle>) -> bool {
+        // No other link can change between the check and the store
+        let _guard = PROTOTYPE_LOCK.lock();
+        if let Some(proto) = &prototype {
+            if proto.ptr.prototype_chain_contains(self) {
+                return false;
+            }
+        }
+        self.store_prototype(prototype)
+    }
+    
+    /// Store the prototype unless the object is frozen; callers hold `PROTOTYPE_LOCK`
+    fn store_prototype(&self, prototype: Option<JSObjectHandle>) -> bool {
+        let mut inner = self.inner.write();
+        if inner.frozen {
+            return false;
+        }
+        let barrier = match &prototype {
+            Some(proto) => self.write_barrier(&inner, &JSValue::Object(proto.clone())),
+            None => None,
+        };
+        inner.prototype = prototype;
+        
+        drop(inner);
+        if let Some(target) = barrier {
+            target.mark();
+        }
+        true
+    }
+    
+    /// Iterate over the prototype chain, nearest prototype first
+    ///
+    /// Each object is yielded at most once, so a cyclic chain ends where it
+    /// loops back.
+    fn prototype_chain(&self) -> PrototypeChain {
+        PrototypeChain {
+            visited: HashSet::from([self as *const JSObject]),
+            next: self.get_prototype(),
+        }
+    }
+    
+    /// Whether `target` is this object or appears anywhere on its prototype chain
+    fn prototype_chain_contains(&self, target: &JSObject) -> bool {
+        std::ptr::eq(self, target)
+            || self.prototype_chain().any(|proto| std::ptr::eq(Arc::as_ptr(&proto.ptr), target))
+    }
+    
+    /// Get the object inherited properties are looked up on
+    pub fn get_prototype(&self) -> Option<JSObjectHandle> {
+        self.inner.read().prototype.clone()
+    }
+    
+    /// Check whether the object itself has `key`, ignoring its prototype
+    pub fn has_own_property(&self, key: &str) -> bool {
+        let inner = self.inner.read();
+        
+        if let Some(index) = inner.element_index(key) {
+            return index < inner.elements.len();
+        }
+        
+        inner.has_named_property(key)
+            || inner.lazy_properties.iter().any(|(lazy_key, _)| lazy_key.as_str() == key)
+    }
+    
+    /// Check whether `key` is found on the object or anywhere on its prototype chain
+    ///
+    /// Each object's keys are looked up in its shape, dictionary, elements and
+    /// pending lazy properties without reading any value, so nothing is cloned
+    /// or initialized; prefer this over `get_property` for existence checks.
+    pub fn has_property(&self, key: &str) -> bool {
+        self.has_own_property(key) || self.prototype_chain().any(|proto| proto.ptr.has_own_property(key))
+    }
+    
+    /// Register a property whose value is computed on first read
+    ///
+    /// The first `get_property` for `key` calls `initializer` and stores the result
+    /// as an ordinary property, so later reads are plain lookups. Until then the key
+    /// doesn't appear in `entries` or `property_names`. Setting the key first
+    /// discards the initializer. Returns false, registering nothing, if the
+    /// object is frozen, or if its shape is frozen and `key` isn't already one
+    /// of its properties.
+    pub fn set_lazy_property(&self, key: &str, initializer: LazyInitializer) -> bool {
+        let mut inner = self.inner.write();
+        if inner.frozen || inner.shape_frozen && !inner.has_named_property(key) {
+            return false;
+        }
+        let key = InternedString::new(key);
+        inner.lazy_properties.retain(|(lazy_key, _)| *lazy_key != key);
+        inner.lazy_properties.push((key, LazyProperty::new(initializer)));
+        true
+    }
+    
+    /// Run a pending lazy initializer for `key` and store its value
+    ///
+    /// The entry stays registered until the value is stored, so the key keeps
+    /// reading as present meanwhile. A write or delete of the key that lands
+    /// first removes the entry, and then wins over the computed value.
+    fn materialize_lazy_property(&self, key: &str) -> JSValue {
+        let (initializer, cell) = {
+            let inner = self.inner.read();
+            match inner.lazy_properties.iter().find(|(lazy_key, _)| lazy_key.as_str() == key) {
+                Some((_, lazy)) => (lazy.initializer, lazy.value.clone()),
+                // Another reader stored it first
+                None => {
+                    drop(inner);
+                    return self.get_property(key);
+                }
+            }
+        };
+        
+        // Run it without the lock so it can read the object; the cell makes
+        // sure it only ever runs once
+        let value = cell.get_or_init(|| initializer(self as *const JSObject as *mut JSObject)).clone();
+        
+        let mut inner = self.inner.write();
+        let position = inner.lazy_properties.iter()
+            .position(|(lazy_key, lazy)| lazy_key.as_str() == key && Arc::ptr_eq(&lazy.value, &cell));
+        let Some(position) = position else {
+            drop(inner);
+            return self.get_property(key);
+        };
+        inner.lazy_properties.remove(position);
+        
+        let barrier = self.write_barrier(&inner, &value);
+        let old_shape_id = inner.shape.id();
+        Self::write_property(&mut inner, key, value.clone());
+        let shape_change = Self::shape_change(&inner, old_shape_id);
+        
+        drop(inner);
+        if let Some(target) = barrier {
+            target.mark();
+        }
+        self.notify_shape_change(shape_change);
+        value
+    }
+    
+    /// Get a property from this object
+    pub fn get_property(&self, key: &str) -> JSValue {
+        let inner = self.inner.read();
+        
+        if inner.lazy_properties.iter().any(|(lazy_key, _)| lazy_key.as_str() == key) {
+            drop(inner);
+            return self.materialize_lazy_property(key);
+        }
+        
+        // Array index keys are served from the element storage
+        if let Some(index) = inner.element_index(key) {
+            return inner.elements.get(index).unwrap_or_default();
+        }
+        
+        if let Some(dictionary) = &inner.dictionary {
+            return dictionary.get(key).cloned().unwrap_or_default();
+        }
+        
+        // Check if property exists in the current shape
+        if let Some(index) = inner.shape.get_property_index(key) {
+            inner.slot_value(index)
+        } else {
+            // Property not found
+            JSValue::Undefined
+        }
+    }
+    
+    /// Read a dotted path like `a.b.c`, one property per segment
+    ///
+    /// Returns `Undefined` if an intermediate segment is missing or doesn't hold
+    /// an object, or if `path` is empty.
+    pub fn get_path(&self, path: &[&str]) -> JSValue {
+        let Some((last, intermediates)) = path.split_last() else {
+            return JSValue::Undefined;
+        };
+        
+        let mut current: Option<JSObjectHandle> = None;
+        for key in intermediates {
+            let next = match &current {
+                Some(handle) => handle.ptr.get_object_property(key),
+                None => self.get_object_property(key),
+            };
+            match next {
+                Some(next) => current = Some(next),
+                None => return JSValue::Undefined,
+            }
+        }
+        
+        match &current {
+            Some(handle) => handle.ptr.get_property(last),
+            None => self.get_property(last),
+        }
+    }
+    
+    /// Write a dotted path like `a.b.c`, creating intermediate objects as needed
+    ///
+    /// Intermediate segments that are missing or hold a non-object are replaced
+    /// with fresh plain objects allocated from `gc`. Does nothing if `path` is empty.
+    pub fn set_path(&self, gc: &GarbageCollector, path: &[&str], value: JSValue) {
+        let Some((last, intermediates)) = path.split_last() else {
+            return;
+        };
+        
+        let mut current: Option<JSObjectHandle> = None;
+        for key in intermediates {
+            let parent = current.as_ref().map_or(self, |handle| &*handle.ptr);
+            let next = match parent.get_object_property(key) {
+                Some(next) => next,
+                None => {
+                    let created = gc.create_object(JSObjectType::Object);
+                    parent.set_property(key, JSValue::Object(created.clone()));
+                    created
+                }
+            };
+            current = Some(next);
+        }
+        
+        let target = current.as_ref().map_or(self, |handle| &*handle.ptr);
+        target.set_property(last, value);
+    }
+    
+    /// Get a property from this object or, failing that, its prototype chain
+    ///
+    /// The nearest object that has `key` wins, so own properties shadow inherited
+    /// ones. A cyclic chain ends the walk with `Undefined`.
+    pub fn get_property_with_proto(&self, key: &str) -> JSValue {
+        if self.has_own_property(key) {
+            return self.get_property(key);
+        }
+        match self.prototype_chain().find(|proto| proto.ptr.has_own_property(key)) {
+            Some(proto) => proto.ptr.get_property(key),
+            None => JSValue::Undefined,
+        }
+    }
+    
+    /// Get a property only if it holds an object
+    pub fn get_object_property(&self, key: &str) -> Option<JSObjectHandle> {
+        match self.get_property(key) {
+            JSValue::Object(handle) => Some(handle),
+            _ => None,
+        }
+    }
+    
+    /// Get a property only if it holds a number
+    pub fn get_number_property(&self, key: &str) -> Option<f64> {
+        match self.get_property(key) {
+            JSValue::Number(n) => Some(n),
+            JSValue::Integer(i) => Some(i as f64),
+            _ => None,
+        }
+    }
+    
+    /// Get a property only if it holds a string
+    pub fn get_string_property(&self, key: &str) -> Option<InternedString> {
+        match self.get_property(key) {
+            JSValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+    
+    /// Get a property only if it holds a boolean
+    pub fn get_bool_property(&self, key: &str) -> Option<bool> {
+        match self.get_property(key) {
+            JSValue::Boolean(b) => Some(b),
+            _ => None,
+        }
+    }
+    
+    /// Mark object for garbage collection
+    pub fn mark(&self) {
+        // Stop at objects that are already marked so cycles terminate
+        if !self.try_mark() {
+            return;
+        }
+        
+        // Mark any object properties recursively
+        for obj in self.referenced_objects() {
+            obj.mark();
+        }
+    }
+    
+    /// Mark only this object, returning whether it was previously unmarked
+    pub fn try_mark(&self) -> bool {
+        let mut inner = self.inner.write();
+        !std::mem::replace(&mut inner.marked, true)
+    }
+    
+    /// Get the objects directly referenced by this object's properties
+    pub fn referenced_objects(&self) -> Vec<Arc<JSObject>> {
+        let inner = self.inner.read();
+        inner.values.iter()
+            .chain(inner.dictionary.iter().flat_map(|dictionary| dictionary.iter().map(|(_, value)| value)))
+            .chain(inner.elements.boxed_values())
+            .filter_map(|value| match value {
+                JSValue::Object(obj) => Some(obj.ptr.clone()),
+                _ => None,
+            })
+            .chain(inner.prototype.as_ref().map(|proto| proto.ptr.clone()))
+            .collect()
+    }
+    
+    /// Unmark object after garbage collection
+    pub fn unmark(&self) {
+        let mut inner = self.inner.write();
+        inner.marked = false;
+    }
+    
+    /// Check if object is marked
+    pub fn is_marked(&self) -> bool {
+        let inner = self.inner.read();
+        inner.marked
+    }
+    
+    /// Get the number of young collections this object has survived
+    pub fn age(&self) -> u8 {
+        self.inner.read().age
+    }
+    
+    /// Record surviving a young collection, returning the new age
+    ///
+    /// The age saturates at `u8::MAX` rather than wrapping.
+    pub fn increment_age(&self) -> u8 {
+        let mut inner = self.inner.write();
+        inner.age = inner.age.saturating_add(1);
+        inner.age
+    }
+    
+    /// Whether this object has been promoted to the old generation
+    pub fn is_tenured(&self) -> bool {
+        self.tenured.load(Ordering::Relaxed)
+    }
+    
+    /// Record that this object now lives in the old generation
+    ///
+    /// An object promoted while it still references young objects joins the
+    /// remembered set, just as if those references had been stored after promotion.
+    pub(crate) fn set_tenured(&self) {
+        self.tenured.store(true, Ordering::Relaxed);
+        if self.referenced_objects().iter().any(|obj| !obj.is_tenured()) {
+            self.remembered.store(true, Ordering::Relaxed);
+        }
+    }
+    
+    /// Whether this object is in the remembered set
+    pub fn is_remembered(&self) -> bool {
+        self.remembered.load(Ordering::Relaxed)
+    }
+    
+    /// Take the write barrier hits recorded on this object since the last call
+    pub(crate) fn take_barrier_hits(&self) -> usize {
+        self.barrier_hits.swap(0, Ordering::Relaxed)
+    }
+    
+    /// Drop this object from the remembered set unless it still references a
+    /// young object, returning whether it stays
+    pub(crate) fn refresh_remembered(&self) -> bool {
+        if !self.is_remembered() {
+            return false;
+        }
+        let still_young = self.referenced_objects().iter().any(|obj| !obj.is_tenured());
+        self.remembered.store(still_young, Ordering::Relaxed);
+        still_young
+    }
+    
+    /// Get the number of indexed elements
+    pub fn array_length(&self) -> usize {
+        self.inner.read().elements.len()
+    }
+    
+    /// Get the element at `index`, or `Undefined` past the end
+    ///
+    /// Indices past the end of an array may have been stored as named
+    /// properties, see `set_element`, so those are looked up by key.
+    pub fn get_element(&self, index: usize) -> JSValue {
+        let inner = self.inner.read();
+        if let Some(value) = inner.elements.get(index) {
+            return value;
+        }
+        let is_array = inner.obj_type == JSObjectType::Array;
+        drop(inner);
+        if is_array {
+            self.get_property(&index.to_string())
+        } else {
+            JSValue::Undefined
+        }
+    }
+    
+    /// Set the element at `index`, growing the elements as needed
+    ///
+    /// Writing past the end fills the gap with `Undefined`, since elements are
+    /// stored without holes. Equivalent to setting the array index key with
+    /// `set_property`, but without parsing the key. Arrays of doubles stay
+    /// unboxed until some other value is stored, see `Elements`. An index far
+    /// past the end is stored as a named property instead, so a stray huge
+    /// index can't allocate a huge run of holes. Returns false, storing
+    /// nothing, if the object is frozen.
+    pub fn set_element(&self, index: usize, value: JSValue) -> bool {
+        let mut inner = self.inner.write();
+        if inner.frozen {
+            return false;
+        }
+        let barrier = self.write_barrier(&inner, &value);
+        let written = if inner.fits_elements(index) {
+            inner.elements.set(index, value);
+            true
+        } else {
+            Self::write_property(&mut inner, &index.to_string(), value)
+        };
+        
+        drop(inner);
+        if let Some(target) = barrier {
+            target.mark();
+        }
+        written
+    }
+    
+    /// Append an element, returning the new length
+    ///
+    /// Frozen objects are left as they are, and their current length returned.
+    pub fn array_push(&self, value: JSValue) -> usize {
+        let mut inner = self.inner.write();
+        if inner.frozen {
+            return inner.elements.len();
+        }
+        let barrier = self.write_barrier(&inner, &value);
+        inner.elements.push(value);
+        let length = inner.elements.len();
+        
+        drop(inner);
+        if let Some(target) = barrier {
+            target.mark();
+        }
+        length
+    }
+    
+    /// Remove and return the last element, or `Undefined` if there are none
+    ///
+    /// Frozen objects keep their elements and return `Undefined`.
+    pub fn array_pop(&self) -> JSValue {
+        let mut inner = self.inner.write();
+        if inner.frozen {
+            return JSValue::Undefined;
+        }
+        inner.elements.pop().unwrap_or_default()
+    }
+    
+    /// Remove and return the first element, or `Undefined` if there are none
+    ///
+    /// Every remaining element moves down one index, so this is O(n). Frozen
+    /// objects keep their elements and return `Undefined`.
+    pub fn array_shift(&self) -> JSValue {
+        let mut inner = self.inner.write();
+        if inner.frozen || inner.elements.is_empty() {
+            JSValue::Undefined
+        } else {
+            inner.elements.remove(0)
+        }
+    }
+    
+    /// Insert an element at the front, returning the new length
+    ///
+    /// Every existing element moves up one index, so this is O(n). Frozen
+    /// objects are left as they are, and their current length returned.
+    pub fn array_unshift(&self, value: JSValue) -> usize {
+        let mut inner = self.inner.write();
+        if inner.frozen {
+            return inner.elements.len();
+        }
+        let barrier = self.write_barrier(&inner, &value);
+        inner.elements.insert(0, value);
+        let length = inner.elements.len();
+        
+        drop(inner);
+        if let Some(target) = barrier {
+            target.mark();
+        }
+        length
+    }
+    
+    /// Sort array elements in place the way `Array.prototype.sort()` does
+    ///
+    /// Uses `JSValue::default_sort_cmp`, so elements are ordered by their string
+    /// form with `undefined` last, and the sort is stable. The elements are
+    /// sorted from a snapshot so stringifying nested arrays never runs under this
+    /// array's lock; if the elements changed while sorting, the sort starts over
+    /// from the new contents. Does nothing for non-arrays or frozen arrays.
+    pub fn array_sort(&self) {
+        loop {
+            let snapshot = {
+                let inner = self.inner.read();
+                if inner.obj_type != JSObjectType::Array || inner.frozen {
+                    return;
+                }
+                inner.elements.to_vec()
+            };
+            
+            let mut sorted = snapshot.clone();
+            sorted.sort_by(|a, b| a.default_sort_cmp(b));
+            
+            let mut inner = self.inner.write();
+            if inner.frozen {
+                return;
+            }
+            let unchanged = inner.elements.len() == snapshot.len()
+                && inner.elements.to_vec().iter().zip(&snapshot).all(|(current, old)| current.same_representation(old));
+            if unchanged {
+                inner.elements = Elements::from_values(sorted);
+                return;
+            }
+        }
+    }
+    
+    /// Set a finalizer to be called when object is collected
+    ///
+    /// It runs exactly once: during the sweep that frees the object, or on drop
+    /// for objects the collector never frees. If other garbage still
+    /// referenced the object, such as in a cycle, its object references have
+    /// been cleared by the time the finalizer runs.
+    pub fn set_finalizer(&self, finalizer: extern "C" fn(*mut JSObject)) {
+        let mut inner = self.inner.write();
+        inner.finalizer = Some(finalizer);
+    }
+    
+    /// Get the number of own properties, counting array elements
+    pub fn property_count(&self) -> usize {
+        let inner = self.inner.read();
+        let named = inner.dictionary.as_ref().map_or(inner.shape.property_count(), PropertyDictionary::len);
+        named + inner.elements.len()
+    }
+    
+    /// Get own property keys in the spec's `OrdinaryOwnPropertyKeys` order
+    ///
+    /// Integer keys come first in ascending order, followed by string keys in
+    /// insertion order. This is the order every enumeration API here follows.
+    pub fn own_keys_spec_order(&self) -> Vec<PropertyKey> {
+        let inner = self.inner.read();
+        let mut keys = Vec::with_capacity(inner.elements.len() + inner.values.len());
+        keys.extend((0..inner.elements.len()).map(PropertyKey::Index));
+        keys.extend(inner.named_properties_ordered().into_iter().map(PropertyKey::Name));
+        keys
+    }
+    
+    /// Get the key at `index` in `own_keys_spec_order` without listing every key
+    ///
+    /// Elements are indexed directly and the named keys' order is cached, so
+    /// walking all keys this way is linear. Returns `None` past the last key.
+    pub fn own_key_at(&self, index: usize) -> Option<PropertyKey> {
+        let inner = self.inner.read();
+        let element_count = inner.elements.len();
+        if index < element_count {
+            return Some(PropertyKey::Index(index));
+        }
+        
+        let named = index - element_count;
+        let name = match &inner.dictionary {
+            Some(dictionary) => dictionary.name_ordered_at(named),
+            None => inner.shape.property_name_ordered_at(named),
+        };
+        name.cloned().map(PropertyKey::Name)
+    }
+    
+    /// Get own key/value pairs in property order, as `Object.entries` sees them
+    ///
+    /// Keys follow `own_keys_spec_order`, with array elements keyed by index.
+    /// The snapshot is taken under a single read lock.
+    pub fn entries(&self) -> Vec<(PropertyKey, JSValue)> {
+        let inner = self.inner.read();
+        let mut entries = Vec::with_capacity(inner.elements.len() + inner.values.len());
+        
+        for (index, value) in inner.elements.to_vec().into_iter().enumerate() {
+            entries.push((PropertyKey::Index(index), value));
+        }
+        
+        if let Some(dictionary) = &inner.dictionary {
+            for key in dictionary.names_ordered() {
+                let value = dictionary.get(&key).cloned().unwrap_or_default();
+                entries.push((PropertyKey::Name(key), value));
+            }
+            return entries;
+        }
+        
+        for key in inner.shape.property_names_ordered() {
+            let value = inner.shape.get_property_index(&key)
+                .map(|slot| inner.slot_value(slot))
+                .unwrap_or_default();
+            entries.push((PropertyKey::Name(key), value));
+        }
+        entries
+    }
+    
+    /// Get own property values in property order, as `Object.values` sees them
+    pub fn values(&self) -> Vec<JSValue> {
+        self.entries().into_iter().map(|(_, value)| value).collect()
+    }
+    
+    /// Compare own properties slot by slot when both objects share a shape
+    ///
+    /// Returns `None` when the shapes differ, when either object is in dictionary
+    /// mode or still has pending lazy properties, and the caller should fall back to a general
+    /// comparison. Otherwise values (and array elements) are compared with
+    /// `strict_equals`, so nested objects must be the same reference.
+    pub fn shallow_equals_same_shape(&self, other: &JSObject) -> Option<bool> {
+        let inner = self.inner.read();
+        // Don't take a second read lock on the same object
+        let other_guard;
+        let other_inner = if std::ptr::eq(self, other) {
+            &*inner
+        } else {
+            other_guard = other.inner.read();
+            &*other_guard
+        };
+        
+        if !Arc::ptr_eq(&inner.shape, &other_inner.shape)
+            || inner.dictionary.is_some()
+            || other_inner.dictionary.is_some()
+            || !inner.lazy_properties.is_empty()
+            || !other_inner.lazy_properties.is_empty()
+        {
+            return None;
+        }
+        
+        let same_properties = (0..inner.shape.property_count())
+            .all(|slot| inner.slot_value(slot).strict_equals(&other_inner.slot_value(slot)));
+        let same_elements = match (&inner.elements, &other_inner.elements) {
+            // f64 equality is already ===: NaN differs from itself and -0 equals 0
+            (Elements::PackedDoubles(a), Elements::PackedDoubles(b)) => a == b,
+            (Elements::Boxed(a), Elements::Boxed(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.strict_equals(b))
+            }
+            (Elements::PackedDoubles(doubles), Elements::Boxed(values))
+            | (Elements::Boxed(values), Elements::PackedDoubles(doubles)) => {
+                doubles.len() == values.len()
+                    && doubles.iter().zip(values).all(|(&n, value)| JSValue::Number(n).strict_equals(value))
+            }
+        };
+        Some(same_properties && same_elements)
+    }
+    
+    /// Drop every object reference this object holds, breaking cycles through it
+    ///
+    /// Object-valued properties and elements become `undefined` and the prototype
+    /// is cleared; other values are left for the finalizer to see.
+    pub(crate) fn clear_references(&self) {
+        let (released, prototype) = {
+            let mut guard = self.inner.write();
+            let inner = &mut *guard;
+            let mut released = Vec::new();
+            for value in inner.values.iter_mut() {
+                if matches!(value, JSValue::Object(_)) {
+                    released.push(mem::take(value));
+                }
+            }
+            if let Some(dictionary) = inner.dictionary.as_mut() {
+                dictionary.take_objects(&mut released);
+            }
+            inner.elements.take_objects(&mut released);
+            (released, inner.prototype.take())
+        };
+        
+        // Referents may be freed here; drop them without holding our lock
+        drop(released);
+        drop(prototype);
+    }
+    
+    /// Whether a finalizer is set on this object
+    pub fn has_finalizer(&self) -> bool {
+        self.inner.read().finalizer.is_some()
+    }
+    
+    /// Whether the object has been finalized, whether or not it had a finalizer
+    pub fn is_finalized(&self) -> bool {
+        self.finalized.load(Ordering::Acquire)
+    }
+    
+    /// Run the finalizer unless the object was already finalized
+    ///
+    /// Sweeps call this for objects they free, so finalization happens during
+    /// the collection that found the object dead rather than whenever its last
+    /// reference goes away. `Drop` calls it too, for objects no sweep saw.
+    /// Returns whether a finalizer ran.
+    pub(crate) fn finalize(&self) -> bool {
+        if self.finalized.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        let finalizer = self.inner.read().finalizer;
+        match finalizer {
+            Some(finalizer) => {
+                // Safety: We're passing a raw pointer to the finalizer
+                finalizer(self as *const JSObject as *mut JSObject);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// Get all named property names in this object, in enumeration order
+    ///
+    /// Names come from the shape's cache, or the dictionary in dictionary mode,
+    /// so this only bumps reference counts.
+    /// Array elements aren't included; `own_keys_spec_order` lists them too.
+    pub fn property_names(&self) -> Vec<InternedString> {
+        self.inner.read().named_properties_ordered()
+    }
+}
+
+// Nesting depth past which `Display` summarizes objects instead of listing them
+const DISPLAY_MAX_DEPTH: usize = 4;
+
+/// Renders the object for logs, like Node's `util.inspect`
+///
+/// For example `{ name: "John", value: 42 }` or `[ 1, 2 ]`. Objects nested
+/// deeper than a few levels print as `[Object]` or `[Array]`, and references
+/// back to an enclosing object as `[Circular]`. Pending lazy properties aren't
+/// materialized.
+impl fmt::Display for JSObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        self.write_inspect(&mut out, 0, &mut Vec::new());
+        f.write_str(&out)
+    }
+}
+
+impl JSObject {
+    fn write_inspect(&self, out: &mut String, depth: usize, ancestors: &mut Vec<*const JSObject>) {
+        // Snapshot the contents so no lock is held while recursing
+        let obj_type = self.inner.read().obj_type;
+        let (open, close, summary, entries) = match obj_type {
+            JSObjectType::Function => return out.push_str("[Function]"),
+            JSObjectType::Array => {
+                let elements = self.inner.read().elements.to_vec();
+                ('[', ']', "[Array]", elements.into_iter().map(|value| (None, value)).collect::<Vec<_>>())
+            }
+            _ => {
+                let entries = self.entries();
+                ('{', '}', "[Object]", entries.into_iter().map(|(key, value)| (Some(key), value)).collect())
+            }
+        };
+        
+        if entries.is_empty() {
+            out.push(open);
+            out.push(close);
+            return;
+        }
+        if depth >= DISPLAY_MAX_DEPTH {
+            return out.push_str(summary);
+        }
+        
+        ancestors.push(self as *const JSObject);
+        out.push(open);
+        for (i, (key, value)) in entries.iter().enumerate() {
+            out.push_str(if i > 0 { ", " } else { " " });
+            if let Some(key) = key {
+                let key = key.to_string();
+                if is_identifier(&key) {
+                    out.push_str(&key);
+                } else {
+                    out.push_str(&format!("{:?}", key));
+                }
+                out.push_str(": ");
+            }
+            match value {
+                JSValue::Object(handle) if ancestors.contains(&Arc::as_ptr(&handle.ptr)) => {
+                    out.push_str("[Circular]");
+                }
+                JSValue::Object(handle) => handle.ptr.write_inspect(out, depth + 1, ancestors),
+                primitive => primitive.write_pretty(out, 0, 0, &mut Vec::new()),
+            }
+        }
+        out.push(' ');
+        out.push(close);
+        ancestors.pop();
+    }
+}
+
+/// Whether `key` can be written unquoted as a property name
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+impl Drop for JSObject {
+    fn drop(&mut self) {
+        // Call the finalizer if set and no sweep has run it yet
+        self.finalize();
+        
+        #[cfg(feature = "handle-validation")]
+        LIVE_OBJECTS.lock().remove(&(self as *const JSObject as usize));
+    }
+}
+
+/// Iterator returned by `JSObject::prototype_chain`
+struct PrototypeChain {
+    visited: HashSet<*const JSObject>,
+    next: Option<JSObjectHandle>,
+}
+
+impl Iterator for PrototypeChain {
+    type Item = JSObjectHandle;
+    
+    fn next(&mut self) -> Option<JSObjectHandle> {
+        let proto = self.next.take()?;
+        if !self.visited.insert(Arc::as_ptr(&proto.ptr)) {
+            return None;
+        }
+        self.next = proto.ptr.get_prototype();
+        Some(proto)
+    }
+}
+
+/// Safe handle to a JavaScript object
+#[derive(Clone)]
+pub struct JSObjectHandle {
+    pub ptr: Arc<JSObject>,
+}
+
+impl JSObjectHandle {
+    /// Create a handle from a raw pointer
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    pub fn from_raw(raw: *mut JSObject) -> Option<Self> {
+        if raw.is_null() {
+            None
+        } else {
+            // Safety: Convert raw pointer back to Arc
+            unsafe {
+                let arc = Arc::from_raw(raw);
+                let ptr = arc.clone();
+                // Don't drop the original Arc when this function returns
+                std::mem::forget(arc);
+                Some(Self { ptr })
+            }
+        }
+    }
+    
+    /// Create a weak handle that doesn't keep the object alive
+    pub fn downgrade(&self) -> WeakJSObjectHandle {
+        WeakJSObjectHandle { ptr: Arc::downgrade(&self.ptr) }
+    }
+}
+
+/// A `WeakRef`: holds an object without keeping it alive
+///
+/// Marking only follows strong `JSValue::Object` references, so an object
+/// reachable only through weak handles is collected normally.
+pub type JSWeakRef = WeakJSObjectHandle;
+
+/// Handle to a JavaScript object that doesn't keep it alive
+#[derive(Clone)]
+pub struct WeakJSObjectHandle {
+    ptr: Weak<JSObject>,
+}
+
+impl WeakJSObjectHandle {
+    /// Get a strong handle, or `None` if the object has been freed
+    pub fn upgrade(&self) -> Option<JSObjectHandle> {
+        self.ptr.upgrade().map(|ptr| JSObjectHandle { ptr })
+    }
+}
+
+impl fmt::Debug for JSObjectHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.ptr.inner.read();
+        write!(f, "JSObject({:?})", inner.obj_type)
+    }
 }
\ No newline at end of file
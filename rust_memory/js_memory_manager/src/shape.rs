@@ -1,126 +1,428 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Weak};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use parking_lot::RwLock;
-use crate::string_interner::InternedString;
-
-/// A PropertyShape represents the structure of an object's properties
-/// It contains the property names and their corresponding index in the values vector
-#[derive(Debug)]
-pub struct PropertyShape {
-    // Unique identifier for this shape
-    id: usize,
-    // Maps property names to indices in the values array
-    // Using InternedString for optimized storage and comparison
-    property_map: HashMap<InternedString, usize>,
-    // Reference to the parent shape (for shape transitions)
-    parent: Option<Weak<PropertyShape>>,
-    // Property added in this shape (compared to parent)
-    added_property: Option<InternedString>,
-    // Cache of transitions to other shapes
-    transitions: RwLock<HashMap<InternedString, Weak<PropertyShape>>>,
-    // Number of objects using this shape (for statistics)
-    ref_count: AtomicUsize,
-}
-
-impl PropertyShape {
-    /// Create a new empty property shape (root shape)
-    pub fn new_empty() -> Arc<Self> {
-        static NEXT_SHAPE_ID: AtomicUsize = AtomicUsize::new(0);
-        
-        Arc::new(Self {
-            id: NEXT_SHAPE_ID.fetch_add(1, Ordering::SeqCst),
-            property_map: HashMap::new(),
-            parent: None,
-            added_property: None,
-            transitions: RwLock::new(HashMap::new()),
-            ref_count: AtomicUsize::new(0),
-        })
-    }
-    
-    /// Get the index of a property in the values array
-    pub fn get_property_index(&self, name: &str) -> Option<usize> {
-        // Create a temporary interned string for lookup only
-        let interned_name = InternedString::new(name);
-        self.property_map.get(&interned_name).copied()
-    }
-    
-    /// Get a transition shape by adding a new property
-    pub fn transition_to(&self, property: &str) -> Arc<PropertyShape> {
-        // Intern the property name for efficient storage and comparison
-        let interned_property = InternedString::new(property);
-        
-        // First check if we already have this transition
-        {
-            let transitions = self.transitions.read();
-            if let Some(weak_shape) = transitions.get(&interned_property) {
-                if let Some(shape) = weak_shape.upgrade() {
-                    return shape;
-                }
-            }
-        }
-        
-        // Create new shape as a transition from this one
-        let next_index = self.property_map.len();
-        let mut new_map = self.property_map.clone();
-        new_map.insert(interned_property.clone(), next_index);
-        
-        let self_arc = match &self.parent {
-            Some(parent_weak) => {
-                if let Some(parent) = parent_weak.upgrade() {
-                    // Try to get grandparent's strong reference
-                    parent
-                } else {
-                    // Fall back to empty shape if parent is gone
-                    PropertyShape::new_empty()
-                }
-            },
-            None => PropertyShape::new_empty(),
-        };
-        
-        static NEXT_SHAPE_ID: AtomicUsize = AtomicUsize::new(0);
-        
-        // Create the new shape
-        let new_shape = Arc::new(PropertyShape {
-            id: NEXT_SHAPE_ID.fetch_add(1, Ordering::SeqCst),
-            property_map: new_map,
-            parent: Some(Arc::downgrade(&self_arc)),
-            added_property: Some(interned_property.clone()),
-            transitions: RwLock::new(HashMap::new()),
-            ref_count: AtomicUsize::new(0),
-        });
-        
-        // Cache this transition
-        let mut transitions = self.transitions.write();
-        transitions.insert(interned_property, Arc::downgrade(&new_shape));
-        
-        new_shape
-    }
-    
-    /// Get the number of properties in this shape
-    pub fn property_count(&self) -> usize {
-        self.property_map.len()
-    }
-    
-    /// Increment the reference count when an object adopts this shape
-    pub fn add_reference(&self) {
-        self.ref_count.fetch_add(1, Ordering::SeqCst);
-    }
-    
-    /// Decrement the reference count when an object no longer uses this shape
-    pub fn remove_reference(&self) {
-        self.ref_count.fetch_sub(1, Ordering::SeqCst);
-    }
-    
-    /// Get all property names in this shape
-    pub fn property_names(&self) -> Vec<String> {
-        self.property_map.keys()
-            .map(|interned| interned.as_str().to_string())
-            .collect()
-    }
-    
-    /// Get a map of property names to their indices
-    pub fn get_property_map(&self) -> &HashMap<InternedString, usize> {
-        &self.property_map
-    }
-}
\ No newline at end of file
+use std::collections::HashMap;
+use std::mem;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use parking_lot::RwLock;
+#[cfg(feature = "shape-type-profile")]
+use parking_lot::Mutex;
+use crate::string_interner::InternedString;
+use crate::object::{array_index, JSValue};
+#[cfg(feature = "shape-type-profile")]
+use crate::object::JSValueTag;
+use once_cell::sync::{Lazy, OnceCell};
+
+// Shape ids are shared between root and transition shapes so they never collide
+static NEXT_SHAPE_ID: AtomicUsize = AtomicUsize::new(0);
+
+// Shapes currently allocated, counted up in `next_shape_id` and down on drop
+static LIVE_SHAPES: AtomicUsize = AtomicUsize::new(0);
+
+// Transition lookups that found a cached shape, and those that had to build one
+static TRANSITION_HITS: AtomicU64 = AtomicU64::new(0);
+static TRANSITION_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// Counts describing how well shapes are being shared, as returned by `get_shape_stats`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShapeStats {
+    /// Shapes currently allocated, including the root shape
+    pub live_shapes: usize,
+    /// `transition_to` calls answered from the transition cache
+    pub transition_hits: u64,
+    /// `transition_to` calls that created a new shape
+    pub transition_misses: u64,
+}
+
+/// Get process-wide shape counts
+///
+/// Many misses relative to hits usually means objects of the same kind add
+/// their properties in different orders, so each order gets its own shapes.
+pub fn get_shape_stats() -> ShapeStats {
+    ShapeStats {
+        live_shapes: LIVE_SHAPES.load(Ordering::Relaxed),
+        transition_hits: TRANSITION_HITS.load(Ordering::Relaxed),
+        transition_misses: TRANSITION_MISSES.load(Ordering::Relaxed),
+    }
+}
+
+/// Hand out the id of a shape being created
+fn next_shape_id() -> usize {
+    LIVE_SHAPES.fetch_add(1, Ordering::Relaxed);
+    NEXT_SHAPE_ID.fetch_add(1, Ordering::SeqCst)
+}
+
+// Root shape shared by every newly created object so that transitions are shared
+static ROOT_SHAPE: Lazy<Arc<PropertyShape>> = Lazy::new(PropertyShape::new_empty);
+
+/// A PropertyShape represents the structure of an object's properties
+/// It contains the property names and their corresponding index in the values vector
+#[derive(Debug)]
+pub struct PropertyShape {
+    // Unique identifier for this shape
+    id: usize,
+    // Maps property names to indices in the values array
+    // Using InternedString for optimized storage and comparison
+    property_map: HashMap<InternedString, usize>,
+    // Reference to the parent shape (for shape transitions)
+    // Held strongly so the transition chain outlives intermediate shapes
+    parent: Option<Arc<PropertyShape>>,
+    // Property added in this shape (compared to parent)
+    added_property: Option<InternedString>,
+    // Cache of transitions to other shapes
+    transitions: RwLock<HashMap<InternedString, Weak<PropertyShape>>>,
+    // Cache of sibling shapes with one property renamed, keyed by (from, to)
+    renames: RwLock<HashMap<(InternedString, InternedString), Weak<PropertyShape>>>,
+    // Property names in slot order, built on first request; shapes never change
+    names: OnceCell<Vec<InternedString>>,
+    // Property names in enumeration order, likewise built on first request
+    names_ordered: OnceCell<Vec<InternedString>>,
+    // Number of objects using this shape (for statistics)
+    ref_count: AtomicUsize,
+    // Whether this shape is reached from the root by plain transitions, i.e.
+    // it's the shape `for_properties` returns for its keys
+    canonical: bool,
+    // Values read from leading slots an object hasn't written, shared by every
+    // shape transitioned from a `with_defaults` shape
+    defaults: Option<Arc<[JSValue]>>,
+    // Per slot, how many writes stored each value type
+    #[cfg(feature = "shape-type-profile")]
+    slot_types: Mutex<HashMap<usize, HashMap<&'static str, u64>>>,
+}
+
+impl PropertyShape {
+    /// Create a new empty property shape (root shape)
+    pub fn new_empty() -> Arc<Self> {
+        Arc::new(Self {
+            id: next_shape_id(),
+            property_map: HashMap::new(),
+            parent: None,
+            added_property: None,
+            transitions: RwLock::new(HashMap::new()),
+            renames: RwLock::new(HashMap::new()),
+            names: OnceCell::new(),
+            names_ordered: OnceCell::new(),
+            ref_count: AtomicUsize::new(0),
+            canonical: true,
+            defaults: None,
+            #[cfg(feature = "shape-type-profile")]
+            slot_types: Mutex::new(HashMap::new()),
+        })
+    }
+    
+    /// Get the shared root shape that new objects start from
+    pub fn root() -> Arc<Self> {
+        ROOT_SHAPE.clone()
+    }
+    
+    /// Get the unique identifier of this shape
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    
+    /// Get the shared shape holding `names` in that slot order
+    ///
+    /// This is the shape an object gets by adding the properties one at a time
+    /// to an empty object, so every caller with the same names gets the same shape.
+    pub fn for_properties(names: &[&str]) -> Arc<Self> {
+        names.iter().fold(Self::root(), |shape, name| shape.transition_to(name))
+    }
+    
+    /// Get a shape holding `properties` in that slot order, with their values as defaults
+    ///
+    /// Objects adopting the shape with `JSObject::adopt_shape` read a slot's
+    /// default until they write that slot, so many instances mostly at their
+    /// defaults don't each store them. Every call creates a separate shape,
+    /// which isn't canonical. Defaults aren't traced by the collector, so
+    /// returns `None` if one is an object, or if a name repeats.
+    pub fn with_defaults(properties: &[(&str, JSValue)]) -> Option<Arc<Self>> {
+        let names: Vec<&str> = properties.iter().map(|(name, _)| *name).collect();
+        let keys = Self::for_properties(&names);
+        if keys.property_count() != properties.len()
+            || properties.iter().any(|(_, value)| matches!(value, JSValue::Object(_)))
+        {
+            return None;
+        }
+        
+        Some(Arc::new(PropertyShape {
+            id: next_shape_id(),
+            property_map: keys.property_map.clone(),
+            parent: keys.parent.clone(),
+            added_property: keys.added_property.clone(),
+            transitions: RwLock::new(HashMap::new()),
+            renames: RwLock::new(HashMap::new()),
+            names: OnceCell::new(),
+            names_ordered: OnceCell::new(),
+            ref_count: AtomicUsize::new(0),
+            canonical: false,
+            defaults: Some(properties.iter().map(|(_, value)| value.clone()).collect()),
+            #[cfg(feature = "shape-type-profile")]
+            slot_types: Mutex::new(HashMap::new()),
+        }))
+    }
+    
+    /// Get the default value of a slot, if this shape carries one
+    pub fn default_value(&self, slot: usize) -> Option<&JSValue> {
+        self.defaults.as_ref()?.get(slot)
+    }
+    
+    /// Whether this shape carries default values, see `with_defaults`
+    pub fn has_defaults(&self) -> bool {
+        self.defaults.is_some()
+    }
+    
+    /// Whether this is the shape `for_properties` returns for its own keys
+    ///
+    /// Shapes produced by `rename_property` or `with_defaults`, and transitions
+    /// from them, aren't; objects with equal keys may then have different shapes.
+    pub fn is_canonical(&self) -> bool {
+        self.canonical
+    }
+    
+    /// Get the shape this one transitioned from, if any
+    pub fn parent(&self) -> Option<&Arc<PropertyShape>> {
+        self.parent.as_ref()
+    }
+    
+    /// Get the property added by this shape relative to its parent
+    pub fn added_property(&self) -> Option<&InternedString> {
+        self.added_property.as_ref()
+    }
+    
+    /// Get the index of a property in the values array
+    ///
+    /// Looks the name up by content, so reading a missing key never interns it.
+    pub fn get_property_index(&self, name: &str) -> Option<usize> {
+        self.property_map.get(name).copied()
+    }
+    
+    /// Get a transition shape by adding a new property
+    pub fn transition_to(self: &Arc<Self>, property: &str) -> Arc<PropertyShape> {
+        // First check if we already have this transition
+        {
+            let transitions = self.transitions.read();
+            if let Some(weak_shape) = transitions.get(property) {
+                if let Some(shape) = weak_shape.upgrade() {
+                    TRANSITION_HITS.fetch_add(1, Ordering::Relaxed);
+                    return shape;
+                }
+            }
+        }
+        TRANSITION_MISSES.fetch_add(1, Ordering::Relaxed);
+        
+        // Intern the property name for efficient storage and comparison
+        let interned_property = InternedString::new(property);
+        
+        // Create new shape as a transition from this one
+        let next_index = self.property_map.len();
+        let mut new_map = self.property_map.clone();
+        new_map.insert(interned_property.clone(), next_index);
+        
+        // Create the new shape
+        let new_shape = Arc::new(PropertyShape {
+            id: next_shape_id(),
+            property_map: new_map,
+            parent: Some(Arc::clone(self)),
+            added_property: Some(interned_property.clone()),
+            transitions: RwLock::new(HashMap::new()),
+            renames: RwLock::new(HashMap::new()),
+            names: OnceCell::new(),
+            names_ordered: OnceCell::new(),
+            ref_count: AtomicUsize::new(0),
+            canonical: self.canonical,
+            defaults: self.defaults.clone(),
+            #[cfg(feature = "shape-type-profile")]
+            slot_types: Mutex::new(HashMap::new()),
+        });
+        
+        // Cache this transition
+        let mut transitions = self.transitions.write();
+        transitions.insert(interned_property, Arc::downgrade(&new_shape));
+        
+        new_shape
+    }
+    
+    /// Get the shape that is identical to this one except `from` is called `to`
+    ///
+    /// The renamed property keeps its slot, so an object can switch to the result
+    /// without moving any values. The result is a sibling of this shape (same parent)
+    /// and is cached by the rename pair, so objects renamed the same way share it.
+    /// Returns `None` if `from` isn't in this shape or `to` already is.
+    pub fn rename_property(self: &Arc<Self>, from: &str, to: &str) -> Option<Arc<PropertyShape>> {
+        let index = self.get_property_index(from)?;
+        if self.get_property_index(to).is_some() {
+            return None;
+        }
+        
+        let from = InternedString::new(from);
+        let to = InternedString::new(to);
+        let key = (from.clone(), to.clone());
+        
+        if let Some(shape) = self.renames.read().get(&key).and_then(Weak::upgrade) {
+            return Some(shape);
+        }
+        
+        let mut new_map = self.property_map.clone();
+        new_map.remove(&from);
+        new_map.insert(to.clone(), index);
+        
+        let added_property = match &self.added_property {
+            Some(added) if *added == from => Some(to),
+            other => other.clone(),
+        };
+        
+        let new_shape = Arc::new(PropertyShape {
+            id: next_shape_id(),
+            property_map: new_map,
+            parent: self.parent.clone(),
+            added_property,
+            transitions: RwLock::new(HashMap::new()),
+            renames: RwLock::new(HashMap::new()),
+            names: OnceCell::new(),
+            names_ordered: OnceCell::new(),
+            ref_count: AtomicUsize::new(0),
+            canonical: false,
+            defaults: self.defaults.clone(),
+            #[cfg(feature = "shape-type-profile")]
+            slot_types: Mutex::new(HashMap::new()),
+        });
+        
+        self.renames.write().insert(key, Arc::downgrade(&new_shape));
+        Some(new_shape)
+    }
+    
+    /// Find the deepest shape both this shape and `other` transitioned through
+    ///
+    /// The properties added after the common ancestor are what an object would
+    /// have to drop and re-add to move between the two layouts. Shapes from
+    /// unrelated roots have no common ancestor.
+    pub fn common_ancestor(&self, other: &Arc<PropertyShape>) -> Option<Arc<PropertyShape>> {
+        // Each transition adds one property, so property count is the depth in the tree.
+        // `None` stands for `self`, which we can only hand out once we reach its parent.
+        let mut mine: Option<Arc<PropertyShape>> = None;
+        let mut theirs = Arc::clone(other);
+        
+        loop {
+            let current = mine.as_deref().unwrap_or(self);
+            if std::ptr::eq(current, Arc::as_ptr(&theirs)) {
+                return Some(theirs);
+            }
+            
+            // Step the deeper chain up; at equal depth both are stepped in turn
+            if current.property_count() >= theirs.property_count() {
+                mine = Some(current.parent.clone()?);
+            } else {
+                theirs = theirs.parent.clone()?;
+            }
+        }
+    }
+    
+    /// Get the number of properties in this shape
+    pub fn property_count(&self) -> usize {
+        self.property_map.len()
+    }
+    
+    /// Increment the reference count when an object adopts this shape
+    pub fn add_reference(&self) {
+        self.ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+    
+    /// Decrement the reference count when an object no longer uses this shape
+    pub fn remove_reference(&self) {
+        self.ref_count.fetch_sub(1, Ordering::SeqCst);
+    }
+    
+    /// Get the number of objects using this shape
+    pub fn reference_count(&self) -> usize {
+        self.ref_count.load(Ordering::SeqCst)
+    }
+    
+    /// Estimate the bytes this shape holds on its own
+    ///
+    /// Each shape keeps a full copy of its property map. Names are interned, so
+    /// only their handles count here.
+    pub fn size_bytes(&self) -> usize {
+        let names = self.names.get().map_or(0, |names| names.capacity())
+            + self.names_ordered.get().map_or(0, |names| names.capacity());
+        mem::size_of::<Self>()
+            + self.property_map.capacity() * mem::size_of::<(InternedString, usize)>()
+            + names * mem::size_of::<InternedString>()
+    }
+    
+    /// Get all property names in this shape, in slot (insertion) order
+    ///
+    /// Computed on first use and cached for the shape's lifetime.
+    pub fn property_names(&self) -> &[InternedString] {
+        self.names.get_or_init(|| {
+            let mut names: Vec<(&InternedString, usize)> = self.property_map.iter()
+                .map(|(name, &slot)| (name, slot))
+                .collect();
+            names.sort_unstable_by_key(|&(_, slot)| slot);
+            names.into_iter().map(|(name, _)| name.clone()).collect()
+        })
+    }
+    
+    /// Get all property names in enumeration order
+    ///
+    /// That is integer keys ascending, then the rest in insertion order. Slots
+    /// are assigned in insertion order and renames keep their slot, so this is
+    /// taken from the slot order rather than the transition chain, which still
+    /// holds the old name of a renamed property.
+    pub fn property_names_ordered(&self) -> Vec<InternedString> {
+        self.names_in_enumeration_order().to_vec()
+    }
+    
+    /// Get the name at `index` in `property_names_ordered`, without copying the list
+    pub fn property_name_ordered_at(&self, index: usize) -> Option<&InternedString> {
+        self.names_in_enumeration_order().get(index)
+    }
+    
+    fn names_in_enumeration_order(&self) -> &[InternedString] {
+        self.names_ordered.get_or_init(|| {
+            let mut names = self.property_names().to_vec();
+            // The sort is stable, so non-integer keys keep their relative order
+            names.sort_by_key(|name| match array_index(name) {
+                Some(index) => (0, index),
+                None => (1, 0),
+            });
+            names
+        })
+    }
+    
+    /// Get a map of property names to their indices
+    pub fn get_property_map(&self) -> &HashMap<InternedString, usize> {
+        &self.property_map
+    }
+    
+    /// Count a write of a value of type `tag` to `slot` of an object with this shape
+    #[cfg(feature = "shape-type-profile")]
+    pub(crate) fn record_slot_type(&self, slot: usize, tag: JSValueTag) {
+        *self.slot_types.lock()
+            .entry(slot)
+            .or_default()
+            .entry(tag.name())
+            .or_insert(0) += 1;
+    }
+    
+    /// Get the value types written to each slot across all objects with this shape
+    ///
+    /// Returns, sorted by slot, the number of writes per type name (as given by
+    /// `JSValueTag::name`). A slot that only ever saw `"number"` is a candidate
+    /// for an unboxed layout. Writes are counted on the shape the object had at
+    /// the time, so objects that reach this shape by adding properties one at a
+    /// time only contribute their later writes; `reserve_properties` avoids that.
+    /// Slots never written with this shape are omitted.
+    #[cfg(feature = "shape-type-profile")]
+    pub fn slot_type_profile(&self) -> Vec<(usize, HashMap<&'static str, u64>)> {
+        let mut profile: Vec<_> = self.slot_types.lock().iter()
+            .map(|(&slot, counts)| (slot, counts.clone()))
+            .collect();
+        profile.sort_unstable_by_key(|&(slot, _)| slot);
+        profile
+    }
+}
+
+impl Drop for PropertyShape {
+    fn drop(&mut self) {
+        LIVE_SHAPES.fetch_sub(1, Ordering::Relaxed);
+    }
+}
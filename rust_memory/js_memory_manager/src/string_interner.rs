@@ -1,160 +1,485 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::fmt;
-use std::hash::{Hash, Hasher};
-use std::ops::Deref;
-use std::borrow::Borrow;
-use lazy_static::lazy_static;
-
-/// A JavaScript string that's been interned for deduplication
-#[derive(Clone)]
-pub struct InternedString {
-    // Arc allows shared ownership of the string data
-    inner: Arc<String>,
-}
-
-impl InternedString {
-    /// Create a new interned string
-    pub fn new(s: &str) -> Self {
-        STRING_INTERNER.with(|interner| interner.intern(s))
-    }
-    
-    /// Get the underlying string as a str slice
-    pub fn as_str(&self) -> &str {
-        &self.inner
-    }
-}
-
-// Custom implementations for InternedString
-
-impl PartialEq for InternedString {
-    fn eq(&self, other: &Self) -> bool {
-        // Since interned strings are deduplicated, 
-        // we can compare their Arc pointers directly
-        Arc::ptr_eq(&self.inner, &other.inner)
-    }
-}
-
-impl Eq for InternedString {}
-
-impl Hash for InternedString {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        // Use the address of the string as the hash
-        Arc::as_ptr(&self.inner).hash(state);
-    }
-}
-
-impl fmt::Debug for InternedString {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Debug::fmt(&**self.inner, f)
-    }
-}
-
-impl fmt::Display for InternedString {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Display::fmt(&**self.inner, f)
-    }
-}
-
-impl Deref for InternedString {
-    type Target = str;
-    
-    fn deref(&self) -> &Self::Target {
-        &self.inner
-    }
-}
-
-impl Borrow<str> for InternedString {
-    fn borrow(&self) -> &str {
-        &self.inner
-    }
-}
-
-impl AsRef<str> for InternedString {
-    fn as_ref(&self) -> &str {
-        &self.inner
-    }
-}
-
-impl From<&str> for InternedString {
-    fn from(s: &str) -> Self {
-        InternedString::new(s)
-    }
-}
-
-impl From<String> for InternedString {
-    fn from(s: String) -> Self {
-        InternedString::new(&s)
-    }
-}
-
-// Actual interner implementation
-
-/// String interner for deduplicating strings
-pub struct StringInterner {
-    // Map of string content to interned string references
-    strings: Mutex<HashMap<String, Arc<String>>>,
-}
-
-impl StringInterner {
-    /// Create a new string interner
-    pub fn new() -> Self {
-        Self {
-            strings: Mutex::new(HashMap::new()),
-        }
-    }
-
-    /// Intern a string, returning a deduplicated reference
-    pub fn intern(&self, s: &str) -> InternedString {
-        let mut strings = self.strings.lock().unwrap();
-
-        if let Some(interned) = strings.get(s) {
-            // String already exists, return existing reference
-            InternedString { inner: Arc::clone(interned) }
-        } else {
-            // String doesn't exist yet, add to the interner
-            let string_arc = Arc::new(s.to_string());
-            strings.insert(s.to_string(), Arc::clone(&string_arc));
-            InternedString { inner: string_arc }
-        }
-    }
-
-    /// Get the number of unique strings in the interner
-    pub fn len(&self) -> usize {
-        self.strings.lock().unwrap().len()
-    }
-
-    /// Check if the interner is empty
-    pub fn is_empty(&self) -> bool {
-        self.strings.lock().unwrap().is_empty()
-    }
-}
-
-// Global string interner
-thread_local! {
-    static STRING_INTERNER: StringInterner = StringInterner::new();
-}
-
-/// Get statistics about the string interner
-pub fn get_interner_stats() -> (usize, usize) {
-    STRING_INTERNER.with(|interner| {
-        let strings = interner.strings.lock().unwrap();
-        let count = strings.len();
-        
-        // Calculate approximate memory usage (key + value)
-        let memory = strings.iter()
-            .map(|(k, v)| k.len() + std::mem::size_of::<Arc<String>>())
-            .sum();
-        
-        (count, memory)
-    })
-}
-
-/// Clear the string interner (mainly for testing)
-#[cfg(test)]
-pub fn clear_interner() {
-    STRING_INTERNER.with(|interner| {
-        let mut strings = interner.strings.lock().unwrap();
-        strings.clear();
-    });
-}
\ No newline at end of file
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::borrow::Borrow;
+
+/// A JavaScript string that's been interned for deduplication
+#[derive(Clone)]
+pub struct InternedString {
+    // Arc allows shared ownership of the string data
+    pub(crate) inner: Arc<String>,
+    // Whether the string came from a large string pool rather than being deduplicated
+    pub(crate) pooled: bool,
+}
+
+impl InternedString {
+    /// Create a new interned string
+    pub fn new(s: &str) -> Self {
+        STRING_INTERNER.intern(s)
+    }
+    
+    /// Create a new interned string from an owned `String`
+    ///
+    /// On a miss the string is moved into the interner without copying its
+    /// contents; on a hit it is simply dropped.
+    pub fn from_owned(s: String) -> Self {
+        STRING_INTERNER.intern_owned(s)
+    }
+    
+    /// Intern UTF-16 code units, such as a string handed over by a JS host
+    ///
+    /// The units are transcoded to UTF-8 first, so the result is the same entry as
+    /// interning the equivalent `&str`. Lone surrogates can't be represented in
+    /// UTF-8 and are each replaced with U+FFFD, so strings differing only in
+    /// unpaired surrogates intern to the same entry.
+    pub fn from_utf16(units: &[u16]) -> Self {
+        Self::from_owned(String::from_utf16_lossy(units))
+    }
+    
+    /// Create a string for a potentially huge value, such as a source file
+    ///
+    /// Strings at or above the large string threshold skip deduplication (see
+    /// `StringInterner::intern_large`); shorter ones are interned as usual.
+    pub fn from_large(s: String) -> Self {
+        STRING_INTERNER.intern_large(s)
+    }
+    
+    /// Whether this string came from the deduplicating table of the global
+    /// interner rather than its large string pool
+    pub fn is_deduplicated(&self) -> bool {
+        !STRING_INTERNER.is_pooled(self)
+    }
+    
+    /// Get the underlying string as a str slice
+    pub fn as_str(&self) -> &str {
+        &self.inner
+    }
+}
+
+// Custom implementations for InternedString
+
+impl PartialEq for InternedString {
+    fn eq(&self, other: &Self) -> bool {
+        // Deduplicated strings are equal exactly when their Arcs are, but pooled
+        // large strings aren't deduplicated, so those fall back to the content
+        Arc::ptr_eq(&self.inner, &other.inner)
+            || (self.pooled || other.pooled) && self.inner == other.inner
+    }
+}
+
+impl Eq for InternedString {}
+
+impl PartialEq<str> for InternedString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for InternedString {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Hash for InternedString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Hash the content, not the address, so maps keyed by interned strings
+        // can be queried with a plain `&str` through `Borrow<str>`
+        self.as_str().hash(state);
+    }
+}
+
+impl fmt::Debug for InternedString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self.inner, f)
+    }
+}
+
+impl fmt::Display for InternedString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self.inner, f)
+    }
+}
+
+impl Deref for InternedString {
+    type Target = str;
+    
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl Borrow<str> for InternedString {
+    fn borrow(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl AsRef<str> for InternedString {
+    fn as_ref(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl From<&str> for InternedString {
+    fn from(s: &str) -> Self {
+        InternedString::new(s)
+    }
+}
+
+impl From<String> for InternedString {
+    fn from(s: String) -> Self {
+        InternedString::from_owned(s)
+    }
+}
+
+// Actual interner implementation
+
+/// Interner entry that hashes and compares by string content
+pub(crate) struct InternEntry(Arc<String>);
+
+impl PartialEq for InternEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for InternEntry {}
+
+impl Hash for InternEntry {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_str().hash(state);
+    }
+}
+
+impl Borrow<str> for InternEntry {
+    fn borrow(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+/// Shard of the interner: the set of strings whose hash maps to it
+pub(crate) type InternShard = HashSet<InternEntry>;
+
+/// Default number of independently locked shards in an interner
+const DEFAULT_SHARD_COUNT: usize = 16;
+
+/// Default size (bytes) from which `intern_large` skips deduplication
+const DEFAULT_LARGE_STRING_THRESHOLD: usize = 64 * 1024;
+
+/// String interner for deduplicating strings
+///
+/// Strings are spread over several shards by content hash, each guarded by its own
+/// lock, so threads interning different strings rarely contend. A given string
+/// always hashes to the same shard, so interning it twice yields the same `Arc`.
+pub struct StringInterner {
+    // Set of interned strings keyed by content, split into shards
+    shards: Vec<Mutex<InternShard>>,
+    // Hasher used to pick a shard for a string
+    hasher: RandomState,
+    // Number of times a shard lock was already held when requested
+    contention: AtomicUsize,
+    // Strings from `intern_large` that bypassed the shards, keyed by address
+    large_strings: Mutex<HashMap<usize, Weak<String>>>,
+    // Size (bytes) from which `intern_large` uses the large string pool
+    large_threshold: AtomicUsize,
+    // Interns that found the string already present
+    hits: AtomicU64,
+    // Interns that had to insert a new string
+    misses: AtomicU64,
+}
+
+impl StringInterner {
+    /// Create a new string interner
+    pub fn new() -> Self {
+        Self::with_shards(DEFAULT_SHARD_COUNT)
+    }
+    
+    /// Create a new string interner with the given number of shards
+    pub fn with_shards(shard_count: usize) -> Self {
+        Self {
+            shards: (0..shard_count.max(1)).map(|_| Mutex::new(HashSet::new())).collect(),
+            hasher: RandomState::new(),
+            contention: AtomicUsize::new(0),
+            large_strings: Mutex::new(HashMap::new()),
+            large_threshold: AtomicUsize::new(DEFAULT_LARGE_STRING_THRESHOLD),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+    
+    /// Get the index of the shard responsible for a string
+    pub(crate) fn shard_index(&self, s: &str) -> usize {
+        (self.hasher.hash_one(s) as usize) % self.shards.len()
+    }
+    
+    /// Lock the shard responsible for a string
+    pub(crate) fn shard(&self, s: &str) -> MutexGuard<'_, InternShard> {
+        let shard = &self.shards[self.shard_index(s)];
+        match shard.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => {
+                self.contention.fetch_add(1, Ordering::Relaxed);
+                shard.lock().unwrap()
+            }
+        }
+    }
+
+    /// Intern a string, returning a deduplicated reference
+    pub fn intern(&self, s: &str) -> InternedString {
+        let mut strings = self.shard(s);
+
+        if let Some(interned) = strings.get(s) {
+            // String already exists, return existing reference
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            InternedString { inner: Arc::clone(&interned.0), pooled: false }
+        } else {
+            // String doesn't exist yet, add to the interner
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            let string_arc = Arc::new(s.to_string());
+            strings.insert(InternEntry(Arc::clone(&string_arc)));
+            InternedString { inner: string_arc, pooled: false }
+        }
+    }
+    
+    /// Intern an owned string, moving it into the interner on a miss
+    pub fn intern_owned(&self, s: String) -> InternedString {
+        let mut strings = self.shard(&s);
+
+        if let Some(interned) = strings.get(s.as_str()) {
+            // String already exists; the owned copy is dropped
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            InternedString { inner: Arc::clone(&interned.0), pooled: false }
+        } else {
+            // Reuse the caller's allocation as the interned storage
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            let string_arc = Arc::new(s);
+            strings.insert(InternEntry(Arc::clone(&string_arc)));
+            InternedString { inner: string_arc, pooled: false }
+        }
+    }
+
+    /// Store a string, skipping deduplication if it's at least the large string threshold
+    ///
+    /// Hashing and comparing a multi-megabyte string on every intern costs far
+    /// more than duplicates would save, so large strings get their own storage in
+    /// a separate pool. Two large strings with the same content are separate
+    /// allocations, but they still compare and hash equal, since equality falls
+    /// back to the content when either side is pooled.
+    pub fn intern_large(&self, s: String) -> InternedString {
+        if s.len() < self.large_string_threshold() {
+            return self.intern_owned(s);
+        }
+        
+        let string_arc = Arc::new(s);
+        let mut pool = self.large_strings.lock().unwrap();
+        // Forget freed strings, releasing their allocations
+        pool.retain(|_, weak| weak.strong_count() > 0);
+        pool.insert(Arc::as_ptr(&string_arc) as usize, Arc::downgrade(&string_arc));
+        InternedString { inner: string_arc, pooled: true }
+    }
+    
+    /// Whether `s` was stored in this interner's large string pool
+    pub fn is_pooled(&self, s: &InternedString) -> bool {
+        let pool = self.large_strings.lock().unwrap();
+        // A pooled entry's allocation outlives the string, so its address can't be reused
+        pool.contains_key(&(Arc::as_ptr(&s.inner) as usize))
+    }
+    
+    /// Get the size (bytes) from which `intern_large` skips deduplication
+    pub fn large_string_threshold(&self) -> usize {
+        self.large_threshold.load(Ordering::Relaxed)
+    }
+    
+    /// Set the size (bytes) from which `intern_large` skips deduplication
+    pub fn set_large_string_threshold(&self, bytes: usize) {
+        self.large_threshold.store(bytes, Ordering::Relaxed);
+    }
+    
+    /// Get the number of live large strings and their total size in bytes
+    pub fn large_string_stats(&self) -> (usize, usize) {
+        let pool = self.large_strings.lock().unwrap();
+        pool.values()
+            .filter_map(Weak::upgrade)
+            .fold((0, 0), |(count, bytes), s| (count + 1, bytes + s.len()))
+    }
+    
+    /// Get the number of unique strings in the interner
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().unwrap().len()).sum()
+    }
+
+    /// Check if the interner is empty
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.lock().unwrap().is_empty())
+    }
+    
+    /// Get the number of shards
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+    
+    /// Get how many times interning had to wait for a shard lock held by another thread
+    pub fn contention_count(&self) -> usize {
+        self.contention.load(Ordering::Relaxed)
+    }
+    
+    /// Get how many interns found their string already present (hits) and how
+    /// many inserted a new one (misses), since the interner was created
+    ///
+    /// A low hit ratio means interning costs a hash and lock per string without
+    /// saving any memory.
+    pub fn hit_miss_stats(&self) -> (u64, u64) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+    
+    /// Get the number of unique strings and their approximate memory usage
+    pub fn stats(&self) -> (usize, usize) {
+        self.shards.iter().fold((0, 0), |(count, memory), shard| {
+            let strings = shard.lock().unwrap();
+            
+            // Calculate approximate memory usage (key + value)
+            let shard_memory: usize = strings.iter()
+                .map(|entry| entry.0.len() + std::mem::size_of::<Arc<String>>())
+                .sum();
+            
+            (count + strings.len(), memory + shard_memory)
+        })
+    }
+    
+    /// Get every interned string, sorted, for saving alongside a heap snapshot
+    pub fn export_table(&self) -> Vec<String> {
+        let mut strings: Vec<String> = self.shards.iter()
+            .flat_map(|shard| {
+                shard.lock().unwrap().iter().map(|entry| entry.0.to_string()).collect::<Vec<_>>()
+            })
+            .collect();
+        strings.sort_unstable();
+        strings
+    }
+    
+    /// Intern every string in a table produced by `export_table`
+    ///
+    /// Strings that are already interned keep their existing storage.
+    pub fn import_table(&self, strings: Vec<String>) {
+        for s in strings {
+            self.intern_owned(s);
+        }
+    }
+    
+    /// Drop strings nothing outside the interner refers to, returning how many
+    ///
+    /// Handles are only handed out under the shard lock, so a string the table
+    /// alone holds can't be picked up while it is removed. Interning the same
+    /// text later simply allocates it again.
+    pub fn sweep_unused(&self) -> usize {
+        self.shards.iter()
+            .map(|shard| {
+                let mut strings = shard.lock().unwrap();
+                let before = strings.len();
+                strings.retain(|entry| Arc::strong_count(&entry.0) > 1);
+                before - strings.len()
+            })
+            .sum()
+    }
+    
+    /// Remove every interned string
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            shard.lock().unwrap().clear();
+        }
+    }
+}
+
+impl Default for StringInterner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Global string interner shared by all threads
+static STRING_INTERNER: Lazy<StringInterner> = Lazy::new(StringInterner::new);
+
+/// Get every string in the global interner, sorted
+pub fn export_table() -> Vec<String> {
+    STRING_INTERNER.export_table()
+}
+
+/// Re-intern a table produced by `export_table` into the global interner
+///
+/// When restoring a heap snapshot, import the table before deserializing any
+/// objects, so their keys and string values resolve to the restored entries.
+pub fn import_table(strings: Vec<String>) {
+    STRING_INTERNER.import_table(strings);
+}
+
+/// Intern a batch of names up front, returning their handles in order
+///
+/// Hot paths can keep the handles and compare against them by pointer.
+pub fn prepopulate(names: &[&str]) -> Vec<InternedString> {
+    names.iter().map(|name| InternedString::new(name)).collect()
+}
+
+/// Property names the compiler and runtime look up constantly
+pub struct WellKnownNames {
+    pub prototype: InternedString,
+    pub constructor: InternedString,
+    pub length: InternedString,
+    pub name: InternedString,
+    pub message: InternedString,
+    pub to_string: InternedString,
+    pub value_of: InternedString,
+}
+
+/// Well-known names, interned once on first use
+pub static WELL_KNOWN: Lazy<WellKnownNames> = Lazy::new(|| {
+    let mut names = prepopulate(&[
+        "prototype",
+        "constructor",
+        "length",
+        "name",
+        "message",
+        "toString",
+        "valueOf",
+    ])
+    .into_iter();
+    let mut next = || names.next().unwrap();
+    
+    WellKnownNames {
+        prototype: next(),
+        constructor: next(),
+        length: next(),
+        name: next(),
+        message: next(),
+        to_string: next(),
+        value_of: next(),
+    }
+});
+
+/// Get statistics about the string interner
+pub fn get_interner_stats() -> (usize, usize) {
+    STRING_INTERNER.stats()
+}
+
+/// Get the global interner's cumulative hit and miss counts
+pub fn hit_miss_stats() -> (u64, u64) {
+    STRING_INTERNER.hit_miss_stats()
+}
+
+/// Drop strings in the global interner that nothing refers to anymore
+pub fn sweep_unused_strings() -> usize {
+    STRING_INTERNER.sweep_unused()
+}
+
+/// Clear the string interner (mainly for testing)
+#[cfg(test)]
+#[allow(dead_code)]
+pub fn clear_interner() {
+    STRING_INTERNER.clear();
+}